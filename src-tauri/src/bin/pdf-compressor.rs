@@ -0,0 +1,681 @@
+// 命令行版本的 PDF 压缩工具，与桌面应用共用同一套压缩引擎
+//
+// 退出码：
+//   0 - 全部成功且文件变小
+//   1 - 全部失败或参数无法处理
+//   2 - 命令行参数错误（由 clap 返回）
+//   3 - 全部成功但没有任何文件变小
+//   4 - 批量处理中部分文件失败
+//
+// 使用 --analyze 时只分析文件占用的空间，不压缩，全部成功时返回 0
+
+use clap::{Parser, ValueEnum};
+use pdf_compressor_lib::engine::{
+    self, format_file_size, CancelToken, ColorMode, CompressionJob, CompressionOptions, CompressionProfile,
+    EncryptionMethod, EncryptionSettings, FormMode, PageSelection, PdfaConformance, Permissions, SizeAnalysis,
+    SizeCategory, AUTO_ENGINE, DEFAULT_TOP_OBJECTS,
+};
+use pdf_compressor_lib::error::ErrorCode;
+use pdf_compressor_lib::i18n::{self, Locale, Text};
+use pdf_compressor_lib::tr;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const EXIT_SUCCESS: u8 = 0;
+const EXIT_FAILURE: u8 = 1;
+const EXIT_NO_GAIN: u8 = 3;
+const EXIT_PARTIAL: u8 = 4;
+
+#[derive(Parser, Debug)]
+#[command(name = "pdf-compressor", version, about = "压缩 PDF 文件")]
+struct Cli {
+    /// 输入文件，支持 glob 通配符（例如 "reports/**/*.pdf"）
+    #[arg(required = true)]
+    inputs: Vec<String>,
+
+    /// 输出文件；多个输入时为输出目录。未指定时在原文件旁生成 *_compressed.pdf
+    #[arg(short, long, conflicts_with = "in_place")]
+    output: Option<PathBuf>,
+
+    /// 原地压缩，用压缩结果替换输入文件，原文件备份为 *.bak
+    #[arg(long)]
+    in_place: bool,
+
+    /// 原地压缩时不保留 *.bak 备份
+    #[arg(long, requires = "in_place")]
+    no_backup: bool,
+
+    /// 压缩等级
+    #[arg(short, long, value_enum, default_value_t = Level::Ebook)]
+    level: Level,
+
+    /// 从 JSON 文件读取自定义压缩配置，指定后忽略 --level
+    #[arg(short, long)]
+    profile: Option<PathBuf>,
+
+    /// 颜色模式：leave、rgb、gray 或 bilevel（扫描件二值化）；覆盖压缩等级或配置中的设置
+    #[arg(long, value_enum, value_name = "MODE")]
+    color: Option<Color>,
+
+    /// 表单域处理方式：keep、flatten（平展到页面内容）或 remove；覆盖压缩等级或配置中的设置
+    #[arg(long, value_enum, value_name = "MODE")]
+    forms: Option<Forms>,
+
+    /// 删除批注类注释（文字批注、高亮、图章等）
+    #[arg(long)]
+    remove_comments: bool,
+
+    /// 转换为 PDF/A：1b、2b 或 3b，需要 Ghostscript 引擎；覆盖压缩等级或配置中的设置
+    #[arg(long, value_enum, value_name = "LEVEL", conflicts_with_all = ["encrypt", "reencrypt"])]
+    pdfa: Option<Pdfa>,
+
+    /// 只保留选中的页面，例如 1-5,8,10-（10- 表示从第 10 页到最后一页）
+    #[arg(long, value_name = "RANGES", value_parser = parse_pages)]
+    pages: Option<String>,
+
+    /// 移除 JavaScript、附件、多媒体和隐藏图层
+    #[arg(long)]
+    sanitize: bool,
+
+    /// 压缩引擎：auto、ghostscript 或 lopdf
+    #[arg(short, long, default_value = AUTO_ENGINE)]
+    engine: String,
+
+    /// 目标文件大小，例如 10MB、500KB 或字节数；指定后自动搜索图像质量
+    #[arg(short, long, value_parser = parse_size)]
+    target_size: Option<u64>,
+
+    /// Ghostscript 的最长运行时间（秒）
+    #[arg(long, value_name = "SECS")]
+    timeout: Option<u64>,
+
+    /// 不使用对象流保存（lopdf 引擎）
+    #[arg(long)]
+    no_object_streams: bool,
+
+    /// 不合并重复对象（lopdf 引擎）
+    #[arg(long)]
+    no_dedup: bool,
+
+    /// 压缩结果比原文件大时仍保留压缩结果（默认改为复制原文件）
+    #[arg(long)]
+    allow_larger: bool,
+
+    /// 加密 PDF 的用户密码或所有者密码
+    #[arg(long)]
+    password: Option<String>,
+
+    /// 用原文件的密码和权限重新加密输出，默认输出不加密
+    #[arg(long)]
+    reencrypt: bool,
+
+    /// 用新的密码和权限加密输出：aes128 或 aes256
+    #[arg(long, value_enum, value_name = "METHOD", conflicts_with = "reencrypt")]
+    encrypt: Option<Encryption>,
+
+    /// 打开输出文件的密码，未指定时不需要密码即可打开
+    #[arg(long, requires = "encrypt")]
+    user_password: Option<String>,
+
+    /// 解除权限限制的密码，未指定时随机生成
+    #[arg(long, requires = "encrypt")]
+    owner_password: Option<String>,
+
+    /// 禁止打印输出文件
+    #[arg(long, requires = "encrypt")]
+    no_print: bool,
+
+    /// 禁止复制输出文件中的文本和图像
+    #[arg(long, requires = "encrypt")]
+    no_copy: bool,
+
+    /// 禁止修改输出文件
+    #[arg(long, requires = "encrypt")]
+    no_modify: bool,
+
+    /// 禁止在输出文件中添加注释和填写表单
+    #[arg(long, requires = "encrypt")]
+    no_annotate: bool,
+
+    /// 只分析文件中图像、字体、内容流等各部分占用的空间，不压缩
+    #[arg(long, conflicts_with_all = ["output", "in_place"])]
+    analyze: bool,
+
+    /// 分析时列出的最大对象数量
+    #[arg(long, value_name = "N", requires = "analyze", default_value_t = DEFAULT_TOP_OBJECTS)]
+    top: usize,
+
+    /// 以 JSON 格式输出结果摘要
+    #[arg(long)]
+    json: bool,
+
+    /// 消息语言：en 或 zh，未指定时根据 LANG 等环境变量确定
+    #[arg(long, value_parser = parse_locale)]
+    lang: Option<Locale>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Level {
+    Screen,
+    Ebook,
+    Printer,
+    Prepress,
+    /// 长期归档，转换为 PDF/A-2b
+    Archive,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Color {
+    Leave,
+    Rgb,
+    Gray,
+    Bilevel,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Forms {
+    Keep,
+    Flatten,
+    Remove,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Pdfa {
+    #[value(name = "1b")]
+    A1b,
+    #[value(name = "2b")]
+    A2b,
+    #[value(name = "3b")]
+    A3b,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Encryption {
+    Aes128,
+    Aes256,
+}
+
+impl Level {
+    fn profile(self) -> CompressionProfile {
+        match self {
+            Level::Screen => CompressionProfile::screen(),
+            Level::Ebook => CompressionProfile::ebook(),
+            Level::Printer => CompressionProfile::printer(),
+            Level::Prepress => CompressionProfile::prepress(),
+            Level::Archive => CompressionProfile::archive(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct FileSummary {
+    input: PathBuf,
+    output: PathBuf,
+    success: bool,
+    original_size: Option<u64>,
+    compressed_size: Option<u64>,
+    message: String,
+    /// 失败时的错误码
+    error_code: Option<ErrorCode>,
+    /// 实际使用的引擎，未开始压缩时为 None
+    engine: Option<String>,
+    duration_ms: Option<u64>,
+    warnings: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct Summary {
+    files: Vec<FileSummary>,
+    succeeded: usize,
+    failed: usize,
+    no_gain: usize,
+}
+
+#[derive(Serialize, Debug)]
+struct FileAnalysis {
+    input: PathBuf,
+    success: bool,
+    analysis: Option<SizeAnalysis>,
+    /// 失败时的错误信息和错误码
+    message: Option<String>,
+    error_code: Option<ErrorCode>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if let Some(locale) = cli.lang {
+        i18n::set_locale(locale);
+    }
+
+    let inputs = match expand_inputs(&cli.inputs) {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+    if cli.analyze {
+        return ExitCode::from(analyze_inputs(&cli, &inputs).await);
+    }
+
+    let mut profile = match &cli.profile {
+        Some(path) => match load_profile(path) {
+            Ok(profile) => profile,
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::from(EXIT_FAILURE);
+            }
+        },
+        None => cli.level.profile(),
+    };
+    if let Some(color) = cli.color {
+        profile.color_mode = match color {
+            Color::Leave => ColorMode::Leave,
+            Color::Rgb => ColorMode::Rgb,
+            Color::Gray => ColorMode::Gray,
+            Color::Bilevel => ColorMode::Bilevel,
+        };
+    }
+    if let Some(forms) = cli.forms {
+        profile.form_mode = match forms {
+            Forms::Keep => FormMode::Keep,
+            Forms::Flatten => FormMode::Flatten,
+            Forms::Remove => FormMode::Remove,
+        };
+    }
+    if cli.remove_comments {
+        profile.remove_comments = true;
+    }
+    if let Some(pdfa) = cli.pdfa {
+        profile.pdfa = Some(match pdfa {
+            Pdfa::A1b => PdfaConformance::Pdfa1b,
+            Pdfa::A2b => PdfaConformance::Pdfa2b,
+            Pdfa::A3b => PdfaConformance::Pdfa3b,
+        });
+    }
+
+    let mut options = CompressionOptions {
+        object_streams: !cli.no_object_streams,
+        deduplicate: !cli.no_dedup,
+        target_size: cli.target_size,
+        keep_original_if_larger: !cli.allow_larger,
+        backup_in_place: !cli.no_backup,
+        password: cli.password.clone(),
+        reencrypt: cli.reencrypt,
+        pages: cli.pages.clone(),
+        sanitize: cli.sanitize,
+        encryption: cli.encrypt.map(|method| EncryptionSettings {
+            method: match method {
+                Encryption::Aes128 => EncryptionMethod::Aes128,
+                Encryption::Aes256 => EncryptionMethod::Aes256,
+            },
+            user_password: cli.user_password.clone().unwrap_or_default(),
+            owner_password: cli.owner_password.clone(),
+            permissions: Permissions {
+                print: !cli.no_print,
+                modify: !cli.no_modify,
+                copy: !cli.no_copy,
+                annotate: !cli.no_annotate,
+            },
+        }),
+        ..CompressionOptions::default()
+    };
+    if cli.timeout.is_some() {
+        options.process_limits.timeout_secs = cli.timeout;
+    }
+
+    // Ctrl+C 时结束当前的压缩进程并删除部分输出
+    let cancel = CancelToken::new();
+    let ctrl_c = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            ctrl_c.cancel();
+        }
+    });
+
+    let mut files = Vec::with_capacity(inputs.len());
+    let mut used_outputs = HashSet::new();
+    for input in &inputs {
+        if cancel.is_cancelled() {
+            break;
+        }
+
+        let output = match output_path_for(input, cli.output.as_deref(), inputs.len() > 1, cli.in_place) {
+            Ok(output) => output,
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::from(EXIT_FAILURE);
+            }
+        };
+
+        // 不同目录下的同名文件会写到同一个输出路径，输出与输入相同则会破坏原文件
+        let conflict = if output == *input && !cli.in_place {
+            Some(tr!(Text::OutputSameAsInput))
+        } else if !used_outputs.insert(output.clone()) {
+            Some(tr!(Text::OutputConflict))
+        } else {
+            None
+        };
+        if let Some(reason) = conflict {
+            report_file(&cli, &mut files, FileSummary {
+                input: input.clone(),
+                output,
+                success: false,
+                original_size: file_size(input),
+                compressed_size: None,
+                message: reason,
+                error_code: Some(ErrorCode::OutputWriteFailed),
+                engine: None,
+                duration_ms: None,
+                warnings: Vec::new(),
+            });
+            continue;
+        }
+
+        let job = CompressionJob {
+            input_path: input.clone(),
+            output_path: output.clone(),
+            profile: profile.clone(),
+            options: options.clone(),
+            progress: None,
+            cancel: cancel.clone(),
+        };
+
+        let summary = match engine::compress_file(&job, &cli.engine).await {
+            Ok(result) => FileSummary {
+                input: input.clone(),
+                output,
+                success: result.success,
+                original_size: Some(result.original_size),
+                compressed_size: result.compressed_size,
+                message: result.message,
+                error_code: (!result.success).then_some(ErrorCode::Cancelled),
+                engine: Some(result.engine),
+                duration_ms: Some(result.duration_ms),
+                warnings: result.warnings,
+            },
+            Err(e) => FileSummary {
+                input: input.clone(),
+                output,
+                success: false,
+                original_size: file_size(input),
+                compressed_size: None,
+                message: e.to_string(),
+                error_code: Some(e.code),
+                engine: None,
+                duration_ms: None,
+                warnings: Vec::new(),
+            },
+        };
+
+        report_file(&cli, &mut files, summary);
+    }
+
+    let succeeded = files.iter().filter(|file| file.success).count();
+    let no_gain = files
+        .iter()
+        .filter(|file| file.success)
+        .filter(|file| matches!((file.original_size, file.compressed_size), (Some(original), Some(compressed)) if compressed >= original))
+        .count();
+    let summary = Summary {
+        failed: files.len() - succeeded,
+        files,
+        succeeded,
+        no_gain,
+    };
+
+    if cli.json {
+        match serde_json::to_string_pretty(&summary) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("{}", tr!(Text::JsonSummaryFailed, e)),
+        }
+    }
+
+    ExitCode::from(exit_code(&summary))
+}
+
+fn report_file(cli: &Cli, files: &mut Vec<FileSummary>, summary: FileSummary) {
+    if !cli.json {
+        let status = if summary.success { "OK  " } else { "FAIL" };
+        println!("[{}] {} -> {}: {}", status, summary.input.display(), summary.output.display(), summary.message);
+        for warning in &summary.warnings {
+            println!("       {}", tr!(Text::Warning, warning));
+        }
+    }
+    files.push(summary);
+}
+
+/// 分析每个输入文件并输出结果，返回退出码
+async fn analyze_inputs(cli: &Cli, inputs: &[PathBuf]) -> u8 {
+    let mut files = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let file = match engine::analyze_file(input, cli.password.as_deref(), cli.top).await {
+            Ok(analysis) => FileAnalysis {
+                input: input.clone(),
+                success: true,
+                analysis: Some(analysis),
+                message: None,
+                error_code: None,
+            },
+            Err(e) => FileAnalysis {
+                input: input.clone(),
+                success: false,
+                analysis: None,
+                message: Some(e.to_string()),
+                error_code: Some(e.code),
+            },
+        };
+        if !cli.json {
+            print_analysis(&file, cli.top);
+        }
+        files.push(file);
+    }
+
+    if cli.json {
+        match serde_json::to_string_pretty(&files) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("{}", tr!(Text::JsonSummaryFailed, e)),
+        }
+    }
+
+    match files.iter().filter(|file| file.success).count() {
+        0 => EXIT_FAILURE,
+        succeeded if succeeded < files.len() => EXIT_PARTIAL,
+        _ => EXIT_SUCCESS,
+    }
+}
+
+/// 输出分析结果，图像和最大的对象各列出前 `top` 个
+fn print_analysis(file: &FileAnalysis, top: usize) {
+    let Some(analysis) = &file.analysis else {
+        println!("[FAIL] {}: {}", file.input.display(), file.message.as_deref().unwrap_or_default());
+        return;
+    };
+
+    println!(
+        "{}",
+        tr!(
+            Text::AnalysisSummary,
+            file.input.display(),
+            format_file_size(analysis.file_size),
+            analysis.version,
+            analysis.page_count,
+            analysis.object_count
+        )
+    );
+    for category in &analysis.categories {
+        let share = category.bytes as f64 * 100.0 / analysis.file_size.max(1) as f64;
+        println!(
+            "  {:<20} {:>10} {:>6.1}%  {}",
+            category_label(category.category),
+            format_file_size(category.bytes),
+            share,
+            tr!(Text::CategoryObjects, category.objects)
+        );
+    }
+
+    if !analysis.images.is_empty() {
+        println!("  {}", tr!(Text::AnalysisImages, analysis.images.len()));
+        for image in analysis.images.iter().take(top) {
+            let mut details = vec![format!("{}x{}", image.width, image.height)];
+            details.extend(image.bits_per_component.map(|bits| format!("{} bpc", bits)));
+            details.extend(image.color_space.clone());
+            details.extend(image.filters.iter().cloned());
+            details.extend(image.effective_dpi.map(|dpi| format!("{} DPI", dpi)));
+            if image.mask {
+                details.push(tr!(Text::ImageMask));
+            }
+            println!(
+                "    {:>6} {} R  {:>10}  {}",
+                image.id.0,
+                image.id.1,
+                format_file_size(image.bytes),
+                details.join(", ")
+            );
+        }
+    }
+
+    if !analysis.fonts.is_empty() {
+        println!("  {}", tr!(Text::AnalysisFonts, analysis.fonts.len()));
+        for font in &analysis.fonts {
+            let embedding = match (font.embedded, font.subset) {
+                (true, true) => tr!(Text::FontSubset),
+                (true, false) => tr!(Text::FontEmbedded),
+                (false, _) => tr!(Text::FontNotEmbedded),
+            };
+            println!(
+                "    {:>6} {} R  {:>10}  {} ({}, {})",
+                font.id.0,
+                font.id.1,
+                format_file_size(font.bytes),
+                font.name,
+                font.subtype,
+                embedding
+            );
+        }
+    }
+
+    if !analysis.largest_objects.is_empty() {
+        println!("  {}", tr!(Text::AnalysisLargestObjects));
+        for object in &analysis.largest_objects {
+            println!(
+                "    {:>6} {} R  {:>10}  {} {}",
+                object.id.0,
+                object.id.1,
+                format_file_size(object.bytes),
+                category_label(object.category),
+                object.kind.as_deref().unwrap_or_default()
+            );
+        }
+    }
+}
+
+fn category_label(category: SizeCategory) -> String {
+    tr!(match category {
+        SizeCategory::Images => Text::CategoryImages,
+        SizeCategory::Fonts => Text::CategoryFonts,
+        SizeCategory::ContentStreams => Text::CategoryContentStreams,
+        SizeCategory::Metadata => Text::CategoryMetadata,
+        SizeCategory::Attachments => Text::CategoryAttachments,
+        SizeCategory::Structure => Text::CategoryStructure,
+    })
+}
+
+fn exit_code(summary: &Summary) -> u8 {
+    if summary.succeeded == 0 {
+        EXIT_FAILURE
+    } else if summary.failed > 0 {
+        EXIT_PARTIAL
+    } else if summary.no_gain == summary.succeeded {
+        EXIT_NO_GAIN
+    } else {
+        EXIT_SUCCESS
+    }
+}
+
+/// 展开 glob 通配符，Windows 的命令行不会自动展开
+fn expand_inputs(patterns: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut inputs = Vec::new();
+
+    for pattern in patterns {
+        if Path::new(pattern).exists() {
+            inputs.push(PathBuf::from(pattern));
+            continue;
+        }
+
+        let paths = glob::glob(pattern).map_err(|e| tr!(Text::InvalidGlob, pattern, e))?;
+        let before = inputs.len();
+        inputs.extend(paths.flatten().filter(|path| path.is_file()));
+
+        if inputs.len() == before {
+            return Err(tr!(Text::NoMatchingFiles, pattern));
+        }
+    }
+
+    Ok(inputs)
+}
+
+fn output_path_for(input: &Path, output: Option<&Path>, batch: bool, in_place: bool) -> Result<PathBuf, String> {
+    let file_name = input
+        .file_name()
+        .ok_or_else(|| tr!(Text::InvalidInputPath, input.display()))?;
+
+    if in_place {
+        return Ok(input.to_path_buf());
+    }
+
+    match output {
+        // 批量处理或输出是已存在的目录时，按原文件名写入该目录
+        Some(dir) if batch || dir.is_dir() => Ok(dir.join(file_name)),
+        Some(file) => Ok(file.to_path_buf()),
+        None => {
+            let stem = input.file_stem().unwrap_or(file_name).to_string_lossy();
+            Ok(input.with_file_name(format!("{}_compressed.pdf", stem)))
+        }
+    }
+}
+
+/// 读取 JSON 格式的压缩配置，未指定的字段使用 ebook 预设的值
+fn load_profile(path: &Path) -> Result<CompressionProfile, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| tr!(Text::CannotReadProfile, path.display(), e))?;
+    let profile: CompressionProfile =
+        serde_json::from_str(&content).map_err(|e| tr!(Text::InvalidProfileFile, path.display(), e))?;
+    profile.validate().map_err(|e| e.to_string())?;
+    Ok(profile)
+}
+
+fn file_size(path: &Path) -> Option<u64> {
+    std::fs::metadata(path).map(|metadata| metadata.len()).ok()
+}
+
+/// 解析带 KB/MB/GB 单位的文件大小，单位按 1024 进制计算
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number.parse().map_err(|_| tr!(Text::InvalidSize, value))?;
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1u64,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return Err(tr!(Text::UnknownSizeUnit, unit)),
+    };
+
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// 检查页面选择表达式，返回规范化后的表达式
+fn parse_pages(value: &str) -> Result<String, String> {
+    PageSelection::parse(value).map(|selection| selection.to_string())
+}
+
+fn parse_locale(value: &str) -> Result<Locale, String> {
+    Locale::from_tag(value).ok_or_else(|| tr!(Text::UnknownLocale, value))
+}
@@ -0,0 +1,191 @@
+// 批量压缩
+//
+// 输入可以是文件或目录，目录会被递归扫描其中的 PDF 文件。输出统一写入一个输出根目录：
+// 单个文件直接放在根目录下，目录中的文件保留原有的目录结构（以该目录名为第一级）。
+
+use super::output::same_file;
+use super::{
+    compress_file, CancelToken, CompressionJob, CompressionOptions, CompressionProfile, JobStatus, ProgressReporter,
+};
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Text;
+use crate::tr;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// 一个待压缩文件及其在输出根目录下的相对路径
+#[derive(Debug, Clone)]
+pub struct BatchEntry {
+    pub input_path: PathBuf,
+    pub relative_path: PathBuf,
+}
+
+/// 批量压缩中单个文件的结果
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchFileResult {
+    pub input_path: PathBuf,
+    pub output_path: PathBuf,
+    pub success: bool,
+    pub status: JobStatus,
+    pub message: String,
+    /// 失败或取消时的错误码
+    pub error_code: Option<ErrorCode>,
+    pub original_size: Option<u64>,
+    pub compressed_size: Option<u64>,
+}
+
+/// 展开输入列表中的文件和目录，返回所有需要压缩的 PDF 文件
+///
+/// `exclude` 下的文件会被跳过，用于避免输出目录位于输入目录中时重复压缩已输出的文件。
+pub fn discover_pdfs(inputs: &[PathBuf], exclude: Option<&Path>) -> Result<Vec<BatchEntry>, AppError> {
+    let exclude = exclude.and_then(|path| path.canonicalize().ok());
+    let mut entries = Vec::new();
+
+    for input in inputs {
+        if input.is_file() {
+            let file_name = input
+                .file_name()
+                .ok_or_else(|| AppError::with_details(ErrorCode::InputMissing, input.display()))?;
+            entries.push(BatchEntry {
+                input_path: input.clone(),
+                relative_path: PathBuf::from(file_name),
+            });
+        } else if input.is_dir() {
+            // 以目录名作为第一级，避免多个输入目录中的同名文件相互覆盖
+            let root_name = input
+                .canonicalize()
+                .ok()
+                .and_then(|path| path.file_name().map(PathBuf::from))
+                .unwrap_or_default();
+
+            let walker = WalkDir::new(input)
+                .follow_links(false)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_entry(|entry| !is_excluded(entry.path(), exclude.as_deref()));
+
+            for entry in walker {
+                let entry = entry.map_err(|e| {
+                    AppError::with_details(ErrorCode::InputUnreadable, tr!(Text::CannotReadDirectory, input.display(), e))
+                })?;
+                if !entry.file_type().is_file() || !is_pdf(entry.path()) {
+                    continue;
+                }
+
+                let relative = entry.path().strip_prefix(input).unwrap_or(entry.path());
+                entries.push(BatchEntry {
+                    input_path: entry.path().to_path_buf(),
+                    relative_path: root_name.join(relative),
+                });
+            }
+        } else {
+            return Err(AppError::with_details(ErrorCode::InputMissing, input.display()));
+        }
+    }
+
+    Ok(entries)
+}
+
+/// 依次压缩所有输入，单个文件失败不会中断整个批次
+///
+/// 取消后正在处理的文件和尚未开始的文件都会被标记为已取消。
+pub async fn compress_batch(
+    inputs: &[PathBuf],
+    output_root: &Path,
+    profile: &CompressionProfile,
+    engine: &str,
+    options: &CompressionOptions,
+    progress: Option<&ProgressReporter>,
+    cancel: &CancelToken,
+) -> Result<Vec<BatchFileResult>, AppError> {
+    let entries = discover_pdfs(inputs, Some(output_root))?;
+    if entries.is_empty() {
+        return Err(AppError::new(ErrorCode::NoPdfFound));
+    }
+
+    let mut results = Vec::with_capacity(entries.len());
+    let mut used_outputs = HashSet::new();
+
+    for entry in entries {
+        let output_path = output_root.join(&entry.relative_path);
+        let original_size = file_size(&entry.input_path);
+
+        // 输出与输入相同会破坏原文件，重复的输出路径会覆盖之前的结果
+        let conflict = if cancel.is_cancelled() {
+            Some((JobStatus::Cancelled, AppError::new(ErrorCode::Cancelled)))
+        } else if same_file(&entry.input_path, &output_path) {
+            Some((JobStatus::Failed, AppError::with_details(ErrorCode::OutputWriteFailed, tr!(Text::OutputSameAsInput))))
+        } else if !used_outputs.insert(output_path.clone()) {
+            Some((JobStatus::Failed, AppError::with_details(ErrorCode::OutputWriteFailed, tr!(Text::OutputConflict))))
+        } else {
+            None
+        };
+        if let Some((status, error)) = conflict {
+            results.push(BatchFileResult {
+                input_path: entry.input_path,
+                output_path,
+                success: false,
+                status,
+                message: error.to_string(),
+                error_code: Some(error.code),
+                original_size,
+                compressed_size: None,
+            });
+            continue;
+        }
+
+        let job = CompressionJob {
+            input_path: entry.input_path.clone(),
+            output_path: output_path.clone(),
+            profile: profile.clone(),
+            options: options.clone(),
+            progress: progress.map(ProgressReporter::restarted),
+            cancel: cancel.clone(),
+        };
+
+        let result = match compress_file(&job, engine).await {
+            Ok(result) => BatchFileResult {
+                success: result.success,
+                status: result.status,
+                message: result.message,
+                error_code: (result.status == JobStatus::Cancelled).then_some(ErrorCode::Cancelled),
+                compressed_size: result.compressed_size,
+                input_path: entry.input_path,
+                output_path,
+                original_size,
+            },
+            Err(e) => BatchFileResult {
+                input_path: entry.input_path,
+                output_path,
+                success: false,
+                status: JobStatus::Failed,
+                message: e.to_string(),
+                error_code: Some(e.code),
+                original_size,
+                compressed_size: None,
+            },
+        };
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.eq_ignore_ascii_case("pdf"))
+        .unwrap_or(false)
+}
+
+fn is_excluded(path: &Path, exclude: Option<&Path>) -> bool {
+    match (exclude, path.canonicalize()) {
+        (Some(exclude), Ok(path)) => path == exclude,
+        _ => false,
+    }
+}
+
+fn file_size(path: &Path) -> Option<u64> {
+    std::fs::metadata(path).map(|metadata| metadata.len()).ok()
+}
@@ -0,0 +1,50 @@
+// 取消压缩任务
+//
+// Ghostscript 引擎在收到取消信号后结束子进程，lopdf 引擎在处理每个图像对象和
+// 每个处理阶段之间检查取消标记，被取消的引擎返回 `ErrorCode::Cancelled`。
+// 部分输出文件由 `compress_file` 统一清理。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+#[derive(Default, Debug)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+/// 可以在多个线程之间共享的取消标记
+#[derive(Clone, Default, Debug)]
+pub struct CancelToken {
+    state: Arc<CancelState>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        self.state.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// 等待直到任务被取消
+    pub async fn cancelled(&self) {
+        loop {
+            // 先注册等待再检查标记，避免在两者之间发生的取消被错过
+            let notified = self.state.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
@@ -0,0 +1,1248 @@
+// 加密 PDF 的处理
+//
+// lopdf 只能用用户密码解密 RC4 加密的文档，并且加载时会因为无法解析加密的对象流而丢弃其中的对象。
+// 这里实现了标准安全处理器（RC4 和 AES，修订版 2 到 6），可以用用户密码或所有者密码打开文档，
+// 在 lopdf 解析对象流之前解密每个对象。重新加密输出时沿用原文件的加密字典和文件密钥，
+// 原来的密码和权限保持不变，不需要知道所有者密码；也可以按 `EncryptionSettings`
+// 生成新的 AES-128 或 AES-256 加密。
+
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Text;
+use crate::optimize::object_streams::is_transient;
+use crate::tr;
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use aes::{Aes128, Aes256};
+use lopdf::xref::XrefEntry;
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use sha2::{Sha256, Sha384, Sha512};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+/// 密码填充串 (PDF 32000-1 7.6.3.3)
+const PASSWORD_PADDING: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08, 0x2E, 0x2E, 0x00,
+    0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+
+/// AES-256 (修订版 5、6) 的密码最多使用 127 个字节
+const MAX_PASSWORD_LEN_R6: usize = 127;
+
+const AES_BLOCK: usize = 16;
+
+/// 解密副本所在临时目录的名称前缀，后面是进程号和随机数
+const DECRYPTED_DIR_PREFIX: &str = "pdf-compressor-decrypted-";
+
+lazy_static::lazy_static! {
+    /// 正在加载的加密文档的安全处理器。lopdf 的过滤函数是普通函数指针，无法捕获变量，只能通过全局状态传入
+    static ref LOADING: RwLock<Option<SecurityHandler>> = RwLock::new(None);
+    /// 同一时间只加载一个加密文档
+    static ref LOAD_LOCK: Mutex<()> = Mutex::new(());
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EncryptionMethod {
+    Rc4,
+    Aes128,
+    Aes256,
+}
+
+impl EncryptionMethod {
+    pub fn label(self) -> &'static str {
+        match self {
+            EncryptionMethod::Rc4 => "RC4",
+            EncryptionMethod::Aes128 => "AES-128",
+            EncryptionMethod::Aes256 => "AES-256",
+        }
+    }
+}
+
+/// 权限标志 (/P) 中常用的几项，未指定的权限默认允许
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct Permissions {
+    /// 打印（包括高质量打印）
+    pub print: bool,
+    /// 修改内容以及插入、删除和旋转页面
+    pub modify: bool,
+    /// 复制文本和图像
+    pub copy: bool,
+    /// 添加注释和填写表单
+    pub annotate: bool,
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Self {
+            print: true,
+            modify: true,
+            copy: true,
+            annotate: true,
+        }
+    }
+}
+
+impl Permissions {
+    /// 转换为修订版 3 及以上的权限标志，保留位和辅助功能提取位始终为 1
+    fn to_flags(self) -> i32 {
+        let mut flags: u32 = 0xFFFF_F0C0 | 1 << 9;
+        if self.print {
+            flags |= 1 << 2 | 1 << 11;
+        }
+        if self.modify {
+            flags |= 1 << 3 | 1 << 10;
+        }
+        if self.copy {
+            flags |= 1 << 4;
+        }
+        if self.annotate {
+            flags |= 1 << 5 | 1 << 8;
+        }
+        flags as i32
+    }
+
+    fn from_flags(flags: i32) -> Self {
+        Self {
+            print: flags & (1 << 2) != 0,
+            modify: flags & (1 << 3) != 0,
+            copy: flags & (1 << 4) != 0,
+            annotate: flags & (1 << 5) != 0,
+        }
+    }
+}
+
+/// 输入文件的加密信息
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncryptionInfo {
+    pub method: EncryptionMethod,
+    /// 密钥长度（位）
+    pub key_length: u32,
+    /// 标准安全处理器的修订版本 (/R)
+    pub revision: i64,
+    /// 打开文档是否需要密码，为 false 时只有所有者密码限制了权限
+    pub requires_password: bool,
+    pub permissions: Permissions,
+    /// 原始的权限标志 (/P)
+    pub permission_flags: i32,
+}
+
+/// 输出文件的加密设置
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct EncryptionSettings {
+    /// 加密算法，只支持 AES-128 和 AES-256
+    pub method: EncryptionMethod,
+    /// 打开文档的密码，为空时不需要密码就能打开，但仍受权限限制
+    pub user_password: String,
+    /// 解除权限限制的密码，未指定时随机生成，任何人都无法解除限制
+    pub owner_password: Option<String>,
+    pub permissions: Permissions,
+}
+
+impl Default for EncryptionSettings {
+    fn default() -> Self {
+        Self {
+            method: EncryptionMethod::Aes256,
+            user_password: String::new(),
+            owner_password: None,
+            permissions: Permissions::default(),
+        }
+    }
+}
+
+impl EncryptionSettings {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.method == EncryptionMethod::Rc4 {
+            return Err(AppError::with_details(ErrorCode::InvalidOptions, tr!(Text::Rc4OutputUnsupported)));
+        }
+        // AES-128 的密码按 PDFDocEncoding 处理，只接受 ASCII 字符以免不同阅读器解释不一致
+        let passwords = [Some(&self.user_password), self.owner_password.as_ref()];
+        if self.method == EncryptionMethod::Aes128 && passwords.iter().flatten().any(|password| !password.is_ascii()) {
+            return Err(AppError::with_details(ErrorCode::InvalidOptions, tr!(Text::Aes128PasswordNotAscii)));
+        }
+        Ok(())
+    }
+}
+
+/// 已通过密码验证的标准安全处理器
+#[derive(Debug, Clone)]
+pub(crate) struct SecurityHandler {
+    info: EncryptionInfo,
+    /// 文件密钥
+    key: Vec<u8>,
+    /// 加密字典的对象编号，加密字典本身不加密
+    dictionary_id: Option<ObjectId>,
+    dictionary: Dictionary,
+    /// 文件标识符 (/ID)，RC4 和 AES-128 的密钥依赖其中的第一项
+    file_id: Option<Object>,
+    encrypt_metadata: bool,
+    /// 位于对象流中的对象编号，这些对象随对象流整体加密
+    compressed: BTreeSet<u32>,
+}
+
+impl SecurityHandler {
+    /// 按设置生成新的加密字典、随机文件密钥和文件标识符
+    pub(crate) fn create(settings: &EncryptionSettings) -> Result<Self, AppError> {
+        let file_id: [u8; 16] = random_bytes()?;
+        let (security, key, dictionary) = StandardSecurity::create(settings, &file_id)?;
+        let file_id = Object::String(file_id.to_vec(), StringFormat::Hexadecimal);
+
+        Ok(Self {
+            info: EncryptionInfo {
+                method: security.method,
+                key_length: security.key_length as u32 * 8,
+                revision: security.revision,
+                requires_password: !settings.user_password.is_empty(),
+                permissions: settings.permissions,
+                permission_flags: security.flags,
+            },
+            key,
+            dictionary_id: None,
+            dictionary,
+            file_id: Some(Object::Array(vec![file_id.clone(), file_id])),
+            encrypt_metadata: true,
+            compressed: BTreeSet::new(),
+        })
+    }
+
+    pub fn info(&self) -> &EncryptionInfo {
+        &self.info
+    }
+
+    /// 计算对象的加密密钥 (Algorithm 1)，AES-256 直接使用文件密钥
+    fn object_key(&self, id: ObjectId) -> Vec<u8> {
+        if self.info.method == EncryptionMethod::Aes256 {
+            return self.key.clone();
+        }
+
+        let mut hasher = Md5::new();
+        hasher.update(&self.key);
+        hasher.update(&id.0.to_le_bytes()[..3]);
+        hasher.update(&id.1.to_le_bytes()[..2]);
+        if self.info.method == EncryptionMethod::Aes128 {
+            hasher.update(b"sAlT");
+        }
+        let hash = hasher.finalize();
+        hash[..(self.key.len() + 5).min(16)].to_vec()
+    }
+
+    /// 解密失败（例如 AES 填充错误）时返回 None
+    fn decrypt_bytes(&self, key: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+        if self.info.method == EncryptionMethod::Rc4 {
+            return Some(rc4(key, data));
+        }
+
+        if data.len() < AES_BLOCK || !data.len().is_multiple_of(AES_BLOCK) {
+            return None;
+        }
+        let (iv, data) = data.split_at(AES_BLOCK);
+        let mut plain = aes_cbc_decrypt(key, iv, data);
+
+        let padding = *plain.last()? as usize;
+        if padding == 0 || padding > AES_BLOCK || padding > plain.len() {
+            return None;
+        }
+        plain.truncate(plain.len() - padding);
+        Some(plain)
+    }
+
+    fn encrypt_bytes(&self, key: &[u8], data: &[u8]) -> Result<Vec<u8>, AppError> {
+        if self.info.method == EncryptionMethod::Rc4 {
+            return Ok(rc4(key, data));
+        }
+
+        let iv: [u8; AES_BLOCK] = random_bytes()?;
+
+        let padding = AES_BLOCK - data.len() % AES_BLOCK;
+        let mut padded = data.to_vec();
+        padded.resize(data.len() + padding, padding as u8);
+
+        let mut encrypted = iv.to_vec();
+        encrypted.extend(aes_cbc_encrypt(key, &iv, &padded));
+        Ok(encrypted)
+    }
+
+    /// 交叉引用流不加密，`/EncryptMetadata false` 时元数据流也不加密
+    fn is_encrypted(&self, object: &Object) -> bool {
+        match object.type_name() {
+            Ok("XRef") => false,
+            Ok("Metadata") => self.encrypt_metadata,
+            _ => true,
+        }
+    }
+
+    fn decrypt_object(&self, id: ObjectId, object: &mut Object) {
+        if Some(id) == self.dictionary_id || self.compressed.contains(&id.0) || !self.is_encrypted(object) {
+            return;
+        }
+
+        let key = self.object_key(id);
+        let _ = map_strings(object, &mut |data| {
+            Ok::<_, ()>(self.decrypt_bytes(&key, data).unwrap_or_else(|| data.to_vec()))
+        });
+        if let Object::Stream(stream) = object {
+            if let Some(content) = self.decrypt_bytes(&key, &stream.content) {
+                stream.set_content(content);
+            }
+        }
+    }
+
+    fn encrypt_object(&self, id: ObjectId, object: &mut Object) -> Result<(), AppError> {
+        if !self.is_encrypted(object) {
+            return Ok(());
+        }
+
+        let key = self.object_key(id);
+        map_strings(object, &mut |data| self.encrypt_bytes(&key, data))?;
+        if let Object::Stream(stream) = object {
+            let content = self.encrypt_bytes(&key, &stream.content)?;
+            stream.set_content(content);
+        }
+        Ok(())
+    }
+
+    /// 加密文档中的所有字符串和流，并写入加密字典和文件标识符
+    fn encrypt_document(&self, document: &mut Document) -> Result<(), AppError> {
+        self.require_version(document);
+        for (&id, object) in document.objects.iter_mut() {
+            self.encrypt_object(id, object)?;
+        }
+
+        let dictionary_id = document.add_object(Object::Dictionary(self.dictionary.clone()));
+        document.trailer.set("Encrypt", Object::Reference(dictionary_id));
+        if let Some(file_id) = &self.file_id {
+            document.trailer.set("ID", file_id.clone());
+        }
+        Ok(())
+    }
+
+    /// AES-128 需要 PDF 1.6，AES-256 需要 PDF 1.7 和 Adobe 扩展级别 8
+    fn require_version(&self, document: &mut Document) {
+        let version = match self.info.method {
+            EncryptionMethod::Rc4 => return,
+            EncryptionMethod::Aes128 => "1.6",
+            EncryptionMethod::Aes256 => "1.7",
+        };
+        if document.version.as_str() < version {
+            document.version = version.to_string();
+        }
+
+        if self.info.method == EncryptionMethod::Aes256 && document.version.as_str() < "2.0" {
+            if let Ok(catalog) = document.catalog_mut() {
+                if !catalog.has(b"Extensions") {
+                    let mut adobe = Dictionary::new();
+                    adobe.set("BaseVersion", Object::Name(b"1.7".to_vec()));
+                    adobe.set("ExtensionLevel", 8);
+                    let mut extensions = Dictionary::new();
+                    extensions.set("ADBE", adobe);
+                    catalog.set("Extensions", extensions);
+                }
+            }
+        }
+    }
+}
+
+/// 检测文件是否加密，加密时用密码验证并返回安全处理器
+///
+/// 未提供密码时尝试空的用户密码，只限制了权限的文档不需要密码就能处理。
+pub(crate) fn open(path: &Path, password: Option<&str>) -> Result<Option<SecurityHandler>, AppError> {
+    // 只需要交叉引用表和加密字典，lopdf 无法解析的文件交给引擎自行报告错误
+    let Ok(document) = Document::load_filtered(path, without_streams) else {
+        return Ok(None);
+    };
+    let Ok(reference) = document.trailer.get(b"Encrypt") else {
+        return Ok(None);
+    };
+
+    let (dictionary_id, dictionary) = match reference {
+        Object::Reference(id) => (
+            Some(*id),
+            document
+                .get_dictionary(*id)
+                .map_err(|e| AppError::with_details(ErrorCode::InvalidPdf, e))?
+                .clone(),
+        ),
+        Object::Dictionary(dictionary) => (None, dictionary.clone()),
+        _ => return Err(AppError::with_details(ErrorCode::InvalidPdf, "/Encrypt")),
+    };
+    let file_id = document.trailer.get(b"ID").ok().cloned();
+
+    let security = StandardSecurity::parse(&dictionary, file_id.as_ref())
+        .map_err(|reason| AppError::with_details(ErrorCode::EncryptedInput, tr!(Text::UnsupportedEncryption, reason)))?;
+
+    let key = match password {
+        Some(password) => security
+            .authenticate(password.as_bytes())
+            .ok_or_else(|| AppError::new(ErrorCode::InvalidPassword))?,
+        None => security
+            .authenticate(b"")
+            .ok_or_else(|| AppError::new(ErrorCode::EncryptedInput))?,
+    };
+
+    let compressed = document
+        .reference_table
+        .entries
+        .iter()
+        .filter(|(_, entry)| matches!(entry, XrefEntry::Compressed { .. }))
+        .map(|(&number, _)| number)
+        .collect();
+
+    Ok(Some(SecurityHandler {
+        info: EncryptionInfo {
+            method: security.method,
+            key_length: security.key_length as u32 * 8,
+            revision: security.revision,
+            requires_password: security.authenticate_user(b"").is_none(),
+            permissions: Permissions::from_flags(security.flags),
+            permission_flags: security.flags,
+        },
+        key,
+        dictionary_id,
+        dictionary,
+        file_id,
+        encrypt_metadata: security.encrypt_metadata,
+        compressed,
+    }))
+}
+
+/// 加载文档，加密的文档在加载过程中解密，同时返回安全处理器供重新加密使用
+pub(crate) fn load_document(
+    path: &Path,
+    password: Option<&str>,
+) -> Result<(Document, Option<SecurityHandler>), AppError> {
+    let handler = open(path, password)?;
+    let document = match &handler {
+        Some(handler) => load_decrypted(path, handler),
+        None => Document::load(path),
+    };
+    let document = document.map_err(|e| AppError::with_details(ErrorCode::InvalidPdf, e))?;
+    Ok((document, handler))
+}
+
+fn load_decrypted(path: &Path, handler: &SecurityHandler) -> lopdf::Result<Document> {
+    let _guard = LOAD_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    set_loading(Some(handler.clone()));
+    let document = Document::load_filtered(path, decrypt_filter);
+    set_loading(None);
+
+    let mut document = document?;
+    document.trailer.remove(b"Encrypt");
+    if let Some(id) = handler.dictionary_id {
+        document.objects.remove(&id);
+    }
+    Ok(document)
+}
+
+/// 用安全处理器的加密字典和密钥加密 `path` 处的文档
+pub(crate) fn encrypt_file(path: &Path, handler: &SecurityHandler) -> Result<(), AppError> {
+    let failed = |e: lopdf::Error| AppError::with_details(ErrorCode::OutputWriteFailed, tr!(Text::CannotEncrypt, e));
+
+    let mut document = Document::load(path).map_err(failed)?;
+    // 加密后使用传统的交叉引用表保存，对象流中的对象在加载时已经展开
+    document.objects.retain(|_, object| !is_transient(object));
+    handler.encrypt_document(&mut document)?;
+    document.save(path).map_err(|e| failed(e.into()))?;
+    Ok(())
+}
+
+/// 解密后的输入副本，供 Ghostscript 读取，密码不必出现在其他用户能通过 ps 看到的命令行上
+///
+/// 副本写在系统临时目录下新建的私有目录中：Unix 上目录权限为 0700，Windows 的 %TEMP% 位于用户配置目录下，
+/// 默认只有当前用户可以访问。离开作用域时删除整个目录，进程被强制结束时残留的目录在下次创建副本时清理。
+pub(crate) struct DecryptedCopy {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl DecryptedCopy {
+    /// 用密码解密输入并写入私有临时目录，输入未加密时返回 None
+    pub fn create(input_path: &Path, password: &str) -> Result<Option<Self>, AppError> {
+        let Some(handler) = open(input_path, Some(password))? else {
+            return Ok(None);
+        };
+        let mut document =
+            load_decrypted(input_path, &handler).map_err(|e| AppError::with_details(ErrorCode::InvalidPdf, e))?;
+        // 加载时已经展开了对象流，旧的对象流和交叉引用流不再需要
+        document.objects.retain(|_, object| !is_transient(object));
+
+        remove_stale_copies();
+        let write_failed = |e: std::io::Error| AppError::with_details(ErrorCode::OutputWriteFailed, e);
+        let dir = create_private_dir().map_err(write_failed)?;
+        let copy = Self {
+            path: dir.join("input.pdf"),
+            dir,
+        };
+
+        let mut file = BufWriter::new(fs::File::create(&copy.path).map_err(write_failed)?);
+        document
+            .save_to(&mut file)
+            .map_err(|e| AppError::with_details(ErrorCode::OutputWriteFailed, e))?;
+        file.flush().map_err(write_failed)?;
+        Ok(Some(copy))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for DecryptedCopy {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// 在系统临时目录下新建只有当前用户能访问的目录，目录已存在时失败，不会复用别人预先创建的目录
+fn create_private_dir() -> std::io::Result<PathBuf> {
+    let mut random = [0u8; 8];
+    getrandom::getrandom(&mut random).map_err(|e| std::io::Error::other(e.to_string()))?;
+    let suffix: String = random.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let dir = std::env::temp_dir().join(format!("{}{}-{}", DECRYPTED_DIR_PREFIX, std::process::id(), suffix));
+
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(&dir)?;
+    Ok(dir)
+}
+
+/// 删除已经退出的进程留下的解密副本
+fn remove_stale_copies() {
+    let Ok(entries) = fs::read_dir(std::env::temp_dir()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(pid) = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(DECRYPTED_DIR_PREFIX))
+            .and_then(|rest| rest.split('-').next())
+            .and_then(|pid| pid.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if pid != std::process::id() && is_stale(pid, &entry) {
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_stale(pid: u32, _entry: &fs::DirEntry) -> bool {
+    let Some(pid) = libc::pid_t::try_from(pid).ok().filter(|&pid| pid > 0) else {
+        return false;
+    };
+    // 信号 0 只检查进程是否存在，ESRCH 表示进程已经退出
+    let result = unsafe { libc::kill(pid, 0) };
+    result != 0 && std::io::Error::last_os_error().raw_os_error() == Some(libc::ESRCH)
+}
+
+/// 其他平台没有简单的方法检查进程是否存在，只清理一天以前创建的副本
+#[cfg(not(unix))]
+fn is_stale(_pid: u32, entry: &fs::DirEntry) -> bool {
+    const STALE_AGE: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+    entry
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age > STALE_AGE)
+}
+
+fn set_loading(handler: Option<SecurityHandler>) {
+    match LOADING.write() {
+        Ok(mut loading) => *loading = handler,
+        Err(poisoned) => *poisoned.into_inner() = handler,
+    }
+}
+
+fn without_streams(id: ObjectId, object: &mut Object) -> Option<(ObjectId, Object)> {
+    match object {
+        Object::Stream(_) => None,
+        _ => keep(id, object),
+    }
+}
+
+/// 在 lopdf 解析对象流之前解密每个对象
+fn decrypt_filter(id: ObjectId, object: &mut Object) -> Option<(ObjectId, Object)> {
+    let loading = LOADING.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(handler) = loading.as_ref() {
+        handler.decrypt_object(id, object);
+    }
+    keep(id, object)
+}
+
+/// lopdf 对顶层对象使用过滤函数修改后的 `object`，对对象流中的对象使用返回值。
+/// 流不会出现在对象流中，返回值会被丢弃，不需要复制
+fn keep(id: ObjectId, object: &Object) -> Option<(ObjectId, Object)> {
+    match object {
+        Object::Stream(_) => Some((id, Object::Null)),
+        _ => Some((id, object.clone())),
+    }
+}
+
+/// 对对象中（包括嵌套在数组和字典中）的每个字符串应用 `f`
+fn map_strings<E, F>(object: &mut Object, f: &mut F) -> Result<(), E>
+where
+    F: FnMut(&[u8]) -> Result<Vec<u8>, E>,
+{
+    match object {
+        Object::String(bytes, _) => *bytes = f(bytes)?,
+        Object::Array(items) => {
+            for item in items {
+                map_strings(item, f)?;
+            }
+        }
+        Object::Dictionary(dictionary) => {
+            for (_, value) in dictionary.iter_mut() {
+                map_strings(value, f)?;
+            }
+        }
+        Object::Stream(stream) => {
+            for (_, value) in stream.dict.iter_mut() {
+                map_strings(value, f)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// 标准安全处理器 (/Filter /Standard) 加密字典中的参数
+struct StandardSecurity {
+    method: EncryptionMethod,
+    /// 密钥长度（字节）
+    key_length: usize,
+    revision: i64,
+    owner: Vec<u8>,
+    user: Vec<u8>,
+    owner_key: Vec<u8>,
+    user_key: Vec<u8>,
+    flags: i32,
+    /// 文件标识符的第一项
+    file_id: Vec<u8>,
+    encrypt_metadata: bool,
+}
+
+impl StandardSecurity {
+    /// 按设置生成加密参数，返回参数、文件密钥和加密字典
+    fn create(settings: &EncryptionSettings, file_id: &[u8]) -> Result<(Self, Vec<u8>, Dictionary), AppError> {
+        let generated;
+        let owner_password = match settings.owner_password.as_deref().filter(|password| !password.is_empty()) {
+            Some(password) => password,
+            None => {
+                generated = random_bytes::<16>()?.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+                &generated
+            }
+        };
+        let user = settings.user_password.as_bytes();
+        let owner = owner_password.as_bytes();
+
+        let (method, key_length, version, revision, filter) = match settings.method {
+            EncryptionMethod::Aes256 => (EncryptionMethod::Aes256, 32, 5, 6, "AESV3"),
+            _ => (EncryptionMethod::Aes128, 16, 4, 4, "AESV2"),
+        };
+        let mut security = Self {
+            method,
+            key_length,
+            revision,
+            owner: Vec::new(),
+            user: Vec::new(),
+            owner_key: Vec::new(),
+            user_key: Vec::new(),
+            flags: settings.permissions.to_flags(),
+            file_id: file_id.to_vec(),
+            encrypt_metadata: true,
+        };
+
+        let mut dictionary = Dictionary::new();
+        let key = if revision >= 5 {
+            // Algorithm 8、9、10
+            let user = &user[..user.len().min(MAX_PASSWORD_LEN_R6)];
+            let owner = &owner[..owner.len().min(MAX_PASSWORD_LEN_R6)];
+            let key: [u8; 32] = random_bytes()?;
+            let salts: [u8; 32] = random_bytes()?;
+
+            security.user = password_hash(revision, user, &salts[..8], &[]);
+            security.user.extend_from_slice(&salts[..16]);
+            security.user_key = aes_cbc_encrypt(&password_hash(revision, user, &salts[8..16], &[]), &[0; AES_BLOCK], &key);
+
+            security.owner = password_hash(revision, owner, &salts[16..24], &security.user);
+            security.owner_key = aes_cbc_encrypt(
+                &password_hash(revision, owner, &salts[24..32], &security.user),
+                &[0; AES_BLOCK],
+                &key,
+            );
+            security.owner.extend_from_slice(&salts[16..32]);
+
+            let mut perms = [0u8; AES_BLOCK];
+            perms[..4].copy_from_slice(&security.flags.to_le_bytes());
+            perms[4..8].copy_from_slice(&[0xFF; 4]);
+            perms[8..12].copy_from_slice(b"Tadb");
+            perms[12..].copy_from_slice(&random_bytes::<4>()?);
+            dictionary.set(
+                "OE",
+                Object::String(security.owner_key.clone(), StringFormat::Hexadecimal),
+            );
+            dictionary.set("UE", Object::String(security.user_key.clone(), StringFormat::Hexadecimal));
+            dictionary.set(
+                "Perms",
+                Object::String(aes_cbc_encrypt(&key, &[0; AES_BLOCK], &perms), StringFormat::Hexadecimal),
+            );
+            key.to_vec()
+        } else {
+            // Algorithm 3、2、5
+            let owner_key = security.owner_rc4_key(owner);
+            security.owner = rc4(&owner_key, &pad_password(user));
+            for round in 1..20u8 {
+                security.owner = rc4(&xor_key(&owner_key, round), &security.owner);
+            }
+            let key = security.file_key(user);
+            security.user = security.user_hash(&key);
+            security.user.extend_from_slice(&random_bytes::<16>()?);
+            key
+        };
+
+        let mut crypt_filter = Dictionary::new();
+        crypt_filter.set("CFM", Object::Name(filter.as_bytes().to_vec()));
+        crypt_filter.set("AuthEvent", Object::Name(b"DocOpen".to_vec()));
+        crypt_filter.set("Length", key_length as i64);
+        let mut crypt_filters = Dictionary::new();
+        crypt_filters.set("StdCF", crypt_filter);
+
+        dictionary.set("Filter", Object::Name(b"Standard".to_vec()));
+        dictionary.set("V", version);
+        dictionary.set("R", revision);
+        dictionary.set("Length", key_length as i64 * 8);
+        dictionary.set("P", security.flags as i64);
+        dictionary.set("O", Object::String(security.owner.clone(), StringFormat::Hexadecimal));
+        dictionary.set("U", Object::String(security.user.clone(), StringFormat::Hexadecimal));
+        dictionary.set("CF", crypt_filters);
+        dictionary.set("StmF", Object::Name(b"StdCF".to_vec()));
+        dictionary.set("StrF", Object::Name(b"StdCF".to_vec()));
+
+        Ok((security, key, dictionary))
+    }
+
+    /// 不支持的加密方式返回原因
+    fn parse(dictionary: &Dictionary, file_id: Option<&Object>) -> Result<Self, String> {
+        let name = |key: &[u8]| dictionary.get(key).and_then(Object::as_name_str).ok();
+        let integer = |key: &[u8]| dictionary.get(key).and_then(Object::as_i64).ok();
+        let bytes = |key: &[u8]| {
+            dictionary
+                .get(key)
+                .and_then(Object::as_str)
+                .map(|value| value.to_vec())
+                .unwrap_or_default()
+        };
+
+        let filter = name(b"Filter").unwrap_or_default();
+        if filter != "Standard" {
+            return Err(format!("/Filter /{}", filter));
+        }
+
+        let version = integer(b"V").unwrap_or(0);
+        let revision = integer(b"R").unwrap_or(0);
+        let (method, key_length) = match (version, revision) {
+            (1, 2) => (EncryptionMethod::Rc4, 5),
+            (2, 2..=3) => (EncryptionMethod::Rc4, integer(b"Length").unwrap_or(40) as usize / 8),
+            (4, 4) => match crypt_filter_method(dictionary) {
+                Some("AESV2") => (EncryptionMethod::Aes128, 16),
+                Some("V2") => (EncryptionMethod::Rc4, 16),
+                other => return Err(format!("/CFM /{}", other.unwrap_or_default())),
+            },
+            (5, 5..=6) => (EncryptionMethod::Aes256, 32),
+            _ => return Err(format!("/V {} /R {}", version, revision)),
+        };
+
+        let security = Self {
+            method,
+            key_length,
+            revision,
+            owner: bytes(b"O"),
+            user: bytes(b"U"),
+            owner_key: bytes(b"OE"),
+            user_key: bytes(b"UE"),
+            flags: integer(b"P").unwrap_or(0) as i32,
+            file_id: file_id
+                .and_then(|id| id.as_array().ok())
+                .and_then(|items| items.first())
+                .and_then(|first| first.as_str().ok())
+                .map(|first| first.to_vec())
+                .unwrap_or_default(),
+            encrypt_metadata: dictionary
+                .get(b"EncryptMetadata")
+                .and_then(Object::as_bool)
+                .unwrap_or(true),
+        };
+
+        let hash_length = if revision >= 5 { 48 } else { 32 };
+        let valid_key = method == EncryptionMethod::Aes256 || (5..=16).contains(&key_length);
+        let valid_hashes = security.owner.len() >= hash_length
+            && security.user.len() >= hash_length
+            && (revision < 5 || security.owner_key.len() >= 32 && security.user_key.len() >= 32);
+        if !valid_key || !valid_hashes {
+            return Err(format!("/V {} /R {}", version, revision));
+        }
+
+        Ok(security)
+    }
+
+    /// 依次尝试作为用户密码和所有者密码验证，成功时返回文件密钥
+    fn authenticate(&self, password: &[u8]) -> Option<Vec<u8>> {
+        self.authenticate_user(password)
+            .or_else(|| self.authenticate_owner(password))
+    }
+
+    fn authenticate_user(&self, password: &[u8]) -> Option<Vec<u8>> {
+        if self.revision >= 5 {
+            let password = &password[..password.len().min(MAX_PASSWORD_LEN_R6)];
+            if password_hash(self.revision, password, &self.user[32..40], &[]) != self.user[..32] {
+                return None;
+            }
+            let intermediate = password_hash(self.revision, password, &self.user[40..48], &[]);
+            return Some(aes_cbc_decrypt(&intermediate, &[0; AES_BLOCK], &self.user_key[..32]));
+        }
+
+        let key = self.file_key(password);
+        let valid = if self.revision == 2 {
+            rc4(&key, &PASSWORD_PADDING) == self.user[..32]
+        } else {
+            self.user_hash(&key) == self.user[..16]
+        };
+        valid.then_some(key)
+    }
+
+    /// 修订版 3 和 4 的 /U 的前 16 个字节 (Algorithm 5)
+    fn user_hash(&self, key: &[u8]) -> Vec<u8> {
+        let mut data = md5(&[&PASSWORD_PADDING, &self.file_id]).to_vec();
+        for round in 0..20u8 {
+            data = rc4(&xor_key(key, round), &data);
+        }
+        data
+    }
+
+    /// 所有者密码先解出用户密码 (Algorithm 7)，AES-256 则直接解出文件密钥
+    fn authenticate_owner(&self, password: &[u8]) -> Option<Vec<u8>> {
+        if self.revision >= 5 {
+            let password = &password[..password.len().min(MAX_PASSWORD_LEN_R6)];
+            let user = &self.user[..48];
+            if password_hash(self.revision, password, &self.owner[32..40], user) != self.owner[..32] {
+                return None;
+            }
+            let intermediate = password_hash(self.revision, password, &self.owner[40..48], user);
+            return Some(aes_cbc_decrypt(&intermediate, &[0; AES_BLOCK], &self.owner_key[..32]));
+        }
+
+        let key = &self.owner_rc4_key(password);
+        let user_password = if self.revision == 2 {
+            rc4(key, &self.owner[..32])
+        } else {
+            let mut data = self.owner[..32].to_vec();
+            for round in (0..20u8).rev() {
+                data = rc4(&xor_key(key, round), &data);
+            }
+            data
+        };
+        self.authenticate_user(&user_password)
+    }
+
+    /// 由所有者密码计算加密 /O 使用的 RC4 密钥 (Algorithm 3 的 a 到 d 步)
+    fn owner_rc4_key(&self, password: &[u8]) -> Vec<u8> {
+        let mut hash = md5(&[&pad_password(password)]);
+        if self.revision >= 3 {
+            for _ in 0..50 {
+                hash = md5(&[&hash]);
+            }
+        }
+        hash[..self.key_length].to_vec()
+    }
+
+    /// 由用户密码计算文件密钥 (Algorithm 2)
+    fn file_key(&self, password: &[u8]) -> Vec<u8> {
+        let mut hasher = Md5::new();
+        hasher.update(pad_password(password));
+        hasher.update(&self.owner[..32]);
+        hasher.update(self.flags.to_le_bytes());
+        hasher.update(&self.file_id);
+        if self.revision >= 4 && !self.encrypt_metadata {
+            hasher.update([0xFF; 4]);
+        }
+        let mut hash: [u8; 16] = hasher.finalize().into();
+
+        if self.revision >= 3 {
+            for _ in 0..50 {
+                hash = md5(&[&hash[..self.key_length]]);
+            }
+        }
+        hash[..self.key_length].to_vec()
+    }
+}
+
+/// 修订版 5 使用 SHA-256，修订版 6 使用 Algorithm 2.B
+fn password_hash(revision: i64, password: &[u8], salt: &[u8], user: &[u8]) -> Vec<u8> {
+    let mut hash = Sha256::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(user)
+        .finalize()
+        .to_vec();
+    if revision == 5 {
+        return hash;
+    }
+
+    let mut round = 0;
+    loop {
+        let mut block = Vec::with_capacity(64 * (password.len() + hash.len() + user.len()));
+        for _ in 0..64 {
+            block.extend_from_slice(password);
+            block.extend_from_slice(&hash);
+            block.extend_from_slice(user);
+        }
+        let encrypted = aes_cbc_encrypt(&hash[..16], &hash[16..32], &block);
+
+        // 前 16 字节作为大端整数对 3 取模，等于各字节之和对 3 取模
+        hash = match encrypted[..16].iter().map(|&byte| byte as u32).sum::<u32>() % 3 {
+            0 => Sha256::digest(&encrypted).to_vec(),
+            1 => Sha384::digest(&encrypted).to_vec(),
+            _ => Sha512::digest(&encrypted).to_vec(),
+        };
+
+        round += 1;
+        let last = encrypted.last().copied().unwrap_or_default() as usize;
+        if round >= 64 && last + 32 <= round {
+            break;
+        }
+    }
+    hash.truncate(32);
+    hash
+}
+
+/// 修订版 4 的加密方法由 /StmF 指定的加密过滤器决定
+fn crypt_filter_method(dictionary: &Dictionary) -> Option<&str> {
+    let filter = dictionary.get(b"StmF").and_then(Object::as_name).ok()?;
+    dictionary
+        .get(b"CF")
+        .and_then(Object::as_dict)
+        .and_then(|filters| filters.get(filter))
+        .and_then(Object::as_dict)
+        .and_then(|filter| filter.get(b"CFM"))
+        .and_then(Object::as_name_str)
+        .ok()
+}
+
+fn pad_password(password: &[u8]) -> [u8; 32] {
+    let length = password.len().min(32);
+    let mut padded = PASSWORD_PADDING;
+    padded[..length].copy_from_slice(&password[..length]);
+    padded[length..].copy_from_slice(&PASSWORD_PADDING[..32 - length]);
+    padded
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], AppError> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(|e| AppError::with_details(ErrorCode::Internal, e))?;
+    Ok(bytes)
+}
+
+fn xor_key(key: &[u8], round: u8) -> Vec<u8> {
+    key.iter().map(|byte| byte ^ round).collect()
+}
+
+fn md5(parts: &[&[u8]]) -> [u8; 16] {
+    let mut hasher = Md5::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: [u8; 256] = std::array::from_fn(|index| index as u8);
+    let mut j = 0u8;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, j as usize);
+    }
+
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+        })
+        .collect()
+}
+
+/// 16 字节密钥使用 AES-128，32 字节密钥使用 AES-256
+enum AesCipher {
+    Aes128(Box<Aes128>),
+    Aes256(Box<Aes256>),
+}
+
+impl AesCipher {
+    fn new(key: &[u8]) -> Self {
+        if key.len() == 32 {
+            AesCipher::Aes256(Box::new(Aes256::new(GenericArray::from_slice(key))))
+        } else {
+            AesCipher::Aes128(Box::new(Aes128::new(GenericArray::from_slice(&key[..16]))))
+        }
+    }
+
+    fn encrypt(&self, block: &mut [u8]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            AesCipher::Aes128(cipher) => cipher.encrypt_block(block),
+            AesCipher::Aes256(cipher) => cipher.encrypt_block(block),
+        }
+    }
+
+    fn decrypt(&self, block: &mut [u8]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            AesCipher::Aes128(cipher) => cipher.decrypt_block(block),
+            AesCipher::Aes256(cipher) => cipher.decrypt_block(block),
+        }
+    }
+}
+
+/// CBC 模式加密，不做填充，`data` 的长度必须是 16 的倍数
+fn aes_cbc_encrypt(key: &[u8], iv: &[u8], data: &[u8]) -> Vec<u8> {
+    let cipher = AesCipher::new(key);
+    let mut previous = iv.to_vec();
+    let mut output = Vec::with_capacity(data.len());
+    for chunk in data.chunks_exact(AES_BLOCK) {
+        let mut block: Vec<u8> = chunk.iter().zip(&previous).map(|(a, b)| a ^ b).collect();
+        cipher.encrypt(&mut block);
+        output.extend_from_slice(&block);
+        previous = block;
+    }
+    output
+}
+
+/// CBC 模式解密，不去除填充
+fn aes_cbc_decrypt(key: &[u8], iv: &[u8], data: &[u8]) -> Vec<u8> {
+    let cipher = AesCipher::new(key);
+    let mut previous = iv;
+    let mut output = Vec::with_capacity(data.len());
+    for chunk in data.chunks_exact(AES_BLOCK) {
+        let mut block = chunk.to_vec();
+        cipher.decrypt(&mut block);
+        output.extend(block.iter().zip(previous).map(|(a, b)| a ^ b));
+        previous = chunk;
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream};
+
+    const CONTENT: &[u8] = b"BT /F1 12 Tf (Confidential) Tj ET";
+    const TITLE: &[u8] = b"Quarterly report";
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&text[index..index + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// 在临时目录中写入一页的文档，页面内容和文档信息中都包含明文
+    fn write_sample(name: &str) -> PathBuf {
+        let mut document = Document::with_version("1.7");
+        let pages_id = document.new_object_id();
+        let content_id = document.add_object(Stream::new(dictionary! {}, CONTENT.to_vec()));
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 200.into(), 200.into()],
+            "Contents" => content_id,
+        });
+        document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }));
+        let catalog_id = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        let info_id = document.add_object(dictionary! { "Title" => Object::string_literal(TITLE) });
+        document.trailer.set("Root", catalog_id);
+        document.trailer.set("Info", info_id);
+
+        let path = std::env::temp_dir().join(format!("pdf-compressor-{}-{}.pdf", std::process::id(), name));
+        document.save(&path).unwrap();
+        path
+    }
+
+    fn encrypted_sample(name: &str, settings: &EncryptionSettings) -> PathBuf {
+        let path = write_sample(name);
+        let handler = SecurityHandler::create(settings).unwrap();
+        encrypt_file(&path, &handler).unwrap();
+        path
+    }
+
+    fn settings(method: EncryptionMethod, user_password: &str) -> EncryptionSettings {
+        EncryptionSettings {
+            method,
+            user_password: user_password.to_string(),
+            owner_password: Some("owner".to_string()),
+            permissions: Permissions { print: true, modify: false, copy: false, annotate: false },
+        }
+    }
+
+    fn assert_plaintext(path: &Path, password: Option<&str>) -> SecurityHandler {
+        let (document, handler) = load_document(path, password).unwrap();
+        let page_id = *document.get_pages().get(&1).unwrap();
+        assert_eq!(document.get_page_content(page_id).unwrap(), CONTENT);
+        let info_id = document.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        let title = document.get_dictionary(info_id).unwrap().get(b"Title").unwrap().as_str().unwrap();
+        assert_eq!(title, TITLE);
+        assert!(!document.trailer.has(b"Encrypt"));
+        handler.expect("document should be encrypted")
+    }
+
+    fn round_trip(method: EncryptionMethod, revision: i64) {
+        let path = encrypted_sample(method.label(), &settings(method, "user"));
+
+        let raw = fs::read(&path).unwrap();
+        assert!(!raw.windows(CONTENT.len()).any(|window| window == CONTENT));
+        assert!(!raw.windows(TITLE.len()).any(|window| window == TITLE));
+
+        for password in ["user", "owner"] {
+            let handler = assert_plaintext(&path, Some(password));
+            let info = handler.info();
+            assert_eq!(info.method, method);
+            assert_eq!(info.revision, revision);
+            assert!(info.requires_password);
+            assert!(info.permissions.print);
+            assert!(!info.permissions.copy);
+        }
+
+        assert_eq!(load_document(&path, None).err().unwrap().code, ErrorCode::EncryptedInput);
+        assert_eq!(load_document(&path, Some("wrong")).err().unwrap().code, ErrorCode::InvalidPassword);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn aes128_round_trip_with_user_and_owner_password() {
+        round_trip(EncryptionMethod::Aes128, 4);
+    }
+
+    #[test]
+    fn aes256_round_trip_with_user_and_owner_password() {
+        round_trip(EncryptionMethod::Aes256, 6);
+    }
+
+    #[test]
+    fn empty_user_password_opens_without_password() {
+        let path = encrypted_sample("owner-only", &settings(EncryptionMethod::Aes256, ""));
+        let handler = assert_plaintext(&path, None);
+        assert!(!handler.info().requires_password);
+        assert_plaintext(&path, Some("owner"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reencrypting_with_loaded_handler_keeps_passwords() {
+        let path = encrypted_sample("reencrypt", &settings(EncryptionMethod::Aes256, "user"));
+        let (mut document, handler) = load_document(&path, Some("owner")).unwrap();
+        document.save(&path).unwrap();
+        encrypt_file(&path, &handler.unwrap()).unwrap();
+
+        assert_plaintext(&path, Some("user"));
+        assert_plaintext(&path, Some("owner"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rc4_known_answers() {
+        assert_eq!(rc4(b"Key", b"Plaintext"), hex("bbf316e8d940af0ad3"));
+        assert_eq!(rc4(b"Wiki", b"pedia"), hex("1021bf0420"));
+        assert_eq!(rc4(b"Secret", b"Attack at dawn"), hex("45a01f645fc35b383552544b9bf5"));
+        assert_eq!(rc4(b"Key", &rc4(b"Key", b"Plaintext")), b"Plaintext");
+    }
+
+    #[test]
+    fn password_hash_known_answers() {
+        let salt = [83, 245, 146, 101, 198, 247, 34, 198];
+        // 修订版 5 只计算一次 SHA-256
+        assert_eq!(
+            password_hash(5, b"user", &salt, b""),
+            hex("21dbf0031a562b99fe1aff1f0b0397f944ad67c2ca76943d88dc7cc69ce166c8")
+        );
+        // 修订版 6 (Algorithm 2.B)，用户密码和带 48 字节用户密钥串的所有者密码
+        assert_eq!(
+            password_hash(6, b"user", &salt, b""),
+            hex("5ee6cd4ba663fa4cdb801155391121a4962e67b0a09cbbe9a6dfa3fd93eb5fb8")
+        );
+        let user: Vec<u8> = (0..48).collect();
+        assert_eq!(
+            password_hash(6, b"owner", &[1, 2, 3, 4, 5, 6, 7, 8], &user),
+            hex("f6fa23bde6d6d6595be33cecfef713f9da09cdc05cc696c10b1c63c439d6924e")
+        );
+    }
+
+    #[test]
+    fn aes_cbc_round_trip() {
+        let iv = [7u8; AES_BLOCK];
+        for key in [vec![1u8; 16], vec![2u8; 32]] {
+            let data = b"exactly thirty-two bytes of data";
+            let encrypted = aes_cbc_encrypt(&key, &iv, data);
+            assert_ne!(encrypted.as_slice(), data.as_slice());
+            assert_eq!(aes_cbc_decrypt(&key, &iv, &encrypted), data);
+        }
+    }
+
+    #[test]
+    fn unencrypted_input_needs_no_decrypted_copy() {
+        let path = write_sample("plain");
+        assert!(DecryptedCopy::create(&path, "secret").unwrap().is_none());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn decrypted_copy_is_private_and_removed_on_drop() {
+        let path = encrypted_sample("decrypted-copy", &settings(EncryptionMethod::Aes256, "user"));
+        let copy = DecryptedCopy::create(&path, "user").unwrap().unwrap();
+        let dir = copy.path().parent().unwrap().to_path_buf();
+        assert!(dir.starts_with(std::env::temp_dir()));
+
+        let document = Document::load(copy.path()).unwrap();
+        assert!(!document.trailer.has(b"Encrypt"));
+        let page_id = *document.get_pages().get(&1).unwrap();
+        assert_eq!(document.get_page_content(page_id).unwrap(), CONTENT);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        }
+
+        drop(copy);
+        assert!(!dir.exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copies_left_by_exited_processes_are_removed() {
+        // 超出 pid_max 的进程号一定不存在
+        let stale = std::env::temp_dir().join(format!("{}{}-test", DECRYPTED_DIR_PREFIX, i32::MAX));
+        fs::create_dir_all(&stale).unwrap();
+        fs::write(stale.join("input.pdf"), b"plaintext").unwrap();
+
+        remove_stale_copies();
+        assert!(!stale.exists());
+    }
+}
@@ -0,0 +1,573 @@
+use super::encryption::DecryptedCopy;
+use super::pdfa::{self, PdfaResources};
+use super::{
+    ColorMode, CompressionBackend, CompressionJob, CompressionProfile, EngineCapabilities, EngineOutput, FormMode,
+    ImageFilter, PageSelection, ProgressUnit,
+};
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Text;
+use crate::optimize::annotations::{COMMENT_SUBTYPES, NON_COMMENT_SUBTYPES};
+use crate::optimize::color::{convert_images, ColorReport};
+use crate::optimize::object_streams::save_with_object_streams;
+use crate::optimize::sanitize::{sanitize, SanitizeReport};
+use crate::tr;
+use futures_util::future::BoxFuture;
+use lopdf::Document;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+/// 检测 Ghostscript 时 `gs --version` 的最长等待时间
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+lazy_static::lazy_static! {
+    /// 缓存的 Ghostscript 命令，外层 None 表示尚未检测
+    static ref GHOSTSCRIPT_COMMAND: Mutex<Option<Option<String>>> = Mutex::new(None);
+}
+
+/// 基于 Ghostscript pdfwrite 设备的压缩引擎
+pub struct GhostscriptBackend;
+
+impl CompressionBackend for GhostscriptBackend {
+    fn name(&self) -> &'static str {
+        "ghostscript"
+    }
+
+    fn is_available(&self) -> BoxFuture<'_, bool> {
+        Box::pin(is_ghostscript_available())
+    }
+
+    fn capabilities(&self) -> EngineCapabilities {
+        EngineCapabilities {
+            image_downsampling: true,
+            font_subsetting: true,
+            external_process: true,
+            pdfa: true,
+        }
+    }
+
+    fn compress<'a>(&'a self, job: &'a CompressionJob) -> BoxFuture<'a, Result<EngineOutput, AppError>> {
+        Box::pin(compress_with_ghostscript(job))
+    }
+}
+
+async fn compress_with_ghostscript(job: &CompressionJob) -> Result<EngineOutput, AppError> {
+    // 构建 Ghostscript 命令
+    let gs_command = find_ghostscript_command()
+        .await
+        .ok_or_else(|| AppError::with_details(ErrorCode::EngineUnavailable, tr!(Text::GhostscriptNotFound)))?;
+    let limits = job.options.process_limits;
+    // 需要密码的输入先用 lopdf 解密到私有临时目录，-sPDFPassword 会让其他用户通过 ps 看到密码
+    let decrypted = match &job.options.password {
+        Some(password) => DecryptedCopy::create(&job.input_path, password)?,
+        None => None,
+    };
+    let input_path = decrypted.as_ref().map_or(job.input_path.as_path(), DecryptedCopy::path);
+    let mut args = build_ghostscript_args(&job.profile, input_path, &job.output_path);
+
+    // -dQUIET 会同时屏蔽逐页输出，去掉后才能得到进度和页数
+    args.retain(|arg| arg != "-dQUIET");
+
+    // 页面选择，Ghostscript 按文档顺序输出选中的页面
+    let selection = job.page_selection()?;
+    if let Some(selection) = &selection {
+        let input = args.pop().unwrap_or_default();
+        args.extend(page_args(selection));
+        args.push(input);
+    }
+
+    // pdfwrite 只能写出 RC4 加密 (-dEncryptionR=2/3)，输出加密由 compress_file 在压缩后统一用 AES 完成，
+    // 这里不传 -sOwnerPassword 等参数
+
+    // PDF/A 的输出意图定义文件也要放在输入文件之前，运行结束后删除
+    let pdfa_resources = match job.profile.pdfa {
+        Some(_) => {
+            let resources = PdfaResources::prepare(&job.output_path)?;
+            let input = args.pop().unwrap_or_default();
+            args.extend(resources.args());
+            args.push(input);
+            Some(resources)
+        }
+        None => None,
+    };
+
+    let mut cmd = Command::new(&gs_command);
+    cmd.args(&args).stdout(Stdio::piped()).stderr(Stdio::piped());
+    limits.apply(&mut cmd);
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| AppError::with_details(ErrorCode::EngineFailed, tr!(Text::GhostscriptSpawnFailed, e)))?;
+
+    // 同时读取 stderr，避免管道缓冲区写满后 Ghostscript 阻塞
+    let stderr = child.stderr.take();
+    let stderr_task = tokio::spawn(async move {
+        let mut buffer = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut buffer).await;
+        }
+        buffer
+    });
+
+    let stdout = child.stdout.take();
+    let mut pages = PageProgress { selection: selection.clone(), ..PageProgress::default() };
+    let outcome = {
+        let run = async {
+            // 逐行解析页面进度
+            if let Some(stdout) = stdout {
+                let mut lines = BufReader::new(stdout).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if let (Some(reporter), Some((current, total))) = (&job.progress, pages.parse_line(&line)) {
+                        reporter.report(&job.input_path, ProgressUnit::Page, current, total);
+                    }
+                }
+            }
+            child.wait().await
+        };
+
+        let timeout = async {
+            match limits.timeout() {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            status = run => Ok(status),
+            _ = job.cancel.cancelled() => Err(AppError::new(ErrorCode::Cancelled)),
+            _ = timeout => Err(AppError::with_details(
+                ErrorCode::EngineTimeout,
+                tr!(Text::GhostscriptTimedOut, limits.timeout_secs.unwrap_or_default()),
+            )),
+        }
+    };
+
+    let status = match outcome {
+        Ok(status) => status,
+        Err(e) => {
+            // 结束进程并等待其退出，之后才能安全地删除部分输出
+            let _ = child.kill().await;
+            return Err(e);
+        }
+    };
+
+    let status = status
+        .map_err(|e| AppError::with_details(ErrorCode::EngineFailed, tr!(Text::GhostscriptSpawnFailed, e)))?;
+    let error_msg = stderr_task.await.unwrap_or_default();
+    drop(pdfa_resources);
+
+    if status.success() {
+        if let (Some(selection), Some(0)) = (&selection, pages.total()) {
+            return Err(AppError::with_details(
+                ErrorCode::InvalidOptions,
+                tr!(Text::NoPagesSelected, selection, pages.last),
+            ));
+        }
+
+        let (color, sanitized) = postprocess(job)?;
+        if let Some(conformance) = job.profile.pdfa {
+            pdfa::verify(job, conformance)?;
+        }
+
+        // 成功时 stderr 中的内容是 Ghostscript 对输入文件问题的警告
+        let mut warnings: Vec<String> = error_msg
+            .lines()
+            .map(|line| line.trim().trim_start_matches('*').trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_string())
+            .collect();
+
+        if let Some(report) = color.filter(|report| report.images_skipped > 0) {
+            warnings.push(tr!(Text::ColorImagesSkipped, report.images_skipped));
+        }
+
+        Ok(EngineOutput {
+            page_count: pages.total(),
+            warnings,
+            color,
+            sanitize: sanitized,
+            ..EngineOutput::default()
+        })
+    } else if error_msg.to_lowercase().contains("password") {
+        // 加密文件缺少密码或密码错误时 Ghostscript 输出 "This file requires a password for access."
+        let code = if job.options.password.is_some() {
+            ErrorCode::InvalidPassword
+        } else {
+            ErrorCode::EncryptedInput
+        };
+        Err(AppError::with_details(code, error_msg.trim()))
+    } else {
+        Err(AppError::with_details(
+            ErrorCode::EngineFailed,
+            tr!(Text::GhostscriptFailed, error_msg.trim()),
+        ))
+    }
+}
+
+/// 用 lopdf 处理 Ghostscript 的输出
+///
+/// pdfwrite 只能把颜色转换为灰度，黑白模式下的图像二值化在这里完成；pdfwrite 还会保留部分脚本和附件，
+/// 净化同样在输出上进行。lopdf 加载时会展开对象流，保存前重新压缩其余的流，并按选项重新写出对象流，
+/// 避免处理节省的空间被结构上的膨胀抵消。
+fn postprocess(job: &CompressionJob) -> Result<(Option<ColorReport>, Option<SanitizeReport>), AppError> {
+    let binarize = job.profile.color_mode == ColorMode::Bilevel;
+    if !binarize && !job.options.sanitize {
+        return Ok((None, None));
+    }
+    let failed = |e: String| AppError::with_details(ErrorCode::EngineFailed, tr!(Text::CannotPostprocess, e));
+
+    let mut document = Document::load(&job.output_path).map_err(|e| failed(e.to_string()))?;
+    let sanitized = job.options.sanitize.then(|| sanitize(&mut document));
+    let color = binarize.then(|| convert_images(&mut document, ColorMode::Bilevel, job.profile.jpeg_quality));
+
+    let sanitized_any = sanitized.is_some_and(|report| report.total() > 0);
+    let binarized_any = color.is_some_and(|report| report.images_converted > 0);
+    if !sanitized_any && !binarized_any {
+        return Ok((color, sanitized));
+    }
+    if sanitized_any {
+        document.prune_objects();
+    }
+    document.compress();
+    let saved = if job.options.object_streams {
+        save_with_object_streams(&mut document, &job.output_path)
+    } else {
+        document.save(&job.output_path).map(|_| ())
+    };
+    saved.map_err(|e| failed(e.to_string()))?;
+    Ok((color, sanitized))
+}
+
+/// 页面选择参数，单个连续区间使用 -dFirstPage/-dLastPage，其他情况使用 -sPageList
+fn page_args(selection: &PageSelection) -> Vec<String> {
+    match selection.single_range() {
+        Some((first, last)) => std::iter::once(format!("-dFirstPage={}", first))
+            .chain(last.map(|last| format!("-dLastPage={}", last)))
+            .collect(),
+        None => vec![format!("-sPageList={}", selection)],
+    }
+}
+
+/// 解析 Ghostscript 的 "Processing pages 1 through N." 和 "Page N" 输出
+///
+/// 指定页面选择时只统计区间内被选中的页面，不同版本的 Ghostscript 报告的区间可能是
+/// 整个文档，也可能已经按 -dFirstPage/-dLastPage 缩小。
+#[derive(Default)]
+struct PageProgress {
+    first: usize,
+    last: usize,
+    selection: Option<PageSelection>,
+}
+
+impl PageProgress {
+    fn total(&self) -> Option<usize> {
+        (self.last >= self.first && self.last > 0).then(|| self.count_between(self.first, self.last))
+    }
+
+    fn count_between(&self, first: usize, last: usize) -> usize {
+        match &self.selection {
+            Some(selection) => selection.count_between(first, last),
+            None => last - first + 1,
+        }
+    }
+
+    /// 返回 (当前页序号, 总页数)，序号从 1 开始
+    fn parse_line(&mut self, line: &str) -> Option<(usize, usize)> {
+        let line = line.trim();
+
+        if let Some(range) = line.strip_prefix("Processing pages ") {
+            let mut numbers = range
+                .trim_end_matches('.')
+                .split(" through ")
+                .map(|number| number.trim().parse::<usize>());
+            if let (Some(Ok(first)), Some(Ok(last))) = (numbers.next(), numbers.next()) {
+                self.first = first;
+                self.last = last;
+            }
+            return None;
+        }
+
+        let page: usize = line.strip_prefix("Page ")?.trim().parse().ok()?;
+        if self.last < self.first || page < self.first {
+            return None;
+        }
+
+        let total = self.count_between(self.first, self.last);
+        Some((self.count_between(self.first, page).min(total), total))
+    }
+}
+
+/// 根据压缩配置构建完整的 Ghostscript 参数列表
+pub fn build_ghostscript_args(profile: &CompressionProfile, input_path: &Path, output_path: &Path) -> Vec<String> {
+    let mut args: Vec<String> = vec![
+        "-sDEVICE=pdfwrite".to_string(),
+        format!("-dCompatibilityLevel={}", profile.output_compatibility_level()),
+        format!("-dPDFSETTINGS={}", profile.base.as_arg()),
+    ];
+
+    args.extend(
+        [
+            "-dNOPAUSE",
+            "-dQUIET",
+            "-dBATCH",
+            "-dSAFER",
+            "-dAutoRotatePages=/None",
+            "-dDownsampleColorImages=true",
+            "-dDownsampleGrayImages=true",
+            "-dDownsampleMonoImages=true",
+            "-dEncodeColorImages=true",
+            "-dEncodeGrayImages=true",
+            "-dOptimize=true",
+            "-dNOPLATFONTS",
+        ]
+        .iter()
+        .map(|arg| arg.to_string()),
+    );
+
+    // 图像分辨率和降采样参数
+    args.extend([
+        format!("-dColorImageResolution={}", profile.color_resolution),
+        format!("-dGrayImageResolution={}", profile.gray_resolution),
+        format!("-dMonoImageResolution={}", profile.mono_resolution),
+        format!("-dColorImageDownsampleType={}", profile.downsample_method.as_arg()),
+        format!("-dGrayImageDownsampleType={}", profile.downsample_method.as_arg()),
+        format!("-dColorImageDownsampleThreshold={}", profile.downsample_threshold),
+        format!("-dGrayImageDownsampleThreshold={}", profile.downsample_threshold),
+        format!("-dJPEGQ={}", profile.jpeg_quality),
+    ]);
+    args.extend(filter_args("Color", profile.color_filter));
+    args.extend(filter_args("Gray", profile.gray_filter));
+
+    // PDF/A 不允许设备相关的颜色，统一转换为 RGB 以匹配 sRGB 输出意图
+    let color_mode = if profile.pdfa.is_some() { ColorMode::Rgb } else { profile.color_mode };
+    args.extend(color_args(color_mode));
+
+    // 无法转换的特性被移除而不是放弃 PDF/A
+    if let Some(conformance) = profile.pdfa {
+        args.extend([
+            format!("-dPDFA={}", conformance.part()),
+            "-dPDFACompatibilityPolicy=1".to_string(),
+            "-dNOOUTERSAVE".to_string(),
+        ]);
+    }
+
+    // 字体参数
+    args.extend([
+        format!("-dEmbedAllFonts={}", profile.embed_fonts),
+        format!("-dSubsetFonts={}", profile.subset_fonts),
+        format!("-dCompressFonts={}", profile.compress_fonts),
+    ]);
+
+    if profile.preserve_annotations {
+        args.push("-dPreserveAnnots=true".to_string());
+        args.push("-dPreserveMarkedContent=true".to_string());
+    }
+    args.extend(annotation_args(profile.form_mode, profile.remove_comments));
+
+    // 添加输出和输入文件
+    args.push(format!("-sOutputFile={}", output_path.to_string_lossy()));
+    args.push(input_path.to_string_lossy().to_string());
+
+    args
+}
+
+/// 颜色转换参数，黑白模式先转换为灰度
+fn color_args(mode: ColorMode) -> Vec<String> {
+    let (strategy, model) = match mode {
+        ColorMode::Leave => return vec!["-dColorConversionStrategy=/LeaveColorUnchanged".to_string()],
+        ColorMode::Rgb => ("RGB", "DeviceRGB"),
+        ColorMode::Gray | ColorMode::Bilevel => ("Gray", "DeviceGray"),
+    };
+    vec![
+        format!("-sColorConversionStrategy={}", strategy),
+        format!("-sProcessColorModel={}", model),
+    ]
+}
+
+/// 注释处理参数
+///
+/// ShowAnnotTypes 中的注释会被绘制，PreserveAnnotTypes 中的注释保留为注释：
+/// 只绘制不保留的类型被平展到页面内容中，两者都不包含的类型被删除。
+/// 两个参数都是 PostScript 名称数组，必须用 -d 传入，-s 会把整个数组当作一个字符串。
+fn annotation_args(form_mode: FormMode, remove_comments: bool) -> Vec<String> {
+    if form_mode == FormMode::Keep && !remove_comments {
+        return Vec::new();
+    }
+
+    let mut shown: Vec<&str> = NON_COMMENT_SUBTYPES.to_vec();
+    if !remove_comments {
+        shown.extend(COMMENT_SUBTYPES);
+    }
+    if form_mode == FormMode::Remove {
+        shown.retain(|&subtype| subtype != "Widget");
+    }
+    let mut preserved = shown.clone();
+    if form_mode == FormMode::Flatten {
+        preserved.retain(|&subtype| subtype != "Widget");
+    }
+
+    let names = |subtypes: &[&str]| subtypes.iter().map(|subtype| format!("/{}", subtype)).collect::<Vec<_>>().join(" ");
+    vec![
+        "-dShowAnnots=true".to_string(),
+        "-dPreserveAnnots=true".to_string(),
+        format!("-dShowAnnotTypes=[{}]", names(&shown)),
+        format!("-dPreserveAnnotTypes=[{}]", names(&preserved)),
+    ]
+}
+
+/// 图像编码方式参数，`kind` 为 "Color" 或 "Gray"
+fn filter_args(kind: &str, filter: ImageFilter) -> Vec<String> {
+    match filter {
+        ImageFilter::Auto => vec![format!("-dAutoFilter{}Images=true", kind)],
+        ImageFilter::Jpeg => vec![
+            format!("-dAutoFilter{}Images=false", kind),
+            format!("-d{}ImageFilter=/DCTEncode", kind),
+        ],
+        ImageFilter::Lossless => vec![
+            format!("-dAutoFilter{}Images=false", kind),
+            format!("-d{}ImageFilter=/FlateEncode", kind),
+        ],
+    }
+}
+
+pub(crate) async fn is_ghostscript_available() -> bool {
+    find_ghostscript_command().await.is_some()
+}
+
+pub(crate) fn get_bundled_ghostscript_path() -> Result<String, AppError> {
+    let app_data_dir = get_app_data_dir()?;
+    let gs_dir = app_data_dir.join("ghostscript");
+
+    let gs_executable = if cfg!(target_os = "windows") {
+        "gs.exe"
+    } else {
+        "gs"
+    };
+
+    let gs_path = gs_dir.join(gs_executable);
+    Ok(gs_path.to_string_lossy().to_string())
+}
+
+/// 查找可用的 Ghostscript 命令，结果会被缓存，安装或卸载后需要调用 `refresh_ghostscript_command`
+pub(crate) async fn find_ghostscript_command() -> Option<String> {
+    let mut cached = GHOSTSCRIPT_COMMAND.lock().await;
+    if let Some(command) = cached.as_ref() {
+        return command.clone();
+    }
+
+    let command = detect_ghostscript_command().await;
+    *cached = Some(command.clone());
+    command
+}
+
+/// 清除缓存的检测结果，下次使用时重新检测
+pub(crate) async fn refresh_ghostscript_command() {
+    *GHOSTSCRIPT_COMMAND.lock().await = None;
+}
+
+async fn detect_ghostscript_command() -> Option<String> {
+    // 首先检查系统安装的 Ghostscript，支持不同平台的 gs 命令
+    for cmd in ["gs", "gswin64c", "gswin32c"] {
+        if probe_ghostscript(cmd).await {
+            return Some(cmd.to_string());
+        }
+    }
+
+    // 如果系统没有安装，尝试使用捆绑的版本
+    if let Ok(bundled_path) = get_bundled_ghostscript_path() {
+        if std::path::Path::new(&bundled_path).exists() {
+            return Some(bundled_path);
+        }
+    }
+
+    None
+}
+
+/// 运行 `gs --version` 检查命令是否可用
+async fn probe_ghostscript(cmd: &str) -> bool {
+    let mut command = Command::new(cmd);
+    command
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+
+    match tokio::time::timeout(PROBE_TIMEOUT, command.status()).await {
+        Ok(Ok(status)) => status.success(),
+        _ => false,
+    }
+}
+
+pub(crate) fn get_app_data_dir() -> Result<PathBuf, AppError> {
+
+    // 获取应用数据目录
+    let app_data = if cfg!(target_os = "windows") {
+        // 在Windows上，尝试使用LOCALAPPDATA而不是APPDATA，避免漫游配置文件的问题
+        std::env::var("LOCALAPPDATA")
+            .or_else(|_| std::env::var("APPDATA"))
+            .map(PathBuf::from)
+            .map_err(|_| AppError::with_details(ErrorCode::Internal, tr!(Text::AppDataDirUnavailable)))?
+    } else if cfg!(target_os = "macos") {
+        std::env::var("HOME")
+            .map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+            .map_err(|_| AppError::with_details(ErrorCode::Internal, tr!(Text::HomeDirUnavailable)))?
+    } else {
+        std::env::var("HOME")
+            .map(|home| PathBuf::from(home).join(".local").join("share"))
+            .map_err(|_| AppError::with_details(ErrorCode::Internal, tr!(Text::HomeDirUnavailable)))?
+    };
+
+    let app_dir = app_data.join("PDF_Compressor");
+
+    // 确保目录存在
+    if !app_dir.exists() {
+        std::fs::create_dir_all(&app_dir)
+            .map_err(|e| AppError::with_details(ErrorCode::Internal, tr!(Text::CannotCreateAppDataDir, e)))?;
+    }
+
+    Ok(app_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn types(args: &[String], key: &str) -> Vec<String> {
+        let prefix = format!("-d{}=[", key);
+        let arg = args.iter().find_map(|arg| arg.strip_prefix(&prefix)).unwrap();
+        arg.trim_end_matches(']').split(' ').map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn keeping_everything_adds_no_annotation_args() {
+        assert!(annotation_args(FormMode::Keep, false).is_empty());
+    }
+
+    #[test]
+    fn annotation_args_flatten_forms_and_drop_comments() {
+        let args = annotation_args(FormMode::Flatten, true);
+        let shown = types(&args, "ShowAnnotTypes");
+        let preserved = types(&args, "PreserveAnnotTypes");
+
+        assert_eq!(shown.len(), NON_COMMENT_SUBTYPES.len());
+        assert!(shown.iter().all(|name| name.starts_with('/')));
+        assert!(shown.contains(&"/Widget".to_string()));
+        assert!(!preserved.contains(&"/Widget".to_string()));
+        assert!(preserved.contains(&"/Link".to_string()));
+        assert!(!shown.contains(&"/Highlight".to_string()));
+    }
+
+    #[test]
+    fn annotation_args_remove_forms_and_keep_comments() {
+        let args = annotation_args(FormMode::Remove, false);
+        let shown = types(&args, "ShowAnnotTypes");
+
+        assert_eq!(shown.len(), NON_COMMENT_SUBTYPES.len() + COMMENT_SUBTYPES.len() - 1);
+        assert!(!shown.contains(&"/Widget".to_string()));
+        assert_eq!(types(&args, "PreserveAnnotTypes"), shown);
+    }
+}
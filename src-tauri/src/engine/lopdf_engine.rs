@@ -0,0 +1,209 @@
+use super::cancel::CancelToken;
+use super::encryption::load_document;
+use super::{
+    ColorMode, CompressionBackend, CompressionJob, CompressionOptions, CompressionProfile, EngineCapabilities,
+    EngineOutput, PdfSettings, ProgressUnit,
+};
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Text;
+use crate::tr;
+use crate::optimize::annotations::process_annotations;
+use crate::optimize::cleanup::{strip_metadata, CleanupOptions};
+use crate::optimize::color::convert_colors;
+use crate::optimize::dedup::deduplicate_objects;
+use crate::optimize::images::{optimize_images_with_progress, ImageSettings};
+use crate::optimize::object_streams::save_with_object_streams;
+use crate::optimize::pages::select_pages;
+use crate::optimize::sanitize::sanitize;
+use futures_util::future::BoxFuture;
+use lopdf::Document;
+use std::ops::ControlFlow;
+
+/// 纯 Rust 实现的回退引擎，无需任何外部依赖
+pub struct LopdfBackend;
+
+impl CompressionBackend for LopdfBackend {
+    fn name(&self) -> &'static str {
+        "lopdf"
+    }
+
+    fn is_available(&self) -> BoxFuture<'_, bool> {
+        Box::pin(async { true })
+    }
+
+    fn capabilities(&self) -> EngineCapabilities {
+        EngineCapabilities {
+            image_downsampling: true,
+            font_subsetting: false,
+            external_process: false,
+            pdfa: false,
+        }
+    }
+
+    fn compress<'a>(&'a self, job: &'a CompressionJob) -> BoxFuture<'a, Result<EngineOutput, AppError>> {
+        Box::pin(async move {
+            let job = job.clone();
+            // lopdf 的处理是纯 CPU 计算，放到阻塞线程池中执行
+            tokio::task::spawn_blocking(move || compress_with_enhanced_lopdf(&job))
+                .await
+                .map_err(|e| AppError::with_details(ErrorCode::Internal, tr!(Text::LopdfTaskPanicked, e)))?
+        })
+    }
+
+    fn result_note(&self) -> Option<String> {
+        Some(tr!(Text::InstallGhostscriptHint))
+    }
+}
+
+fn compress_with_enhanced_lopdf(job: &CompressionJob) -> Result<EngineOutput, AppError> {
+    // 使用增强的 lopdf 压缩，加密的文档在加载时解密
+    let (mut document, encryption) = load_document(&job.input_path, job.options.password.as_deref())?;
+    check_cancelled(&job.cancel)?;
+
+    // 先删除未选中的页面并回收其对象，之后的步骤不再处理这些页面上的图像
+    if let Some(selection) = job.page_selection()? {
+        if select_pages(&mut document, &selection) == 0 {
+            return Err(AppError::with_details(
+                ErrorCode::InvalidOptions,
+                tr!(Text::NoPagesSelected, selection, document.get_pages().len()),
+            ));
+        }
+        document.prune_objects();
+    }
+
+    // 元数据清理默认只在高压缩等级下开启
+    let cleanup_options = job.options.cleanup.unwrap_or_else(|| {
+        if matches!(job.profile.base, PdfSettings::Screen | PdfSettings::Ebook) {
+            CleanupOptions::all()
+        } else {
+            CleanupOptions::none()
+        }
+    });
+
+    let images = image_settings(&job.profile);
+
+    // 图像处理占用了绝大部分时间，按图像对象报告进度并检查是否被取消
+    let mut progress = |current, total| {
+        if let Some(reporter) = &job.progress {
+            reporter.report(&job.input_path, ProgressUnit::Object, current, total);
+        }
+        if job.cancel.is_cancelled() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    };
+
+    // 执行增强的压缩优化
+    let mut output = enhanced_pdf_optimization(
+        &mut document,
+        &images,
+        &job.profile,
+        &cleanup_options,
+        &job.options,
+        &job.cancel,
+        &mut progress,
+    )?;
+
+    check_cancelled(&job.cancel)?;
+
+    // 加密输出时对象需要单独加密，无法放入对象流
+    let encrypt = job.options.encryption.is_some() || (job.options.reencrypt && encryption.is_some());
+    if job.options.object_streams && encrypt {
+        output
+            .warnings
+            .push(tr!(Text::EncryptedNoObjectStreams));
+    }
+
+    // 保存压缩后的PDF，加密时 `encrypt_file` 会重写所有对象，不必先构建对象流
+    let saved = if job.options.object_streams && !encrypt {
+        save_with_object_streams(&mut document, &job.output_path)
+    } else {
+        document.save(&job.output_path).map(|_| ())
+    };
+    saved.map_err(|e| AppError::with_details(ErrorCode::OutputWriteFailed, tr!(Text::SaveFailed, e)))?;
+
+    Ok(output)
+}
+
+/// 压缩配置对应的图像参数，lopdf 引擎只有一个目标分辨率，使用彩色图像的设置
+fn image_settings(profile: &CompressionProfile) -> ImageSettings {
+    ImageSettings {
+        target_dpi: profile.color_resolution as f32,
+        downsample_threshold: profile.downsample_threshold,
+        jpeg_quality: profile.jpeg_quality,
+        reencode_lossless: profile.reencode_lossless(),
+    }
+}
+
+fn enhanced_pdf_optimization(
+    document: &mut Document,
+    image_settings: &ImageSettings,
+    profile: &CompressionProfile,
+    cleanup_options: &CleanupOptions,
+    options: &CompressionOptions,
+    cancel: &CancelToken,
+    progress: &mut dyn FnMut(usize, usize) -> ControlFlow<()>,
+) -> Result<EngineOutput, AppError> {
+    // 先净化，删除的多媒体注释和隐藏内容不再参与之后的处理
+    let sanitized = options.sanitize.then(|| sanitize(document));
+
+    // 平展的外观流成为页面内容的一部分，之后的颜色转换也会处理它们
+    let annotations = process_annotations(document, profile.form_mode, profile.remove_comments);
+
+    // 颜色转换放在降采样之前，转换后的图像继续按压缩等级降采样
+    let color = (profile.color_mode != ColorMode::Leave)
+        .then(|| convert_colors(document, profile.color_mode, image_settings.jpeg_quality));
+    check_cancelled(cancel)?;
+
+    // 图像降采样和重新编码，必须在 compress() 之前执行，避免 JPEG 数据被再次压缩
+    let (_, flow) = optimize_images_with_progress(document, image_settings, progress);
+    if flow.is_break() {
+        return Err(AppError::new(ErrorCode::Cancelled));
+    }
+
+    // 执行基本的 PDF 结构优化
+    document.compress();
+    check_cancelled(cancel)?;
+
+    // 清理元数据和应用程序私有数据
+    let cleanup = strip_metadata(document, cleanup_options);
+
+    // 合并重复对象，放在清理之后，因为去掉元数据后更多对象会变得相同
+    let dedup = options.deduplicate.then(|| deduplicate_objects(document));
+    check_cancelled(cancel)?;
+
+    // 执行垃圾回收，移除未引用的对象
+    document.prune_objects();
+
+    let mut warnings = Vec::new();
+    if let Some(report) = color {
+        if report.images_skipped > 0 {
+            warnings.push(tr!(Text::ColorImagesSkipped, report.images_skipped));
+        }
+        if report.operators_skipped > 0 {
+            warnings.push(tr!(Text::ColorOperatorsSkipped, report.operators_skipped));
+        }
+    }
+    if annotations.fields_not_flattened > 0 {
+        warnings.push(tr!(Text::FormFieldsNotFlattened, annotations.fields_not_flattened));
+    }
+
+    Ok(EngineOutput {
+        cleanup: Some(cleanup),
+        dedup,
+        color,
+        annotations: Some(annotations),
+        sanitize: sanitized,
+        page_count: Some(document.get_pages().len()),
+        warnings,
+    })
+}
+
+fn check_cancelled(cancel: &CancelToken) -> Result<(), AppError> {
+    if cancel.is_cancelled() {
+        Err(AppError::new(ErrorCode::Cancelled))
+    } else {
+        Ok(())
+    }
+}
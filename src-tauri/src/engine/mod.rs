@@ -0,0 +1,540 @@
+// 压缩引擎抽象层
+//
+// 每个引擎只负责把输入文件压缩到输出路径，文件大小统计、压缩率计算等
+// 公共逻辑统一在 `run_compression` 中完成。
+
+mod batch;
+mod cancel;
+mod encryption;
+mod ghostscript;
+mod lopdf_engine;
+mod output;
+mod pdfa;
+mod process;
+mod profile;
+mod progress;
+mod result;
+mod target_size;
+
+pub use batch::{compress_batch, discover_pdfs, BatchEntry, BatchFileResult};
+pub use cancel::CancelToken;
+pub use encryption::{EncryptionInfo, EncryptionMethod, EncryptionSettings, Permissions};
+pub use ghostscript::GhostscriptBackend;
+pub use lopdf_engine::LopdfBackend;
+pub use pdfa::PdfaConformance;
+pub use crate::optimize::analysis::{
+    CategorySize, FontInfo, ImageInfo, ObjectSize, SizeAnalysis, SizeCategory, DEFAULT_TOP_OBJECTS,
+};
+pub use crate::optimize::annotations::FormMode;
+pub use crate::optimize::color::ColorMode;
+pub use crate::optimize::pages::PageSelection;
+pub use process::ProcessLimits;
+pub use profile::{CompressionProfile, DownsampleMethod, ImageFilter, ImageQuality, PdfSettings};
+pub use progress::{CompressionProgress, ProgressReporter, ProgressUnit};
+pub use result::{CompressionResult, JobStatus};
+pub use target_size::TargetSizeReport;
+
+pub(crate) use ghostscript::{
+    get_app_data_dir, get_bundled_ghostscript_path, is_ghostscript_available, refresh_ghostscript_command,
+};
+
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Text;
+use crate::optimize::annotations::AnnotationReport;
+use crate::optimize::cleanup::{CleanupOptions, CleanupReport};
+use crate::optimize::color::ColorReport;
+use crate::optimize::dedup::DedupReport;
+use crate::optimize::sanitize::SanitizeReport;
+use crate::tr;
+use encryption::SecurityHandler;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// 自动选择引擎时使用的名称
+pub const AUTO_ENGINE: &str = "auto";
+
+/// 一次压缩任务的输入参数
+#[derive(Debug, Clone)]
+pub struct CompressionJob {
+    pub input_path: PathBuf,
+    pub output_path: PathBuf,
+    pub profile: CompressionProfile,
+    pub options: CompressionOptions,
+    /// 进度回调，None 表示不需要报告进度
+    pub progress: Option<ProgressReporter>,
+    pub cancel: CancelToken,
+}
+
+impl CompressionJob {
+    /// 解析选项中的页面选择表达式，未指定时为 None
+    pub fn page_selection(&self) -> Result<Option<PageSelection>, AppError> {
+        self.options
+            .pages
+            .as_deref()
+            .map(PageSelection::parse)
+            .transpose()
+            .map_err(|details| AppError::with_details(ErrorCode::InvalidOptions, details))
+    }
+}
+
+/// 压缩命令的可选参数，未指定的字段使用默认值
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CompressionOptions {
+    /// 自定义压缩配置，指定后忽略压缩等级
+    pub profile: Option<CompressionProfile>,
+    /// 元数据清理选项，None 表示按压缩等级使用默认值
+    pub cleanup: Option<CleanupOptions>,
+    /// 使用对象流和交叉引用流保存 (PDF 1.5)，仅 lopdf 引擎支持
+    pub object_streams: bool,
+    /// 合并内容完全相同的重复对象，仅 lopdf 引擎支持
+    pub deduplicate: bool,
+    /// 外部进程（Ghostscript）的超时和资源限制
+    pub process_limits: ProcessLimits,
+    /// 目标文件大小（字节），指定后自动搜索能满足大小限制的最高图像质量
+    pub target_size: Option<u64>,
+    /// 压缩结果不比原文件小时，用原文件替换输出
+    pub keep_original_if_larger: bool,
+    /// 原地压缩（输出路径与输入相同）时把原文件备份为 `*.bak`
+    pub backup_in_place: bool,
+    /// 加密输入的用户密码或所有者密码
+    pub password: Option<String>,
+    /// 用原文件的密码和权限重新加密输出，否则输出不加密
+    pub reencrypt: bool,
+    /// 用新的密码和权限加密输出，指定后忽略 `reencrypt`
+    pub encryption: Option<EncryptionSettings>,
+    /// 只保留选中的页面，例如 `1-5,8,10-`，None 表示保留全部页面
+    pub pages: Option<String>,
+    /// 移除 JavaScript、附件、多媒体和隐藏图层，用于对外发送的文件
+    pub sanitize: bool,
+}
+
+impl Default for CompressionOptions {
+    fn default() -> Self {
+        Self {
+            profile: None,
+            cleanup: None,
+            object_streams: true,
+            deduplicate: true,
+            process_limits: ProcessLimits::default(),
+            target_size: None,
+            keep_original_if_larger: true,
+            backup_in_place: true,
+            password: None,
+            reencrypt: false,
+            encryption: None,
+            pages: None,
+            sanitize: false,
+        }
+    }
+}
+
+/// 确定压缩任务使用的配置：优先使用自定义配置，否则使用压缩等级对应的内置配置
+pub fn resolve_profile(compression_level: &str, options: &CompressionOptions) -> Result<CompressionProfile, AppError> {
+    match &options.profile {
+        Some(profile) => Ok(profile.clone()),
+        None => CompressionProfile::from_level(compression_level),
+    }
+}
+
+/// 引擎在压缩过程中产生的附加信息
+#[derive(Debug, Clone, Default)]
+pub struct EngineOutput {
+    pub cleanup: Option<CleanupReport>,
+    pub dedup: Option<DedupReport>,
+    pub color: Option<ColorReport>,
+    pub annotations: Option<AnnotationReport>,
+    pub sanitize: Option<SanitizeReport>,
+    pub page_count: Option<usize>,
+    pub warnings: Vec<String>,
+}
+
+/// 引擎支持的能力，供前端展示和自动选择时参考
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct EngineCapabilities {
+    /// 是否能对图像进行降采样和重新编码
+    pub image_downsampling: bool,
+    /// 是否能对字体进行子集化
+    pub font_subsetting: bool,
+    /// 是否依赖外部进程
+    pub external_process: bool,
+    /// 是否能输出 PDF/A
+    pub pdfa: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EngineInfo {
+    pub name: String,
+    pub available: bool,
+    pub capabilities: EngineCapabilities,
+}
+
+pub trait CompressionBackend: Send + Sync {
+    /// 引擎的唯一名称，用于命令参数中显式指定引擎
+    fn name(&self) -> &'static str;
+
+    /// 检查引擎在当前环境中是否可用
+    fn is_available(&self) -> BoxFuture<'_, bool>;
+
+    fn capabilities(&self) -> EngineCapabilities;
+
+    /// 将 `job.input_path` 压缩并写入 `job.output_path`
+    fn compress<'a>(&'a self, job: &'a CompressionJob) -> BoxFuture<'a, Result<EngineOutput, AppError>>;
+
+    /// 附加在压缩结果消息后的提示，例如推荐安装更好的引擎
+    fn result_note(&self) -> Option<String> {
+        None
+    }
+}
+
+/// 已注册的压缩引擎，按注册顺序决定自动选择时的优先级
+#[derive(Default)]
+pub struct BackendRegistry {
+    backends: Vec<Arc<dyn CompressionBackend>>,
+}
+
+impl BackendRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 内置引擎：优先 Ghostscript，回退到 lopdf
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(Arc::new(GhostscriptBackend));
+        registry.register(Arc::new(LopdfBackend));
+        registry
+    }
+
+    /// 注册一个引擎，同名引擎会被替换
+    pub fn register(&mut self, backend: Arc<dyn CompressionBackend>) {
+        self.backends.retain(|existing| existing.name() != backend.name());
+        self.backends.push(backend);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn CompressionBackend>> {
+        self.backends
+            .iter()
+            .find(|backend| backend.name() == name)
+            .cloned()
+    }
+
+    pub fn backends(&self) -> &[Arc<dyn CompressionBackend>] {
+        &self.backends
+    }
+
+    /// 根据名称选择引擎，`auto` 表示选择第一个可用的引擎
+    pub async fn select(&self, engine: &str) -> Result<Arc<dyn CompressionBackend>, AppError> {
+        if engine == AUTO_ENGINE {
+            for backend in &self.backends {
+                if backend.is_available().await {
+                    return Ok(backend.clone());
+                }
+            }
+            return Err(AppError::with_details(ErrorCode::EngineUnavailable, tr!(Text::NoEngineAvailable)));
+        }
+
+        let backend = self
+            .get(engine)
+            .ok_or_else(|| AppError::with_details(ErrorCode::UnknownEngine, engine))?;
+
+        if !backend.is_available().await {
+            return Err(AppError::with_details(ErrorCode::EngineUnavailable, engine));
+        }
+
+        Ok(backend)
+    }
+
+    pub async fn engine_infos(&self) -> Vec<EngineInfo> {
+        let mut infos = Vec::with_capacity(self.backends.len());
+        for backend in &self.backends {
+            infos.push(EngineInfo {
+                name: backend.name().to_string(),
+                available: backend.is_available().await,
+                capabilities: backend.capabilities(),
+            });
+        }
+        infos
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref ENGINE_REGISTRY: BackendRegistry = BackendRegistry::with_defaults();
+}
+
+/// 全局引擎注册表
+pub fn registry() -> &'static BackendRegistry {
+    &ENGINE_REGISTRY
+}
+
+/// 校验输入输出路径、选择引擎并执行压缩，供 Tauri 命令和命令行共同使用
+pub async fn compress_file(job: &CompressionJob, engine: &str) -> Result<CompressionResult, AppError> {
+    // 检查输入文件是否存在
+    if !job.input_path.exists() {
+        return Err(AppError::with_details(ErrorCode::InputMissing, job.input_path.display()));
+    }
+
+    job.profile.validate()?;
+    job.options.process_limits.validate()?;
+    job.page_selection()?;
+    if let Some(settings) = &job.options.encryption {
+        settings.validate()?;
+    }
+    if job.profile.pdfa.is_some() && (job.options.encryption.is_some() || job.options.reencrypt) {
+        return Err(AppError::with_details(ErrorCode::InvalidOptions, tr!(Text::PdfaForbidsEncryption)));
+    }
+
+    // 检查输出目录是否存在，如果不存在则创建
+    if let Some(parent) = job.output_path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AppError::with_details(ErrorCode::OutputWriteFailed, tr!(Text::CannotCreateOutputDir, e)))?;
+        }
+    }
+
+    // 加密的输入在选择引擎之前验证密码，缺少密码或密码错误时尽早失败
+    let encryption = encryption::open(&job.input_path, job.options.password.as_deref())?;
+    let created = job.options.encryption.as_ref().map(SecurityHandler::create).transpose()?;
+    let encrypt = match &created {
+        Some(handler) => Some(handler),
+        None => encryption.as_ref().filter(|_| job.options.reencrypt),
+    };
+
+    let backend = registry().select(engine).await?;
+    if let Some(conformance) = job.profile.pdfa.filter(|_| !backend.capabilities().pdfa) {
+        return Err(AppError::with_details(
+            ErrorCode::EngineUnavailable,
+            tr!(Text::PdfaUnsupportedEngine, backend.name(), conformance.label()),
+        ));
+    }
+    let started = Instant::now();
+
+    // 引擎写入临时文件，校验通过后再替换输出文件
+    let mut staged = job.clone();
+    staged.output_path = output::staging_path(&job.output_path);
+    let result = match job.options.target_size {
+        Some(target_size) => {
+            target_size::compress_to_target(backend.as_ref(), &staged, target_size, encrypt, started).await
+        }
+        None => run_compression(backend.as_ref(), &staged, encrypt, started).await,
+    };
+
+    // 取消或失败时只需删除临时文件，原有的输出文件保持不变
+    let discard = || {
+        let _ = std::fs::remove_file(&staged.output_path);
+    };
+    if job.cancel.is_cancelled() {
+        discard();
+        return Ok(CompressionResult::cancelled(backend.name(), job, started));
+    }
+    let mut result = result.inspect_err(|_| discard())?;
+
+    if let Some(handler) = &encryption {
+        result.encryption = Some(handler.info().clone());
+        if encrypt.is_none() && !result.kept_original {
+            result.warnings.push(tr!(Text::EncryptionRemoved));
+        }
+    }
+    if let Some(handler) = encrypt {
+        result.output_encryption = Some(handler.info().clone());
+        result.message = result.describe(backend.result_note().as_deref());
+    }
+
+    // 原地压缩没有效果时原文件就是结果，不需要替换
+    if result.kept_original && output::same_file(&job.input_path, &job.output_path) {
+        discard();
+        return Ok(result);
+    }
+
+    result.backup_path = output::commit_output(job, &staged.output_path).inspect_err(|_| discard())?;
+    if result.backup_path.is_some() {
+        result.message = result.describe(backend.result_note().as_deref());
+    }
+
+    Ok(result)
+}
+
+/// 分析 PDF 文件中各部分占用的空间，列出最大的 `top` 个对象；加密的文件需要提供密码
+pub async fn analyze_file(path: &Path, password: Option<&str>, top: usize) -> Result<SizeAnalysis, AppError> {
+    if !path.exists() {
+        return Err(AppError::with_details(ErrorCode::InputMissing, path.display()));
+    }
+
+    let path = path.to_path_buf();
+    let password = password.map(str::to_owned);
+    // 加载和遍历文档是纯 CPU 计算，放到阻塞线程池中执行
+    tokio::task::spawn_blocking(move || {
+        let file_size = std::fs::metadata(&path)
+            .map_err(|e| AppError::with_details(ErrorCode::InputUnreadable, e))?
+            .len();
+        let (document, _) = encryption::load_document(&path, password.as_deref())?;
+        Ok(crate::optimize::analysis::analyze(&document, file_size, top))
+    })
+    .await
+    .map_err(|e| AppError::with_details(ErrorCode::Internal, tr!(Text::AnalysisTaskPanicked, e)))?
+}
+
+/// 使用指定引擎执行压缩，并统一计算压缩结果
+async fn run_compression(
+    backend: &dyn CompressionBackend,
+    job: &CompressionJob,
+    encrypt: Option<&SecurityHandler>,
+    started: Instant,
+) -> Result<CompressionResult, AppError> {
+    // 获取原始文件大小
+    let original_size = std::fs::metadata(&job.input_path)
+        .map_err(|e| AppError::with_details(ErrorCode::InputUnreadable, e))?
+        .len();
+
+    let output = backend.compress(job).await?;
+    if let Some(handler) = encrypt {
+        encryption::encrypt_file(&job.output_path, handler)?;
+    }
+    let kept_original = output::keep_original_if_larger(job, original_size)?;
+
+    Ok(CompressionResult::completed(backend, job, original_size, output, None, kept_original, started))
+}
+
+pub fn format_file_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit_index = 0;
+
+    while size >= 1024.0 && unit_index < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit_index += 1;
+    }
+
+    if unit_index == 0 {
+        format!("{} {}", size as u64, UNITS[unit_index])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit_index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 只用于测试引擎选择的引擎，不执行压缩
+    struct FakeBackend {
+        name: &'static str,
+        available: bool,
+    }
+
+    impl CompressionBackend for FakeBackend {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn is_available(&self) -> BoxFuture<'_, bool> {
+            Box::pin(async move { self.available })
+        }
+
+        fn capabilities(&self) -> EngineCapabilities {
+            EngineCapabilities::default()
+        }
+
+        fn compress<'a>(&'a self, _job: &'a CompressionJob) -> BoxFuture<'a, Result<EngineOutput, AppError>> {
+            Box::pin(async { Ok(EngineOutput::default()) })
+        }
+    }
+
+    fn registry_of(backends: &[(&'static str, bool)]) -> BackendRegistry {
+        let mut registry = BackendRegistry::new();
+        for &(name, available) in backends {
+            registry.register(Arc::new(FakeBackend { name, available }));
+        }
+        registry
+    }
+
+    #[test]
+    fn default_registry_prefers_ghostscript() {
+        let registry = BackendRegistry::with_defaults();
+        let names: Vec<&str> = registry.backends().iter().map(|backend| backend.name()).collect();
+        assert_eq!(names, ["ghostscript", "lopdf"]);
+    }
+
+    #[test]
+    fn register_replaces_backend_with_same_name() {
+        let registry = registry_of(&[("a", false), ("b", true), ("a", true)]);
+        let names: Vec<&str> = registry.backends().iter().map(|backend| backend.name()).collect();
+        assert_eq!(names, ["b", "a"]);
+        assert!(registry.get("c").is_none());
+    }
+
+    #[tokio::test]
+    async fn auto_selects_first_available_backend() {
+        let registry = registry_of(&[("first", false), ("second", true), ("third", true)]);
+        let backend = registry.select(AUTO_ENGINE).await.unwrap();
+        assert_eq!(backend.name(), "second");
+    }
+
+    #[tokio::test]
+    async fn auto_fails_without_available_backend() {
+        let registry = registry_of(&[("first", false)]);
+        let error = registry.select(AUTO_ENGINE).await.err().unwrap();
+        assert_eq!(error.code, ErrorCode::EngineUnavailable);
+    }
+
+    #[tokio::test]
+    async fn explicit_selection_checks_name_and_availability() {
+        let registry = registry_of(&[("ready", true), ("missing", false)]);
+        assert_eq!(registry.select("ready").await.unwrap().name(), "ready");
+        assert_eq!(registry.select("missing").await.err().unwrap().code, ErrorCode::EngineUnavailable);
+        assert_eq!(registry.select("unknown").await.err().unwrap().code, ErrorCode::UnknownEngine);
+    }
+
+    #[tokio::test]
+    async fn engine_infos_follow_registration_order() {
+        let registry = registry_of(&[("first", false), ("second", true)]);
+        let infos = registry.engine_infos().await;
+        let summary: Vec<(&str, bool)> = infos.iter().map(|info| (info.name.as_str(), info.available)).collect();
+        assert_eq!(summary, [("first", false), ("second", true)]);
+    }
+
+    #[test]
+    fn options_default_to_lossless_settings() {
+        let options = CompressionOptions::default();
+        assert!(options.profile.is_none());
+        assert!(options.cleanup.is_none());
+        assert!(options.object_streams);
+        assert!(options.deduplicate);
+        assert_eq!(options.process_limits, ProcessLimits::default());
+        assert!(options.target_size.is_none());
+        assert!(options.keep_original_if_larger);
+        assert!(options.backup_in_place);
+        assert!(options.password.is_none());
+        assert!(!options.reencrypt);
+        assert!(options.encryption.is_none());
+    }
+
+    #[test]
+    fn missing_option_fields_use_defaults() {
+        let options: CompressionOptions = serde_json::from_str(r#"{ "object_streams": false }"#).unwrap();
+        assert!(!options.object_streams);
+        assert!(options.deduplicate);
+        assert!(options.keep_original_if_larger);
+
+        let empty: CompressionOptions = serde_json::from_str("{}").unwrap();
+        assert!(empty.object_streams);
+    }
+
+    #[test]
+    fn custom_profile_overrides_level() {
+        let options = CompressionOptions {
+            profile: Some(CompressionProfile::screen()),
+            ..CompressionOptions::default()
+        };
+        assert_eq!(resolve_profile("printer", &options).unwrap(), CompressionProfile::screen());
+        assert_eq!(
+            resolve_profile("printer", &CompressionOptions::default()).unwrap(),
+            CompressionProfile::printer()
+        );
+        let error = resolve_profile("unknown", &CompressionOptions::default()).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidProfile);
+    }
+}
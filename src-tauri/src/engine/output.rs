@@ -0,0 +1,262 @@
+// 输出文件的写入
+//
+// 引擎不直接写入输出路径，而是写入同一目录下的临时文件。压缩完成后先检查临时文件
+// 是一个完整的 PDF，再通过重命名原子地替换输出文件。这样引擎崩溃、超时或任务取消时
+// 不会留下不完整的输出，输出路径与输入文件相同（原地压缩）时原文件也不会在压缩过程中被破坏。
+
+use super::{ColorMode, CompressionJob, FormMode};
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Text;
+use crate::tr;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// PDF 头和文件结束标记允许出现的范围
+const MARKER_WINDOW: u64 = 1024;
+
+/// 输出路径旁的临时文件，以点开头以免在文件管理器中出现
+pub(super) fn staging_path(output_path: &Path) -> PathBuf {
+    let file_name = output_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    output_path.with_file_name(format!(".{}.tmp", file_name))
+}
+
+/// 原地压缩时原文件的备份路径
+pub(super) fn backup_path(input_path: &Path) -> PathBuf {
+    let mut file_name = input_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".bak");
+    input_path.with_file_name(file_name)
+}
+
+/// 任务要求改变文档本身时原文件不满足要求，不能用原文件代替压缩结果
+///
+/// 要求加密、PDF/A 输出、删除了页面、转换颜色、处理表单和批注或净化时，原文件没有相应的加密、
+/// 权限限制、归档标记、页面范围、颜色、注释或净化；重新加密时原文件虽然也是加密的，
+/// 但结果中不会再报告加密相关的提示。
+pub(super) fn requires_rewrite(job: &CompressionJob) -> bool {
+    job.options.reencrypt
+        || job.options.encryption.is_some()
+        || job.profile.pdfa.is_some()
+        || job.options.pages.is_some()
+        || job.profile.color_mode != ColorMode::Leave
+        || job.profile.form_mode != FormMode::Keep
+        || job.profile.remove_comments
+        || job.options.sanitize
+}
+
+/// 输出不比原文件小时按选项用原文件替换输出，返回是否保留了原文件
+pub(super) fn keep_original_if_larger(job: &CompressionJob, original_size: u64) -> Result<bool, AppError> {
+    if !job.options.keep_original_if_larger || requires_rewrite(job) || same_file(&job.input_path, &job.output_path) {
+        return Ok(false);
+    }
+
+    let compressed_size = fs::metadata(&job.output_path)
+        .map_err(|e| AppError::with_details(ErrorCode::OutputWriteFailed, tr!(Text::CannotReadOutputSize, e)))?
+        .len();
+    if compressed_size < original_size {
+        return Ok(false);
+    }
+
+    fs::copy(&job.input_path, &job.output_path).map_err(|e| AppError::with_details(ErrorCode::OutputWriteFailed, e))?;
+    Ok(true)
+}
+
+/// 校验临时文件并替换 `job.output_path`，原地压缩且开启备份时返回备份路径
+pub(super) fn commit_output(job: &CompressionJob, staged_path: &Path) -> Result<Option<PathBuf>, AppError> {
+    validate_pdf(staged_path)?;
+
+    let backup = if job.options.backup_in_place && same_file(&job.input_path, &job.output_path) {
+        let backup = backup_path(&job.input_path);
+        fs::copy(&job.input_path, &backup)
+            .map_err(|e| AppError::with_details(ErrorCode::OutputWriteFailed, tr!(Text::CannotCreateBackup, e)))?;
+        Some(backup)
+    } else {
+        None
+    };
+
+    fs::rename(staged_path, &job.output_path).map_err(|e| AppError::with_details(ErrorCode::OutputWriteFailed, e))?;
+    Ok(backup)
+}
+
+/// 检查文件开头的 `%PDF-` 头和末尾的 `%%EOF` 标记，引擎中途退出时通常缺少后者
+fn validate_pdf(path: &Path) -> Result<(), AppError> {
+    let invalid = |details: String| AppError::with_details(ErrorCode::InvalidOutput, details);
+
+    let mut file = File::open(path).map_err(|e| invalid(e.to_string()))?;
+    let size = file.metadata().map_err(|e| invalid(e.to_string()))?.len();
+
+    let mut head = Vec::new();
+    (&mut file)
+        .take(MARKER_WINDOW)
+        .read_to_end(&mut head)
+        .map_err(|e| invalid(e.to_string()))?;
+    if !contains(&head, b"%PDF-") {
+        return Err(invalid(tr!(Text::MissingPdfHeader)));
+    }
+
+    let mut tail = Vec::new();
+    file.seek(SeekFrom::Start(size.saturating_sub(MARKER_WINDOW)))
+        .and_then(|_| file.read_to_end(&mut tail))
+        .map_err(|e| invalid(e.to_string()))?;
+    if !contains(&tail, b"%%EOF") {
+        return Err(invalid(tr!(Text::MissingPdfTrailer)));
+    }
+
+    Ok(())
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+pub(super) fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{
+        CancelToken, CompressionOptions, CompressionProfile, EncryptionMethod, EncryptionSettings, PdfaConformance,
+        Permissions,
+    };
+
+    /// 在临时目录中准备原文件和比原文件大的压缩结果
+    fn job_with_larger_output(name: &str) -> CompressionJob {
+        let dir = std::env::temp_dir().join(format!("pdf-compressor-output-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let job = CompressionJob {
+            input_path: dir.join("input.pdf"),
+            output_path: dir.join("output.pdf"),
+            profile: CompressionProfile::ebook(),
+            options: CompressionOptions::default(),
+            progress: None,
+            cancel: CancelToken::default(),
+        };
+        fs::write(&job.input_path, b"%PDF-1.4 original %%EOF").unwrap();
+        fs::write(&job.output_path, b"%PDF-1.4 compressed but larger %%EOF").unwrap();
+        job
+    }
+
+    fn cleanup(job: &CompressionJob) {
+        let _ = fs::remove_dir_all(job.input_path.parent().unwrap());
+    }
+
+    #[test]
+    fn larger_output_is_replaced_by_original() {
+        let job = job_with_larger_output("larger");
+        let original_size = fs::metadata(&job.input_path).unwrap().len();
+
+        assert!(keep_original_if_larger(&job, original_size).unwrap());
+        assert_eq!(fs::read(&job.output_path).unwrap(), fs::read(&job.input_path).unwrap());
+        cleanup(&job);
+    }
+
+    #[test]
+    fn larger_output_is_kept_when_option_is_off() {
+        let mut job = job_with_larger_output("option-off");
+        job.options.keep_original_if_larger = false;
+        let original_size = fs::metadata(&job.input_path).unwrap().len();
+
+        assert!(!keep_original_if_larger(&job, original_size).unwrap());
+        assert_eq!(fs::read(&job.output_path).unwrap(), b"%PDF-1.4 compressed but larger %%EOF");
+        cleanup(&job);
+    }
+
+    #[test]
+    fn larger_output_is_kept_when_reencrypting() {
+        let mut job = job_with_larger_output("reencrypt");
+        job.options.reencrypt = true;
+        let original_size = fs::metadata(&job.input_path).unwrap().len();
+
+        assert!(!keep_original_if_larger(&job, original_size).unwrap());
+        assert_eq!(fs::read(&job.output_path).unwrap(), b"%PDF-1.4 compressed but larger %%EOF");
+        cleanup(&job);
+    }
+
+    #[test]
+    fn larger_output_is_kept_when_encrypting() {
+        let mut job = job_with_larger_output("encrypt");
+        job.options.encryption = Some(EncryptionSettings {
+            method: EncryptionMethod::Aes256,
+            user_password: "user".to_string(),
+            owner_password: None,
+            permissions: Permissions::default(),
+        });
+        let original_size = fs::metadata(&job.input_path).unwrap().len();
+
+        assert!(!keep_original_if_larger(&job, original_size).unwrap());
+        cleanup(&job);
+    }
+
+    #[test]
+    fn larger_output_is_kept_for_pdfa() {
+        let mut job = job_with_larger_output("pdfa");
+        job.profile.pdfa = Some(PdfaConformance::Pdfa2b);
+        let original_size = fs::metadata(&job.input_path).unwrap().len();
+
+        assert!(!keep_original_if_larger(&job, original_size).unwrap());
+        cleanup(&job);
+    }
+
+    #[test]
+    fn larger_output_is_kept_after_color_conversion() {
+        let mut job = job_with_larger_output("color");
+        job.profile.color_mode = ColorMode::Gray;
+        let original_size = fs::metadata(&job.input_path).unwrap().len();
+
+        assert!(!keep_original_if_larger(&job, original_size).unwrap());
+        cleanup(&job);
+    }
+
+    #[test]
+    fn larger_output_is_kept_with_page_selection() {
+        let mut job = job_with_larger_output("pages");
+        job.options.pages = Some("1".to_string());
+        let original_size = fs::metadata(&job.input_path).unwrap().len();
+
+        assert!(!keep_original_if_larger(&job, original_size).unwrap());
+        cleanup(&job);
+    }
+
+    #[test]
+    fn larger_output_is_kept_when_forms_or_comments_change() {
+        for (name, form_mode, remove_comments) in
+            [("flatten", FormMode::Flatten, false), ("forms", FormMode::Remove, false), ("comments", FormMode::Keep, true)]
+        {
+            let mut job = job_with_larger_output(name);
+            job.profile.form_mode = form_mode;
+            job.profile.remove_comments = remove_comments;
+            let original_size = fs::metadata(&job.input_path).unwrap().len();
+
+            assert!(!keep_original_if_larger(&job, original_size).unwrap(), "{} kept the original", name);
+            cleanup(&job);
+        }
+    }
+
+    #[test]
+    fn larger_output_is_kept_when_sanitizing() {
+        let mut job = job_with_larger_output("sanitize");
+        job.options.sanitize = true;
+        let original_size = fs::metadata(&job.input_path).unwrap().len();
+
+        assert!(!keep_original_if_larger(&job, original_size).unwrap());
+        cleanup(&job);
+    }
+
+    #[test]
+    fn validation_requires_header_and_trailer() {
+        let job = job_with_larger_output("validate");
+        assert!(validate_pdf(&job.output_path).is_ok());
+
+        fs::write(&job.output_path, b"%PDF-1.4 truncated").unwrap();
+        assert_eq!(validate_pdf(&job.output_path).unwrap_err().code, ErrorCode::InvalidOutput);
+        cleanup(&job);
+    }
+}
@@ -0,0 +1,297 @@
+// PDF/A 归档输出
+//
+// Ghostscript 的 pdfwrite 设备在 -dPDFA 模式下要求提供输出意图 (OutputIntent)，
+// 通常通过修改 Ghostscript 自带的 PDFA_def.ps 指定 ICC 文件。这里在压缩前生成 sRGB
+// 特性文件和对应的定义文件，压缩后再检查输出的 XMP 元数据是否声明了要求的 PDF/A 级别。
+
+use super::CompressionJob;
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Text;
+use crate::tr;
+use lopdf::{Document, Object};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 输出意图和 ICC 特性文件中使用的色彩空间名称
+const OUTPUT_CONDITION: &str = "sRGB IEC61966-2.1";
+
+/// ICC 特性文件的 D50 白点
+const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+/// 经 Bradford 变换适配到 D50 的 sRGB 三原色
+const SRGB_PRIMARIES: [[f64; 3]; 3] = [
+    [0.4361, 0.2225, 0.0139],
+    [0.3851, 0.7169, 0.0971],
+    [0.1431, 0.0606, 0.7141],
+];
+
+/// sRGB 色调曲线的采样点数
+const CURVE_POINTS: usize = 1024;
+
+/// PDF/A 的级别，都只要求 B 级（视觉外观）一致性
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdfaConformance {
+    /// PDF/A-1b (ISO 19005-1)，基于 PDF 1.4，不允许透明和对象流
+    #[serde(rename = "1b")]
+    Pdfa1b,
+    /// PDF/A-2b (ISO 19005-2)，基于 PDF 1.7
+    #[serde(rename = "2b")]
+    Pdfa2b,
+    /// PDF/A-3b (ISO 19005-3)，在 PDF/A-2b 的基础上允许嵌入任意附件
+    #[serde(rename = "3b")]
+    Pdfa3b,
+}
+
+impl PdfaConformance {
+    pub fn part(self) -> u8 {
+        match self {
+            PdfaConformance::Pdfa1b => 1,
+            PdfaConformance::Pdfa2b => 2,
+            PdfaConformance::Pdfa3b => 3,
+        }
+    }
+
+    /// 该级别要求的 PDF 版本
+    pub fn compatibility_level(self) -> &'static str {
+        match self {
+            PdfaConformance::Pdfa1b => "1.4",
+            PdfaConformance::Pdfa2b | PdfaConformance::Pdfa3b => "1.7",
+        }
+    }
+
+    pub fn label(self) -> String {
+        format!("PDF/A-{}b", self.part())
+    }
+}
+
+/// 一次 Ghostscript 运行所需的 ICC 文件和 PDFA 定义文件，离开作用域时删除
+pub(super) struct PdfaResources {
+    icc_path: PathBuf,
+    definition_path: PathBuf,
+}
+
+impl PdfaResources {
+    /// 在输出文件旁写入 sRGB 特性文件和引用它的定义文件
+    pub fn prepare(output_path: &Path) -> Result<Self, AppError> {
+        let file_name = output_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let resources = Self {
+            icc_path: output_path.with_file_name(format!(".{}.srgb.icc", file_name)),
+            definition_path: output_path.with_file_name(format!(".{}.pdfa.ps", file_name)),
+        };
+
+        let write_failed = |e: std::io::Error| AppError::with_details(ErrorCode::OutputWriteFailed, e);
+        fs::write(&resources.icc_path, srgb_icc_profile()).map_err(write_failed)?;
+        fs::write(&resources.definition_path, definition(&resources.icc_path)).map_err(write_failed)?;
+        Ok(resources)
+    }
+
+    /// 需要加在输入文件之前的参数，-dSAFER 下要显式允许读取 ICC 文件
+    pub fn args(&self) -> Vec<String> {
+        vec![
+            format!("--permit-file-read={}", self.icc_path.to_string_lossy()),
+            self.definition_path.to_string_lossy().to_string(),
+        ]
+    }
+}
+
+impl Drop for PdfaResources {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.icc_path);
+        let _ = fs::remove_file(&self.definition_path);
+    }
+}
+
+/// 检查输出文件的 XMP 元数据是否声明了要求的 PDF/A 级别
+pub(super) fn verify(job: &CompressionJob, conformance: PdfaConformance) -> Result<(), AppError> {
+    let failed = |details: String| AppError::with_details(ErrorCode::PdfaVerificationFailed, details);
+
+    let document = Document::load(&job.output_path).map_err(|e| failed(e.to_string()))?;
+    let xmp = document
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Metadata"))
+        .and_then(Object::as_reference)
+        .and_then(|id| document.get_object(id))
+        .and_then(Object::as_stream)
+        .map(|stream| stream.decompressed_content().unwrap_or_else(|_| stream.content.clone()))
+        .map_err(|_| failed(tr!(Text::PdfaMetadataMissing)))?;
+    let xmp = String::from_utf8_lossy(&xmp);
+
+    let part = xmp_property(&xmp, "pdfaid:part");
+    let level = xmp_property(&xmp, "pdfaid:conformance");
+    let declared = match (part, level) {
+        (Some(part), Some(level)) => format!("PDF/A-{}{}", part, level.to_lowercase()),
+        _ => return Err(failed(tr!(Text::PdfaNotDeclared, conformance.label()))),
+    };
+
+    // B 级是最低要求，声明 A 级或 U 级的文件同样满足
+    let part_matches = part == Some(conformance.part().to_string().as_str());
+    let level_matches = level.is_some_and(|level| ["A", "B", "U"].contains(&level.to_uppercase().as_str()));
+    if !part_matches || !level_matches {
+        return Err(failed(tr!(Text::PdfaDeclarationMismatch, declared, conformance.label())));
+    }
+    Ok(())
+}
+
+/// 读取 XMP 中的简单属性，支持 `name="value"` 和 `<name>value</name>` 两种写法
+fn xmp_property<'a>(xmp: &'a str, name: &str) -> Option<&'a str> {
+    let attribute = format!("{}=", name);
+    if let Some(start) = xmp.find(&attribute) {
+        let rest = &xmp[start + attribute.len()..];
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        return rest[1..].split(quote).next().map(str::trim);
+    }
+
+    let element = format!("<{}>", name);
+    let start = xmp.find(&element)? + element.len();
+    let end = start + xmp[start..].find('<')?;
+    Some(xmp[start..end].trim())
+}
+
+/// 基于 Ghostscript 自带 PDFA_def.ps 的定义文件，把 sRGB 特性文件设为文档的输出意图
+fn definition(icc_path: &Path) -> String {
+    format!(
+        "%!\n\
+         /ICCProfile ({icc}) def\n\
+         [/_objdef {{icc_PDFA}} /type /stream /OBJ pdfmark\n\
+         [{{icc_PDFA}} << /N 3 >> /PUT pdfmark\n\
+         [{{icc_PDFA}} ICCProfile (r) file /PUT pdfmark\n\
+         [/_objdef {{OutputIntent_PDFA}} /type /dict /OBJ pdfmark\n\
+         [{{OutputIntent_PDFA}} <<\n  \
+           /Type /OutputIntent\n  \
+           /S /GTS_PDFA1\n  \
+           /DestOutputProfile {{icc_PDFA}}\n  \
+           /OutputConditionIdentifier ({condition})\n  \
+           /Info ({condition})\n  \
+           /RegistryName (http://www.color.org)\n\
+         >> /PUT pdfmark\n\
+         [{{Catalog}} << /OutputIntents [ {{OutputIntent_PDFA}} ] >> /PUT pdfmark\n",
+        icc = postscript_string(&icc_path.to_string_lossy()),
+        condition = OUTPUT_CONDITION,
+    )
+}
+
+/// 转义 PostScript 字符串中的反斜杠和括号，Windows 路径中的反斜杠需要转义
+fn postscript_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '(' | ')') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// 生成 ICC v2 的 sRGB 显示设备特性文件（矩阵加色调曲线）
+fn srgb_icc_profile() -> Vec<u8> {
+    let curve = srgb_curve();
+    let tags: [(&[u8; 4], Vec<u8>); 9] = [
+        (b"desc", description_tag(OUTPUT_CONDITION)),
+        (b"cprt", text_tag("No copyright, use freely")),
+        (b"wtpt", xyz_tag(D50)),
+        (b"rXYZ", xyz_tag(SRGB_PRIMARIES[0])),
+        (b"gXYZ", xyz_tag(SRGB_PRIMARIES[1])),
+        (b"bXYZ", xyz_tag(SRGB_PRIMARIES[2])),
+        (b"rTRC", curve.clone()),
+        (b"gTRC", curve.clone()),
+        (b"bTRC", curve),
+    ];
+
+    // 标签数据紧跟在标签表之后，按 4 字节对齐，内容相同的标签共用同一份数据
+    let mut table = Vec::new();
+    let mut data: Vec<u8> = Vec::new();
+    let data_start = 128 + 4 + 12 * tags.len();
+    let mut written: Vec<(&[u8], usize)> = Vec::new();
+    for (signature, content) in &tags {
+        let offset = match written.iter().find(|(existing, _)| *existing == content.as_slice()) {
+            Some((_, offset)) => *offset,
+            None => {
+                let offset = data_start + data.len();
+                data.extend_from_slice(content);
+                data.resize(data.len().next_multiple_of(4), 0);
+                written.push((content, offset));
+                offset
+            }
+        };
+        table.extend_from_slice(*signature);
+        table.extend_from_slice(&(offset as u32).to_be_bytes());
+        table.extend_from_slice(&(content.len() as u32).to_be_bytes());
+    }
+
+    let size = data_start + data.len();
+    let mut profile = Vec::with_capacity(size);
+    profile.extend_from_slice(&(size as u32).to_be_bytes());
+    profile.extend_from_slice(&[0; 4]); // CMM
+    profile.extend_from_slice(&0x0210_0000u32.to_be_bytes()); // 版本 2.1
+    profile.extend_from_slice(b"mntrRGB XYZ ");
+    for field in [2024u16, 1, 1, 0, 0, 0] {
+        profile.extend_from_slice(&field.to_be_bytes());
+    }
+    profile.extend_from_slice(b"acsp");
+    profile.extend_from_slice(&[0; 24]); // 平台、标志、设备制造商、型号和属性
+    profile.extend_from_slice(&0u32.to_be_bytes()); // 感知渲染意图
+    profile.extend_from_slice(&xyz_numbers(D50));
+    profile.resize(128, 0);
+
+    profile.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+    profile.extend_from_slice(&table);
+    profile.extend_from_slice(&data);
+    profile
+}
+
+fn srgb_curve() -> Vec<u8> {
+    let mut tag = tag_header(b"curv");
+    tag.extend_from_slice(&(CURVE_POINTS as u32).to_be_bytes());
+    for index in 0..CURVE_POINTS {
+        let encoded = index as f64 / (CURVE_POINTS - 1) as f64;
+        let linear = if encoded <= 0.04045 {
+            encoded / 12.92
+        } else {
+            ((encoded + 0.055) / 1.055).powf(2.4)
+        };
+        tag.extend_from_slice(&((linear * 65535.0).round() as u16).to_be_bytes());
+    }
+    tag
+}
+
+/// ICC v2 的 textDescriptionType，只填写 ASCII 部分
+fn description_tag(text: &str) -> Vec<u8> {
+    let mut tag = tag_header(b"desc");
+    tag.extend_from_slice(&(text.len() as u32 + 1).to_be_bytes());
+    tag.extend_from_slice(text.as_bytes());
+    tag.push(0);
+    tag.extend_from_slice(&[0; 8]); // Unicode 语言代码和长度
+    tag.extend_from_slice(&[0; 3]); // ScriptCode 代码和长度
+    tag.extend_from_slice(&[0; 67]);
+    tag
+}
+
+fn text_tag(text: &str) -> Vec<u8> {
+    let mut tag = tag_header(b"text");
+    tag.extend_from_slice(text.as_bytes());
+    tag.push(0);
+    tag
+}
+
+fn xyz_tag(xyz: [f64; 3]) -> Vec<u8> {
+    let mut tag = tag_header(b"XYZ ");
+    tag.extend_from_slice(&xyz_numbers(xyz));
+    tag
+}
+
+/// 以 s15Fixed16Number 表示的 XYZ 值
+fn xyz_numbers(xyz: [f64; 3]) -> Vec<u8> {
+    xyz.iter()
+        .flat_map(|value| ((value * 65536.0).round() as i32).to_be_bytes())
+        .collect()
+}
+
+fn tag_header(signature: &[u8; 4]) -> Vec<u8> {
+    let mut tag = signature.to_vec();
+    tag.extend_from_slice(&[0; 4]);
+    tag
+}
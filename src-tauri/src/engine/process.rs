@@ -0,0 +1,127 @@
+// 外部进程的执行限制
+//
+// 损坏或恶意构造的 PDF 可能让 Ghostscript 长时间运行或耗尽内存。
+// 超时由调用方通过 `tokio::time` 实现，内存和 CPU 时间限制只在 Linux 上通过 setrlimit 生效，
+// 优先级在 Unix 上通过 nice 调整，在 Windows 上使用较低的进程优先级类。
+
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Text;
+use crate::tr;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// 默认的最长运行时间
+const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+/// 外部进程的资源限制，未指定的字段使用默认值
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProcessLimits {
+    /// 最长运行时间（秒），超时后结束进程，None 表示不限制
+    pub timeout_secs: Option<u64>,
+    /// 最大虚拟内存（MB），仅 Linux 生效
+    pub memory_limit_mb: Option<u64>,
+    /// 最大 CPU 时间（秒），仅 Linux 生效
+    pub cpu_time_limit_secs: Option<u64>,
+    /// 进程优先级调整值 (0-19)，越大优先级越低；Windows 上大于 0 时使用低于正常的优先级
+    pub niceness: Option<i32>,
+}
+
+impl Default for ProcessLimits {
+    fn default() -> Self {
+        Self {
+            timeout_secs: Some(DEFAULT_TIMEOUT_SECS),
+            memory_limit_mb: None,
+            cpu_time_limit_secs: None,
+            niceness: None,
+        }
+    }
+}
+
+impl ProcessLimits {
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_secs.map(Duration::from_secs)
+    }
+
+    pub fn validate(&self) -> Result<(), AppError> {
+        self.check().map_err(|details| AppError::with_details(ErrorCode::InvalidOptions, details))
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.timeout_secs == Some(0) {
+            return Err(tr!(Text::TimeoutMustBePositive));
+        }
+        if self.memory_limit_mb == Some(0) {
+            return Err(tr!(Text::MemoryLimitMustBePositive));
+        }
+        if self.cpu_time_limit_secs == Some(0) {
+            return Err(tr!(Text::CpuTimeLimitMustBePositive));
+        }
+        if let Some(niceness) = self.niceness {
+            if !(0..=19).contains(&niceness) {
+                return Err(tr!(Text::NicenessOutOfRange, niceness));
+            }
+        }
+        Ok(())
+    }
+
+    /// 在子进程启动前应用内存、CPU 时间和优先级限制
+    pub fn apply(&self, command: &mut Command) {
+        // 调用方被取消或超时后丢弃子进程句柄时一并结束进程
+        command.kill_on_drop(true);
+
+        #[cfg(unix)]
+        {
+            let limits = *self;
+            // SAFETY: pre_exec 在 fork 之后、exec 之前的子进程中执行，
+            // 这里只调用 setrlimit 和 nice，二者都是 async-signal-safe 的系统调用
+            unsafe {
+                command.pre_exec(move || limits.apply_in_child());
+            }
+        }
+
+        #[cfg(windows)]
+        {
+            const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+            if self.niceness.is_some_and(|niceness| niceness > 0) {
+                command.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn apply_in_child(&self) -> std::io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            // 资源编号的类型在 glibc 和 musl 中不同，用闭包让编译器推断
+            let set_rlimit = |resource, value: u64| {
+                let limit = libc::rlimit {
+                    rlim_cur: value as libc::rlim_t,
+                    rlim_max: value as libc::rlim_t,
+                };
+                if unsafe { libc::setrlimit(resource, &limit) } == 0 {
+                    Ok(())
+                } else {
+                    Err(std::io::Error::last_os_error())
+                }
+            };
+
+            if let Some(megabytes) = self.memory_limit_mb {
+                set_rlimit(libc::RLIMIT_AS, megabytes.saturating_mul(1024 * 1024))?;
+            }
+            if let Some(seconds) = self.cpu_time_limit_secs {
+                set_rlimit(libc::RLIMIT_CPU, seconds)?;
+            }
+        }
+
+        if let Some(niceness) = self.niceness {
+            // nice 的返回值可能合法地为 -1，失败时只是保持原优先级，不影响压缩
+            unsafe {
+                libc::nice(niceness);
+            }
+        }
+
+        Ok(())
+    }
+}
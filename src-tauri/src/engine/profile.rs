@@ -0,0 +1,287 @@
+// 压缩配置
+//
+// 固定的压缩等级只是内置的配置，前端也可以传入完全自定义的配置。
+// Ghostscript 引擎把配置转换为命令行参数，lopdf 引擎使用其中的图像参数。
+
+use super::{ColorMode, FormMode, PdfaConformance};
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Text;
+use crate::tr;
+use serde::{Deserialize, Serialize};
+
+/// Ghostscript 支持的 PDF 兼容性版本
+const COMPATIBILITY_LEVELS: &[&str] = &["1.3", "1.4", "1.5", "1.6", "1.7", "2.0"];
+
+/// 彩色和灰度图像允许的最大分辨率
+const MAX_RESOLUTION: u32 = 2400;
+
+/// 黑白图像允许的最大分辨率
+const MAX_MONO_RESOLUTION: u32 = 4800;
+
+/// 自动选择编码方式时，JPEG 质量不低于该值的配置保留无损图像
+const LOSSLESS_QUALITY_THRESHOLD: u8 = 80;
+
+/// 作为基础的 Ghostscript PDFSETTINGS 预设，配置中未覆盖的参数使用预设的默认值
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PdfSettings {
+    Screen,
+    Ebook,
+    Printer,
+    Prepress,
+}
+
+impl PdfSettings {
+    pub fn as_arg(self) -> &'static str {
+        match self {
+            PdfSettings::Screen => "/screen",
+            PdfSettings::Ebook => "/ebook",
+            PdfSettings::Printer => "/printer",
+            PdfSettings::Prepress => "/prepress",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DownsampleMethod {
+    Subsample,
+    Average,
+    Bicubic,
+}
+
+impl DownsampleMethod {
+    pub fn as_arg(self) -> &'static str {
+        match self {
+            DownsampleMethod::Subsample => "/Subsample",
+            DownsampleMethod::Average => "/Average",
+            DownsampleMethod::Bicubic => "/Bicubic",
+        }
+    }
+}
+
+/// 图像编码方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFilter {
+    /// 由引擎根据图像内容选择
+    Auto,
+    /// 强制使用 JPEG (DCTEncode)
+    Jpeg,
+    /// 强制使用无损的 Flate 压缩
+    Lossless,
+}
+
+/// 图像降采样的目标分辨率和 JPEG 编码质量
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageQuality {
+    pub resolution: u32,
+    pub jpeg_quality: u8,
+}
+
+/// 完整的压缩配置，未指定的字段使用 ebook 预设的值
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CompressionProfile {
+    pub base: PdfSettings,
+    /// 彩色图像目标分辨率 (DPI)
+    pub color_resolution: u32,
+    /// 灰度图像目标分辨率 (DPI)
+    pub gray_resolution: u32,
+    /// 黑白图像目标分辨率 (DPI)
+    pub mono_resolution: u32,
+    pub downsample_method: DownsampleMethod,
+    /// 实际分辨率超过目标分辨率的多少倍时才降采样，不能小于 1.0
+    pub downsample_threshold: f32,
+    /// JPEG 编码质量 (1-100)
+    pub jpeg_quality: u8,
+    pub color_filter: ImageFilter,
+    pub gray_filter: ImageFilter,
+    pub embed_fonts: bool,
+    pub subset_fonts: bool,
+    pub compress_fonts: bool,
+    /// 输出的颜色模式
+    pub color_mode: ColorMode,
+    /// 保留注释和标记内容，用于印刷等需要完整保留文档结构的场景
+    pub preserve_annotations: bool,
+    /// 表单域的处理方式
+    pub form_mode: FormMode,
+    /// 删除批注类注释（文字批注、高亮、图章等）
+    pub remove_comments: bool,
+    /// Ghostscript 输出的 PDF 版本，转换为 PDF/A 时由 PDF/A 级别决定
+    pub compatibility_level: String,
+    /// 转换为 PDF/A 的级别，仅 Ghostscript 引擎支持，要求嵌入全部字体
+    pub pdfa: Option<PdfaConformance>,
+}
+
+impl Default for CompressionProfile {
+    fn default() -> Self {
+        Self::ebook()
+    }
+}
+
+impl CompressionProfile {
+    /// 最大压缩，适合网页分享和邮件
+    pub fn screen() -> Self {
+        Self {
+            base: PdfSettings::Screen,
+            color_resolution: 72,
+            gray_resolution: 72,
+            mono_resolution: 300,
+            jpeg_quality: 30,
+            color_filter: ImageFilter::Jpeg,
+            gray_filter: ImageFilter::Jpeg,
+            ..Self::ebook()
+        }
+    }
+
+    /// 平衡质量，适合一般文档阅读
+    pub fn ebook() -> Self {
+        Self {
+            base: PdfSettings::Ebook,
+            color_resolution: 150,
+            gray_resolution: 150,
+            mono_resolution: 300,
+            downsample_method: DownsampleMethod::Bicubic,
+            downsample_threshold: 1.5,
+            jpeg_quality: 50,
+            color_filter: ImageFilter::Auto,
+            gray_filter: ImageFilter::Auto,
+            embed_fonts: true,
+            subset_fonts: true,
+            compress_fonts: true,
+            color_mode: ColorMode::Leave,
+            preserve_annotations: false,
+            form_mode: FormMode::Keep,
+            remove_comments: false,
+            compatibility_level: "1.4".to_string(),
+            pdfa: None,
+        }
+    }
+
+    /// 高质量，适合办公打印
+    pub fn printer() -> Self {
+        Self {
+            base: PdfSettings::Printer,
+            color_resolution: 300,
+            gray_resolution: 300,
+            mono_resolution: 1200,
+            jpeg_quality: 80,
+            ..Self::ebook()
+        }
+    }
+
+    /// 专业印刷
+    pub fn prepress() -> Self {
+        Self {
+            base: PdfSettings::Prepress,
+            color_resolution: 300,
+            gray_resolution: 300,
+            mono_resolution: 1200,
+            jpeg_quality: 90,
+            preserve_annotations: true,
+            ..Self::ebook()
+        }
+    }
+
+    /// 长期归档，保持打印质量并转换为 PDF/A-2b
+    pub fn archive() -> Self {
+        Self {
+            base: PdfSettings::Printer,
+            color_resolution: 300,
+            gray_resolution: 300,
+            mono_resolution: 1200,
+            jpeg_quality: 85,
+            subset_fonts: false,
+            compatibility_level: "1.7".to_string(),
+            pdfa: Some(PdfaConformance::Pdfa2b),
+            ..Self::ebook()
+        }
+    }
+
+    /// Ghostscript 实际使用的 PDF 版本
+    pub fn output_compatibility_level(&self) -> &str {
+        match self.pdfa {
+            Some(conformance) => conformance.compatibility_level(),
+            None => &self.compatibility_level,
+        }
+    }
+
+    /// 根据压缩等级名称获取内置配置，支持 "/screen" 和 "screen" 两种写法
+    pub fn from_level(level: &str) -> Result<Self, AppError> {
+        match level.trim_start_matches('/') {
+            "screen" => Ok(Self::screen()),
+            "ebook" => Ok(Self::ebook()),
+            "printer" => Ok(Self::printer()),
+            "prepress" => Ok(Self::prepress()),
+            "archive" => Ok(Self::archive()),
+            _ => Err(AppError::with_details(ErrorCode::InvalidProfile, tr!(Text::UnknownLevel, level))),
+        }
+    }
+
+    /// 使用指定的分辨率和 JPEG 质量，并强制 JPEG 编码使其一定生效
+    pub fn with_image_quality(mut self, quality: ImageQuality) -> Self {
+        self.color_resolution = quality.resolution;
+        self.gray_resolution = quality.resolution;
+        self.jpeg_quality = quality.jpeg_quality;
+        self.color_filter = ImageFilter::Jpeg;
+        self.gray_filter = ImageFilter::Jpeg;
+        self
+    }
+
+    /// 是否把无损压缩的图像重新编码为 JPEG（lopdf 引擎）
+    pub fn reencode_lossless(&self) -> bool {
+        match self.color_filter {
+            ImageFilter::Jpeg => true,
+            ImageFilter::Lossless => false,
+            ImageFilter::Auto => self.jpeg_quality < LOSSLESS_QUALITY_THRESHOLD,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), AppError> {
+        self.check().map_err(|details| AppError::with_details(ErrorCode::InvalidProfile, details))
+    }
+
+    fn check(&self) -> Result<(), String> {
+        for (name, resolution, max) in [
+            (Text::ColorResolution, self.color_resolution, MAX_RESOLUTION),
+            (Text::GrayResolution, self.gray_resolution, MAX_RESOLUTION),
+            (Text::MonoResolution, self.mono_resolution, MAX_MONO_RESOLUTION),
+        ] {
+            if resolution == 0 || resolution > max {
+                return Err(tr!(Text::ResolutionOutOfRange, tr!(name), max, resolution));
+            }
+        }
+
+        if !self.downsample_threshold.is_finite() || self.downsample_threshold < 1.0 {
+            return Err(tr!(Text::DownsampleThresholdTooLow, self.downsample_threshold));
+        }
+
+        if !(1..=100).contains(&self.jpeg_quality) {
+            return Err(tr!(Text::JpegQualityOutOfRange, self.jpeg_quality));
+        }
+
+        if !COMPATIBILITY_LEVELS.contains(&self.compatibility_level.as_str()) {
+            return Err(tr!(
+                Text::UnsupportedCompatibilityLevel,
+                self.compatibility_level,
+                COMPATIBILITY_LEVELS.join(&tr!(Text::ListSeparator))
+            ));
+        }
+
+        if self.subset_fonts && !self.embed_fonts {
+            return Err(tr!(Text::SubsetFontsRequiresEmbedding));
+        }
+
+        if let Some(conformance) = self.pdfa.filter(|_| !self.embed_fonts) {
+            return Err(tr!(Text::PdfaRequiresEmbeddedFonts, conformance.label()));
+        }
+
+        // PDF/A 输出使用 sRGB 输出意图，无法同时转换为灰度
+        if let Some(conformance) = self.pdfa.filter(|_| matches!(self.color_mode, ColorMode::Gray | ColorMode::Bilevel)) {
+            return Err(tr!(Text::PdfaRequiresRgb, conformance.label(), self.color_mode.as_str()));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+// 压缩进度
+//
+// 引擎本身不依赖 Tauri，进度通过回调交给调用方：桌面应用转发为 `compression-progress`
+// 事件，命令行可以直接忽略。
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 两次进度回调之间的最小间隔，避免逐对象处理时产生过多事件
+const MIN_REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 进度计数的单位
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressUnit {
+    /// Ghostscript 逐页处理
+    Page,
+    /// lopdf 逐个处理图像对象
+    Object,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompressionProgress {
+    pub input_path: PathBuf,
+    pub unit: ProgressUnit,
+    /// 已处理的数量
+    pub current: usize,
+    pub total: usize,
+    /// 已用时间（毫秒）
+    pub elapsed_ms: u64,
+    /// 按当前速度估算的剩余时间（毫秒），处理第一项之前无法估算
+    pub eta_ms: Option<u64>,
+}
+
+type ProgressCallback = dyn Fn(CompressionProgress) + Send + Sync;
+
+/// 进度回调的包装，负责计算耗时、剩余时间并限制回调频率
+#[derive(Clone)]
+pub struct ProgressReporter {
+    callback: Arc<ProgressCallback>,
+    started: Instant,
+    last_report: Arc<Mutex<Option<Instant>>>,
+}
+
+impl ProgressReporter {
+    pub fn new(callback: impl Fn(CompressionProgress) + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Arc::new(callback),
+            started: Instant::now(),
+            last_report: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// 共享同一个回调、重新开始计时的报告器，用于批量处理中的下一个文件
+    pub fn restarted(&self) -> Self {
+        Self {
+            callback: self.callback.clone(),
+            started: Instant::now(),
+            last_report: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// 报告处理到第 `current` 项（共 `total` 项），最后一项总是会被报告
+    pub fn report(&self, input_path: &std::path::Path, unit: ProgressUnit, current: usize, total: usize) {
+        let now = Instant::now();
+        {
+            let mut last_report = match self.last_report.lock() {
+                Ok(guard) => guard,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            let throttled = last_report.is_some_and(|last| now.duration_since(last) < MIN_REPORT_INTERVAL);
+            if throttled && current < total {
+                return;
+            }
+            *last_report = Some(now);
+        }
+
+        let elapsed = now.duration_since(self.started);
+        let eta = (current > 0 && total >= current).then(|| {
+            let per_item = elapsed.as_secs_f64() / current as f64;
+            (per_item * (total - current) as f64 * 1000.0) as u64
+        });
+
+        (self.callback)(CompressionProgress {
+            input_path: input_path.to_path_buf(),
+            unit,
+            current,
+            total,
+            elapsed_ms: elapsed.as_millis() as u64,
+            eta_ms: eta,
+        });
+    }
+}
+
+impl fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressReporter")
+            .field("started", &self.started)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,298 @@
+// 压缩结果
+//
+// 结果中的每一项信息都是独立的字段，面向用户的消息由这些字段生成，
+// 前端和脚本不需要解析消息文本。
+
+use super::{
+    format_file_size, ColorMode, CompressionBackend, CompressionJob, CompressionProfile, EncryptionInfo, EngineOutput,
+    TargetSizeReport,
+};
+use crate::error::ErrorCode;
+use crate::i18n::Text;
+use crate::tr;
+use crate::optimize::annotations::AnnotationReport;
+use crate::optimize::cleanup::CleanupReport;
+use crate::optimize::color::ColorReport;
+use crate::optimize::dedup::DedupReport;
+use crate::optimize::sanitize::SanitizeReport;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// 压缩任务的最终状态
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Completed,
+    /// 压缩成功，但结果不比原文件小
+    NoGain,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompressionResult {
+    pub success: bool,
+    pub status: JobStatus,
+    /// 由其他字段生成的消息，供界面直接显示
+    pub message: String,
+    /// 实际使用的引擎名称
+    pub engine: String,
+    /// 实际使用的压缩配置
+    pub profile: CompressionProfile,
+    pub original_size: u64,
+    /// 输出文件大小，任务取消或无法读取输出文件时为 None
+    pub compressed_size: Option<u64>,
+    /// 体积减少的百分比，没有变小时为 None
+    pub ratio: Option<f64>,
+    /// 压缩结果不比原文件小，输出文件是原文件的副本
+    pub kept_original: bool,
+    /// 原地压缩时原文件的备份
+    pub backup_path: Option<PathBuf>,
+    /// 压缩耗时（毫秒），目标大小模式下包含所有尝试
+    pub duration_ms: u64,
+    /// 输出的页数，引擎无法得知时为 None
+    pub page_count: Option<usize>,
+    /// 规范化后的页面选择表达式，保留全部页面时为 None
+    pub page_selection: Option<String>,
+    /// 压缩成功但需要提醒用户的问题
+    pub warnings: Vec<String>,
+    /// lopdf 引擎清理的元数据条目，其他引擎为 None
+    pub cleanup: Option<CleanupReport>,
+    /// lopdf 引擎合并的重复对象，其他引擎为 None
+    pub dedup: Option<DedupReport>,
+    /// 颜色模式转换的结果，未转换颜色时为 None
+    pub color: Option<ColorReport>,
+    /// lopdf 引擎平展或删除的表单域和批注，其他引擎为 None
+    pub annotations: Option<AnnotationReport>,
+    /// 净化时移除的各类条目，未净化时为 None
+    pub sanitize: Option<SanitizeReport>,
+    /// 目标大小模式下最终使用的参数和达到的大小
+    pub target: Option<TargetSizeReport>,
+    /// 输入文件的加密信息，未加密时为 None
+    pub encryption: Option<EncryptionInfo>,
+    /// 输出文件的加密信息，输出未加密时为 None
+    pub output_encryption: Option<EncryptionInfo>,
+}
+
+impl CompressionResult {
+    /// 引擎成功写出输出文件后生成结果
+    pub(super) fn completed(
+        backend: &dyn CompressionBackend,
+        job: &CompressionJob,
+        original_size: u64,
+        output: EngineOutput,
+        target: Option<TargetSizeReport>,
+        kept_original: bool,
+        started: Instant,
+    ) -> Self {
+        // 保留原文件时，引擎报告描述的是被丢弃的输出，不能出现在结果中
+        let output = if kept_original {
+            EngineOutput {
+                warnings: output.warnings,
+                ..EngineOutput::default()
+            }
+        } else {
+            output
+        };
+        let compressed_size = fs::metadata(&job.output_path).map(|m| m.len()).ok();
+        let no_gain = kept_original || compressed_size.is_some_and(|compressed_size| compressed_size >= original_size);
+        let ratio = compressed_size
+            .filter(|_| !no_gain)
+            .map(|compressed_size| ((original_size as f64 - compressed_size as f64) / original_size as f64) * 100.0);
+
+        let mut result = Self {
+            success: true,
+            status: if no_gain { JobStatus::NoGain } else { JobStatus::Completed },
+            message: String::new(),
+            engine: backend.name().to_string(),
+            profile: job.profile.clone(),
+            original_size,
+            compressed_size,
+            ratio,
+            kept_original,
+            backup_path: None,
+            duration_ms: started.elapsed().as_millis() as u64,
+            page_count: output.page_count,
+            page_selection: job.page_selection().ok().flatten().map(|selection| selection.to_string()),
+            warnings: output.warnings,
+            cleanup: output.cleanup,
+            dedup: output.dedup,
+            color: output.color,
+            annotations: output.annotations,
+            sanitize: output.sanitize,
+            target,
+            encryption: None,
+            output_encryption: None,
+        };
+        result.message = result.describe(backend.result_note().as_deref());
+        result
+    }
+
+    pub(super) fn cancelled(engine: &str, job: &CompressionJob, started: Instant) -> Self {
+        let mut result = Self {
+            success: false,
+            status: JobStatus::Cancelled,
+            message: String::new(),
+            engine: engine.to_string(),
+            profile: job.profile.clone(),
+            original_size: fs::metadata(&job.input_path).map(|m| m.len()).unwrap_or_default(),
+            compressed_size: None,
+            ratio: None,
+            kept_original: false,
+            backup_path: None,
+            duration_ms: started.elapsed().as_millis() as u64,
+            page_count: None,
+            page_selection: None,
+            warnings: Vec::new(),
+            cleanup: None,
+            dedup: None,
+            color: None,
+            annotations: None,
+            sanitize: None,
+            target: None,
+            encryption: None,
+            output_encryption: None,
+        };
+        result.message = result.describe(None);
+        result
+    }
+
+    /// 根据各字段按当前语言生成面向用户的消息，`note` 为引擎附加的提示
+    pub fn describe(&self, note: Option<&str>) -> String {
+        if self.status == JobStatus::Cancelled {
+            return ErrorCode::Cancelled.message().to_string();
+        }
+
+        let mut message = match (self.status, self.compressed_size, self.ratio) {
+            (JobStatus::NoGain, _, _) if self.kept_original => tr!(Text::NoGainKeptOriginal),
+            (JobStatus::NoGain, Some(compressed_size), _) => tr!(
+                Text::NoGainLarger,
+                format_file_size(compressed_size.saturating_sub(self.original_size))
+            ),
+            (_, Some(compressed_size), Some(ratio)) => tr!(
+                Text::CompressionSucceededWithRatio,
+                format!("{:.1}", ratio),
+                format_file_size(self.original_size.saturating_sub(compressed_size))
+            ),
+            _ => tr!(Text::CompressionSucceeded),
+        };
+
+        if let Some(report) = &self.target {
+            message.push_str(&match report.quality {
+                Some(quality) => tr!(
+                    Text::TargetSizeSummary,
+                    format_file_size(report.target_size),
+                    quality.resolution,
+                    quality.jpeg_quality
+                ),
+                None => tr!(Text::TargetSizeAlreadyMet, format_file_size(report.target_size)),
+            });
+        }
+
+        if let Some(page_count) = self.page_count.filter(|_| self.page_selection.is_some()) {
+            message.push_str(&tr!(Text::PagesKept, page_count));
+        }
+
+        if let Some(report) = self.cleanup.filter(|report| report.total() > 0) {
+            message.push_str(&tr!(Text::MetadataCleaned, report.total()));
+        }
+
+        if let Some(report) = self.dedup.filter(|report| report.objects_merged > 0) {
+            message.push_str(&tr!(
+                Text::DuplicatesMerged,
+                report.objects_merged,
+                format_file_size(report.bytes_saved)
+            ));
+        }
+
+        // Ghostscript 不报告转换数量，只要设置了颜色模式就提示
+        let mode = match self.profile.color_mode {
+            ColorMode::Leave => None,
+            ColorMode::Rgb => Some(tr!(Text::ColorModeRgb)),
+            ColorMode::Gray => Some(tr!(Text::ColorModeGray)),
+            ColorMode::Bilevel => Some(tr!(Text::ColorModeBilevel)),
+        };
+        if let Some(mode) = mode {
+            message.push_str(&tr!(Text::ColorsConverted, mode));
+            if let Some(report) = self.color.filter(|report| report.images_converted > 0) {
+                message.push_str(&tr!(Text::ColorImagesConverted, report.images_converted));
+            }
+        }
+
+        if let Some(report) = self.annotations {
+            if report.fields_flattened > 0 {
+                message.push_str(&tr!(Text::FormFieldsFlattened, report.fields_flattened));
+            }
+            if report.widgets_removed + report.comments_removed > 0 {
+                message.push_str(&tr!(Text::AnnotationsRemoved, report.widgets_removed + report.comments_removed));
+            }
+        }
+
+        if let Some(report) = self.sanitize.filter(|report| report.total() > 0) {
+            message.push_str(&tr!(Text::Sanitized, report.total()));
+        }
+
+        if let Some(conformance) = self.profile.pdfa {
+            message.push_str(&tr!(Text::PdfaConverted, conformance.label()));
+        }
+
+        if let Some(info) = &self.output_encryption {
+            message.push_str(&tr!(Text::OutputEncrypted, info.method.label()));
+        }
+
+        if let Some(backup_path) = &self.backup_path {
+            message.push_str(&tr!(Text::BackupCreated, backup_path.display()));
+        }
+
+        if let Some(note) = note {
+            message.push_str(&tr!(Text::ResultNote, note));
+        }
+
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{CancelToken, CompressionOptions, LopdfBackend};
+    use crate::optimize::cleanup::CleanupReport;
+    use std::path::PathBuf;
+
+    fn job() -> CompressionJob {
+        CompressionJob {
+            input_path: PathBuf::from("missing-input.pdf"),
+            output_path: PathBuf::from("missing-output.pdf"),
+            profile: CompressionProfile::ebook(),
+            options: CompressionOptions::default(),
+            progress: None,
+            cancel: CancelToken::default(),
+        }
+    }
+
+    #[test]
+    fn kept_original_reports_no_changes() {
+        let output = EngineOutput {
+            cleanup: Some(CleanupReport::default()),
+            dedup: Some(DedupReport::default()),
+            color: Some(ColorReport::default()),
+            annotations: Some(AnnotationReport::default()),
+            sanitize: Some(SanitizeReport::default()),
+            page_count: Some(3),
+            warnings: vec!["warning".to_string()],
+        };
+        let result = CompressionResult::completed(&LopdfBackend, &job(), 100, output, None, true, Instant::now());
+
+        assert_eq!(result.status, JobStatus::NoGain);
+        assert!(result.page_count.is_none());
+        assert!(result.cleanup.is_none());
+        assert!(result.dedup.is_none());
+        assert!(result.color.is_none());
+        assert!(result.annotations.is_none());
+        assert!(result.sanitize.is_none());
+        assert_eq!(result.warnings, ["warning"]);
+        assert_eq!(result.describe(None), tr!(Text::NoGainKeptOriginal));
+    }
+}
@@ -0,0 +1,262 @@
+// 目标大小模式
+//
+// 用户通常只有一个硬性的大小限制（例如邮件网关要求小于 10 MB），而不是某个预设。
+// 这里按从高到低的图像质量阶梯进行二分搜索，找到输出不超过目标大小的最高质量。
+// 每次尝试写入输出路径旁的临时文件，只有最终选中的结果会被重命名为输出文件。
+
+use super::encryption::{encrypt_file, DecryptedCopy, SecurityHandler};
+use super::output::{keep_original_if_larger, requires_rewrite, same_file};
+use super::{format_file_size, CompressionBackend, CompressionJob, CompressionResult, EngineOutput, ImageQuality};
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Text;
+use crate::tr;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// 搜索使用的质量阶梯，从高到低排列，输出大小近似单调递减
+const QUALITY_LADDER: &[(u32, u8)] = &[
+    (300, 90),
+    (250, 85),
+    (200, 80),
+    (150, 70),
+    (150, 50),
+    (120, 40),
+    (96, 35),
+    (72, 30),
+    (72, 20),
+    (50, 15),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TargetSizeReport {
+    /// 要求的最大文件大小
+    pub target_size: u64,
+    /// 实际输出的文件大小
+    pub achieved_size: u64,
+    /// 最终使用的图像参数，原文件已满足目标大小而未重新压缩时为 None
+    pub quality: Option<ImageQuality>,
+    /// 压缩尝试的次数
+    pub attempts: u32,
+}
+
+struct Attempt {
+    index: usize,
+    path: PathBuf,
+    size: u64,
+    output: EngineOutput,
+}
+
+/// 搜索满足目标大小的最高图像质量并写入 `job.output_path`
+pub(super) async fn compress_to_target(
+    backend: &dyn CompressionBackend,
+    job: &CompressionJob,
+    target_size: u64,
+    encrypt: Option<&SecurityHandler>,
+    started: Instant,
+) -> Result<CompressionResult, AppError> {
+    let original_size = fs::metadata(&job.input_path)
+        .map_err(|e| AppError::with_details(ErrorCode::InputUnreadable, e))?
+        .len();
+
+    // 原文件已经满足目标大小时没有必要降低图像质量
+    if original_size <= target_size && !requires_rewrite(job) {
+        if !same_file(&job.input_path, &job.output_path) {
+            fs::copy(&job.input_path, &job.output_path)
+                .map_err(|e| AppError::with_details(ErrorCode::OutputWriteFailed, e))?;
+        }
+        let report = TargetSizeReport {
+            target_size,
+            achieved_size: original_size,
+            quality: None,
+            attempts: 0,
+        };
+        return Ok(CompressionResult::completed(
+            backend,
+            job,
+            original_size,
+            EngineOutput::default(),
+            Some(report),
+            true,
+            started,
+        ));
+    }
+
+    // 外部引擎每次尝试都要重新解密输入，改为只解密一次，所有尝试都读取解密后的副本
+    let decrypted = match &job.options.password {
+        Some(password) if backend.capabilities().external_process => DecryptedCopy::create(&job.input_path, password)?,
+        _ => None,
+    };
+    let decrypted_job = decrypted.as_ref().map(|copy| {
+        let mut decrypted_job = job.clone();
+        decrypted_job.input_path = copy.path().to_path_buf();
+        decrypted_job.options.password = None;
+        decrypted_job
+    });
+    let search_job = decrypted_job.as_ref().unwrap_or(job);
+
+    let mut attempts = 0;
+
+    // 先尝试最低质量，连它都无法满足时没有继续搜索的必要
+    let lowest = QUALITY_LADDER.len() - 1;
+    let mut best = run_attempt(backend, search_job, lowest, encrypt).await?;
+    attempts += 1;
+    if best.size > target_size {
+        let _ = fs::remove_file(&best.path);
+        let (resolution, jpeg_quality) = QUALITY_LADDER[lowest];
+        return Err(AppError::with_details(ErrorCode::TargetSizeUnreachable, tr!(
+            Text::TargetSizeUnreachable,
+            format_file_size(target_size),
+            resolution,
+            jpeg_quality,
+            format_file_size(best.size)
+        )));
+    }
+
+    // 在 [low, high) 中查找第一个满足目标大小的质量等级，`best` 始终是已知满足的最高质量
+    let mut low = 0;
+    let mut high = lowest;
+    while low < high && !job.cancel.is_cancelled() {
+        let middle = (low + high) / 2;
+        let attempt = run_attempt(backend, search_job, middle, encrypt).await;
+        attempts += 1;
+
+        let attempt = match attempt {
+            Ok(attempt) => attempt,
+            Err(e) => {
+                let _ = fs::remove_file(&best.path);
+                return Err(e);
+            }
+        };
+
+        if attempt.size <= target_size {
+            let _ = fs::remove_file(&best.path);
+            best = attempt;
+            high = middle;
+        } else {
+            let _ = fs::remove_file(&attempt.path);
+            low = middle + 1;
+        }
+    }
+
+    if let Err(e) = fs::rename(&best.path, &job.output_path) {
+        let _ = fs::remove_file(&best.path);
+        return Err(AppError::with_details(ErrorCode::OutputWriteFailed, e));
+    }
+
+    let (resolution, jpeg_quality) = QUALITY_LADDER[best.index];
+    let report = TargetSizeReport {
+        target_size,
+        achieved_size: best.size,
+        quality: Some(ImageQuality { resolution, jpeg_quality }),
+        attempts,
+    };
+
+    let kept_original = keep_original_if_larger(job, original_size)?;
+
+    Ok(CompressionResult::completed(
+        backend,
+        job,
+        original_size,
+        best.output,
+        Some(report),
+        kept_original,
+        started,
+    ))
+}
+
+/// 执行一次尝试，需要加密时先加密再统计大小
+async fn run_attempt(
+    backend: &dyn CompressionBackend,
+    job: &CompressionJob,
+    index: usize,
+    encrypt: Option<&SecurityHandler>,
+) -> Result<Attempt, AppError> {
+    let (resolution, jpeg_quality) = QUALITY_LADDER[index];
+    let path = attempt_path(&job.output_path, index);
+
+    let mut attempt_job = job.clone();
+    attempt_job.output_path = path.clone();
+    attempt_job.profile = job.profile.clone().with_image_quality(ImageQuality { resolution, jpeg_quality });
+
+    let output = backend.compress(&attempt_job).await.and_then(|output| {
+        if let Some(handler) = encrypt {
+            encrypt_file(&path, handler)?;
+        }
+        Ok(output)
+    });
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            let _ = fs::remove_file(&path);
+            return Err(e);
+        }
+    };
+
+    let size = fs::metadata(&path)
+        .map_err(|e| AppError::with_details(ErrorCode::OutputWriteFailed, tr!(Text::CannotReadOutputSize, e)))?
+        .len();
+
+    Ok(Attempt { index, path, size, output })
+}
+
+fn attempt_path(output_path: &Path, index: usize) -> PathBuf {
+    let file_name = output_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    output_path.with_file_name(format!(".{}.attempt{}.tmp", file_name, index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{CancelToken, CompressionOptions, CompressionProfile, EngineCapabilities};
+    use futures_util::future::BoxFuture;
+
+    /// 任何压缩尝试都会失败的引擎
+    struct FailingBackend;
+
+    impl CompressionBackend for FailingBackend {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn is_available(&self) -> BoxFuture<'_, bool> {
+            Box::pin(async { true })
+        }
+
+        fn capabilities(&self) -> EngineCapabilities {
+            EngineCapabilities::default()
+        }
+
+        fn compress<'a>(&'a self, _job: &'a CompressionJob) -> BoxFuture<'a, Result<EngineOutput, AppError>> {
+            Box::pin(async { Err(AppError::new(ErrorCode::Internal)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn original_within_target_is_not_recompressed() {
+        let dir = std::env::temp_dir().join(format!("pdf-compressor-target-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let job = CompressionJob {
+            input_path: dir.join("input.pdf"),
+            output_path: dir.join("output.pdf"),
+            profile: CompressionProfile::ebook(),
+            options: CompressionOptions::default(),
+            progress: None,
+            cancel: CancelToken::default(),
+        };
+        fs::write(&job.input_path, b"%PDF-1.4 small").unwrap();
+
+        let result = compress_to_target(&FailingBackend, &job, 1024, None, Instant::now()).await.unwrap();
+        let report = result.target.unwrap();
+        assert!(result.kept_original);
+        assert_eq!(report.attempts, 0);
+        assert!(report.quality.is_none());
+        assert_eq!(fs::read(&job.output_path).unwrap(), b"%PDF-1.4 small");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+// 错误类型
+//
+// 命令和引擎返回带有稳定错误码的 `AppError`：自动化脚本根据 `code` 判断失败原因，
+// `message` 是由错误码决定、按当前语言生成的用户提示，
+// `details` 保留路径、底层错误或外部程序输出等具体信息。
+
+use crate::i18n::current_locale;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 稳定的错误码，序列化为 snake_case，新增错误码时不要修改已有的名称
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// 输入文件或目录不存在
+    InputMissing,
+    /// 输入存在但无法读取
+    InputUnreadable,
+    /// 输入不是有效的 PDF 文件
+    InvalidPdf,
+    /// 输入 PDF 已加密，需要密码
+    EncryptedInput,
+    /// 提供的密码不正确
+    InvalidPassword,
+    /// 批量压缩的输入中没有 PDF 文件
+    NoPdfFound,
+    /// 压缩等级或自定义配置无效
+    InvalidProfile,
+    /// 压缩选项无效
+    InvalidOptions,
+    /// 指定的引擎不存在
+    UnknownEngine,
+    /// 引擎在当前环境中不可用
+    EngineUnavailable,
+    /// 引擎执行失败
+    EngineFailed,
+    /// 外部进程运行超时
+    EngineTimeout,
+    /// 最低质量下仍无法达到目标大小
+    TargetSizeUnreachable,
+    /// 无法写入输出文件或创建输出目录
+    OutputWriteFailed,
+    /// 引擎生成的文件不是完整的 PDF
+    InvalidOutput,
+    /// 输出文件没有声明要求的 PDF/A 级别
+    PdfaVerificationFailed,
+    /// 任务被用户取消
+    Cancelled,
+    /// 同一 ID 的任务已在运行
+    JobAlreadyRunning,
+    /// 找不到指定 ID 的任务
+    JobNotFound,
+    /// 用户关闭了文件对话框
+    DialogCancelled,
+    /// 文件对话框等待超时
+    DialogTimeout,
+    /// 下载 Ghostscript 失败
+    DownloadFailed,
+    /// 安装 Ghostscript 失败
+    InstallFailed,
+    /// 卸载 Ghostscript 失败
+    UninstallFailed,
+    /// 当前平台不支持该操作
+    UnsupportedPlatform,
+    /// 其他内部错误
+    Internal,
+}
+
+impl ErrorCode {
+    /// 错误码对应的当前语言的用户提示
+    pub fn message(self) -> &'static str {
+        let (zh, en) = match self {
+            ErrorCode::InputMissing => ("输入文件不存在", "Input file does not exist"),
+            ErrorCode::InputUnreadable => ("无法读取输入文件", "Cannot read the input file"),
+            ErrorCode::InvalidPdf => ("无法加载PDF文件", "Cannot load the PDF file"),
+            ErrorCode::EncryptedInput => (
+                "PDF 文件已加密，需要密码才能打开",
+                "The PDF is encrypted and requires a password",
+            ),
+            ErrorCode::InvalidPassword => ("PDF 密码不正确", "The PDF password is incorrect"),
+            ErrorCode::NoPdfFound => ("没有找到 PDF 文件", "No PDF files found"),
+            ErrorCode::InvalidProfile => ("压缩配置无效", "Invalid compression profile"),
+            ErrorCode::InvalidOptions => ("压缩选项无效", "Invalid compression options"),
+            ErrorCode::UnknownEngine => ("未知的压缩引擎", "Unknown compression engine"),
+            ErrorCode::EngineUnavailable => ("压缩引擎不可用", "Compression engine is not available"),
+            ErrorCode::EngineFailed => ("压缩失败", "Compression failed"),
+            ErrorCode::EngineTimeout => ("压缩超时", "Compression timed out"),
+            ErrorCode::TargetSizeUnreachable => ("无法压缩到目标大小", "Cannot compress to the target size"),
+            ErrorCode::OutputWriteFailed => ("无法写入输出文件", "Cannot write the output file"),
+            ErrorCode::InvalidOutput => ("压缩结果不是有效的 PDF 文件", "The compressed file is not a valid PDF"),
+            ErrorCode::PdfaVerificationFailed => (
+                "压缩结果不符合要求的 PDF/A 级别",
+                "The compressed file does not declare the requested PDF/A conformance",
+            ),
+            ErrorCode::Cancelled => ("压缩已取消", "Compression cancelled"),
+            ErrorCode::JobAlreadyRunning => ("压缩任务已在运行", "Compression job is already running"),
+            ErrorCode::JobNotFound => ("未找到压缩任务", "Compression job not found"),
+            ErrorCode::DialogCancelled => ("未选择文件", "No file selected"),
+            ErrorCode::DialogTimeout => ("选择文件超时", "Timed out waiting for a file"),
+            ErrorCode::DownloadFailed => ("下载 Ghostscript 失败", "Failed to download Ghostscript"),
+            ErrorCode::InstallFailed => ("安装 Ghostscript 失败", "Failed to install Ghostscript"),
+            ErrorCode::UninstallFailed => ("卸载 Ghostscript 失败", "Failed to uninstall Ghostscript"),
+            ErrorCode::UnsupportedPlatform => (
+                "当前平台不支持该操作",
+                "This operation is not supported on this platform",
+            ),
+            ErrorCode::Internal => ("内部错误", "Internal error"),
+        };
+        current_locale().pick(zh, en)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AppError {
+    pub code: ErrorCode,
+    /// 由错误码决定的用户提示
+    pub message: String,
+    /// 具体的出错信息，例如文件路径或底层错误
+    pub details: Option<String>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode) -> Self {
+        Self {
+            code,
+            message: code.message().to_string(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(code: ErrorCode, details: impl fmt::Display) -> Self {
+        Self {
+            details: Some(details.to_string()),
+            ..Self::new(code)
+        }
+    }
+}
+
+impl From<ErrorCode> for AppError {
+    fn from(code: ErrorCode) -> Self {
+        Self::new(code)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.details {
+            Some(details) => write!(f, "{}: {}", self.message, details),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
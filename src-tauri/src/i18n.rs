@@ -0,0 +1,527 @@
+// 后端消息的本地化
+//
+// 所有面向用户的文本都登记在 `Text` 中，每一项同时提供简体中文和英文模板，
+// 模板中的 `{}` 按顺序替换为参数。当前语言是进程内的全局设置：启动时根据系统的
+// 语言环境变量确定，之后可以由前端的 `set_locale` 命令或命令行的 `--lang` 修改。
+// 消息在生成结果或错误时按当时的语言确定，已经返回的结果不会随语言切换而改变。
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::RwLock;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    En,
+    Zh,
+}
+
+lazy_static::lazy_static! {
+    static ref CURRENT_LOCALE: RwLock<Locale> = RwLock::new(Locale::from_env().unwrap_or(Locale::Zh));
+}
+
+impl Locale {
+    /// 解析 "zh-CN"、"zh_CN.UTF-8"、"en-US" 等语言标签，中文以外的语言都使用英文
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let tag = tag.trim().to_ascii_lowercase();
+        if tag.is_empty() || tag == "c" || tag.starts_with("c.") || tag == "posix" {
+            None
+        } else if tag.starts_with("zh") {
+            Some(Locale::Zh)
+        } else {
+            Some(Locale::En)
+        }
+    }
+
+    /// 按 POSIX 的优先级读取语言环境变量
+    pub fn from_env() -> Option<Self> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find_map(|value| Self::from_tag(&value))
+    }
+
+    /// 从中英文两个版本中选择当前语言的版本
+    pub fn pick<T>(self, zh: T, en: T) -> T {
+        match self {
+            Locale::Zh => zh,
+            Locale::En => en,
+        }
+    }
+}
+
+pub fn current_locale() -> Locale {
+    match CURRENT_LOCALE.read() {
+        Ok(locale) => *locale,
+        Err(poisoned) => *poisoned.into_inner(),
+    }
+}
+
+pub fn set_locale(locale: Locale) {
+    match CURRENT_LOCALE.write() {
+        Ok(mut current) => *current = locale,
+        Err(poisoned) => *poisoned.into_inner() = locale,
+    }
+}
+
+/// 使用当前语言的模板生成消息，一般通过 `tr!` 调用
+pub fn translate(text: Text, args: &[&dyn fmt::Display]) -> String {
+    let template = text.template(current_locale());
+    let mut message = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut rest = template;
+
+    while let Some(index) = rest.find("{}") {
+        message.push_str(&rest[..index]);
+        if let Some(arg) = args.next() {
+            message.push_str(&arg.to_string());
+        }
+        rest = &rest[index + 2..];
+    }
+    message.push_str(rest);
+    message
+}
+
+/// 生成当前语言的消息：`tr!(Text::CannotCreateOutputDir, e)`
+#[macro_export]
+macro_rules! tr {
+    ($text:expr) => {
+        $crate::i18n::translate($text, &[])
+    };
+    ($text:expr, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::translate($text, &[$(&$arg as &dyn ::std::fmt::Display),+])
+    };
+}
+
+/// 消息目录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Text {
+    // 压缩结果
+    CompressionSucceeded,
+    CompressionSucceededWithRatio,
+    NoGainKeptOriginal,
+    NoGainLarger,
+    TargetSizeSummary,
+    TargetSizeAlreadyMet,
+    MetadataCleaned,
+    DuplicatesMerged,
+    BackupCreated,
+    ResultNote,
+    InstallGhostscriptHint,
+    EncryptedNoObjectStreams,
+    EncryptionRemoved,
+    OutputEncrypted,
+    PdfaConverted,
+    ColorsConverted,
+    ColorImagesConverted,
+    ColorModeRgb,
+    ColorModeGray,
+    ColorModeBilevel,
+    ColorImagesSkipped,
+    ColorOperatorsSkipped,
+    PagesKept,
+    FormFieldsFlattened,
+    AnnotationsRemoved,
+    FormFieldsNotFlattened,
+    Sanitized,
+
+    // 配置和选项校验
+    UnknownLevel,
+    ColorResolution,
+    GrayResolution,
+    MonoResolution,
+    ResolutionOutOfRange,
+    DownsampleThresholdTooLow,
+    JpegQualityOutOfRange,
+    UnsupportedCompatibilityLevel,
+    ListSeparator,
+    SubsetFontsRequiresEmbedding,
+    TimeoutMustBePositive,
+    MemoryLimitMustBePositive,
+    CpuTimeLimitMustBePositive,
+    NicenessOutOfRange,
+    Rc4OutputUnsupported,
+    Aes128PasswordNotAscii,
+    PdfaRequiresEmbeddedFonts,
+    PdfaForbidsEncryption,
+    PdfaRequiresRgb,
+    InvalidPageSelection,
+    PageRangeReversed,
+    NoPagesSelected,
+
+    // 压缩过程
+    NoEngineAvailable,
+    CannotCreateOutputDir,
+    CannotReadDirectory,
+    OutputSameAsInput,
+    OutputConflict,
+    GhostscriptNotFound,
+    GhostscriptSpawnFailed,
+    GhostscriptTimedOut,
+    GhostscriptFailed,
+    LopdfTaskPanicked,
+    AnalysisTaskPanicked,
+    SaveFailed,
+    TargetSizeUnreachable,
+    CannotReadOutputSize,
+    CannotCreateBackup,
+    MissingPdfHeader,
+    MissingPdfTrailer,
+    UnsupportedEncryption,
+    CannotEncrypt,
+    PdfaUnsupportedEngine,
+    CannotPostprocess,
+    PdfaMetadataMissing,
+    PdfaNotDeclared,
+    PdfaDeclarationMismatch,
+
+    // 文件对话框
+    PdfFileFilter,
+    NoSaveLocationSelected,
+    SaveDialogTimeout,
+
+    // Ghostscript 安装和卸载
+    AppDataDirUnavailable,
+    HomeDirUnavailable,
+    CannotCreateAppDataDir,
+    CannotCreateGhostscriptDir,
+    CannotSaveGhostscript,
+    MacosInstallHint,
+    LinuxInstallHint,
+    HttpStatus,
+    DownloadInterrupted,
+    CannotCreateDirectory,
+    CannotSaveInstaller,
+    CannotCreateBatchFile,
+    CannotWriteBatchFile,
+    CannotRunInstaller,
+    InstallerExitCode,
+    CannotCopyGhostscript,
+    CannotCopyInstaller,
+    CannotExtractArchive,
+    GhostscriptNotInArchive,
+    SystemRemoveFailed,
+    SystemCommandError,
+    ManualInstallWindows,
+    ManualInstallMacos,
+    ManualInstallLinux,
+
+    // 命令行
+    InvalidGlob,
+    NoMatchingFiles,
+    InvalidInputPath,
+    CannotReadProfile,
+    InvalidProfileFile,
+    InvalidSize,
+    UnknownSizeUnit,
+    UnknownLocale,
+    Warning,
+    JsonSummaryFailed,
+    AnalysisSummary,
+    CategoryImages,
+    CategoryFonts,
+    CategoryContentStreams,
+    CategoryMetadata,
+    CategoryAttachments,
+    CategoryStructure,
+    CategoryObjects,
+    AnalysisImages,
+    AnalysisFonts,
+    AnalysisLargestObjects,
+    ImageMask,
+    FontSubset,
+    FontEmbedded,
+    FontNotEmbedded,
+}
+
+impl Text {
+    pub fn template(self, locale: Locale) -> &'static str {
+        let (zh, en) = match self {
+            Text::CompressionSucceeded => ("PDF 压缩成功！", "PDF compressed successfully!"),
+            Text::CompressionSucceededWithRatio => (
+                "PDF 压缩成功！压缩率: {}% (节省 {})",
+                "PDF compressed successfully! Reduced by {}% (saved {})",
+            ),
+            Text::NoGainKeptOriginal => (
+                "压缩后的文件没有变小，已保留原文件",
+                "Compression did not reduce the file size, the original was kept",
+            ),
+            Text::NoGainLarger => (
+                "压缩后的文件没有变小 (增大 {})",
+                "Compression did not reduce the file size (grew by {})",
+            ),
+            Text::TargetSizeSummary => (
+                "，目标大小 {} (分辨率 {} DPI，JPEG 质量 {})",
+                ", target size {} ({} DPI, JPEG quality {})",
+            ),
+            Text::TargetSizeAlreadyMet => (
+                "，原文件已不超过目标大小 {}，未重新压缩",
+                ", the original already fits the target size {} and was not recompressed",
+            ),
+            Text::MetadataCleaned => ("，清理 {} 项元数据", ", removed {} metadata entries"),
+            Text::DuplicatesMerged => ("，合并 {} 个重复对象 (节省 {})", ", merged {} duplicate objects (saved {})"),
+            Text::BackupCreated => ("，原文件已备份到 {}", ", original backed up to {}"),
+            Text::ResultNote => (" - 注意：{}", " - Note: {}"),
+            Text::InstallGhostscriptHint => (
+                "安装 Ghostscript 可获得更好的压缩效果",
+                "install Ghostscript for better compression",
+            ),
+            Text::EncryptedNoObjectStreams => (
+                "文档已加密，未使用对象流保存",
+                "The document is encrypted and was saved without object streams",
+            ),
+            Text::EncryptionRemoved => (
+                "输入文件已加密，输出文件未加密",
+                "The input was encrypted, the output is not",
+            ),
+            Text::OutputEncrypted => ("，输出已使用 {} 加密", ", output encrypted with {}"),
+            Text::PdfaConverted => ("，已转换为 {}", ", converted to {}"),
+            Text::ColorsConverted => ("，颜色已转换为{}", ", colors converted to {}"),
+            Text::ColorImagesConverted => (" (包括 {} 个图像)", " (including {} images)"),
+            Text::ColorModeRgb => ("RGB", "RGB"),
+            Text::ColorModeGray => ("灰度", "grayscale"),
+            Text::ColorModeBilevel => ("黑白", "black and white"),
+            Text::ColorImagesSkipped => (
+                "{} 个图像的色彩空间或编码不受支持，颜色保持不变",
+                "{} images use an unsupported color space or encoding and were left unchanged",
+            ),
+            Text::ColorOperatorsSkipped => (
+                "{} 处颜色设置使用了无法转换的色彩空间（专色、索引色或图案），颜色保持不变",
+                "{} color settings use a color space that cannot be converted (spot colors, indexed colors or patterns) and were left unchanged",
+            ),
+            Text::PagesKept => ("，保留 {} 页", ", {} pages kept"),
+            Text::FormFieldsFlattened => ("，平展了 {} 个表单域", ", {} form fields flattened"),
+            Text::AnnotationsRemoved => ("，删除了 {} 个注释", ", {} annotations removed"),
+            Text::FormFieldsNotFlattened => (
+                "{} 个表单域没有外观流，无法平展，保留为可填写的表单域",
+                "{} form fields have no appearance stream and were kept as fillable fields instead of being flattened",
+            ),
+            Text::Sanitized => (
+                "，移除了 {} 项脚本、附件、多媒体或隐藏图层",
+                ", {} scripts, attachments, media or hidden layers removed",
+            ),
+
+            Text::UnknownLevel => ("未知的压缩等级: {}", "Unknown compression level: {}"),
+            Text::ColorResolution => ("彩色图像分辨率", "Color image resolution"),
+            Text::GrayResolution => ("灰度图像分辨率", "Gray image resolution"),
+            Text::MonoResolution => ("黑白图像分辨率", "Monochrome image resolution"),
+            Text::ResolutionOutOfRange => (
+                "{}必须在 1 到 {} DPI 之间，当前为 {}",
+                "{} must be between 1 and {} DPI, got {}",
+            ),
+            Text::DownsampleThresholdTooLow => (
+                "降采样阈值不能小于 1.0，当前为 {}",
+                "Downsample threshold must be at least 1.0, got {}",
+            ),
+            Text::JpegQualityOutOfRange => (
+                "JPEG 质量必须在 1 到 100 之间，当前为 {}",
+                "JPEG quality must be between 1 and 100, got {}",
+            ),
+            Text::UnsupportedCompatibilityLevel => (
+                "不支持的 PDF 兼容性版本: {}，可选值为 {}",
+                "Unsupported PDF compatibility level: {}, expected one of {}",
+            ),
+            Text::ListSeparator => ("、", ", "),
+            Text::SubsetFontsRequiresEmbedding => (
+                "字体子集化需要同时开启字体嵌入",
+                "Font subsetting requires font embedding",
+            ),
+            Text::TimeoutMustBePositive => ("超时时间必须大于 0 秒", "Timeout must be greater than 0 seconds"),
+            Text::MemoryLimitMustBePositive => ("内存限制必须大于 0 MB", "Memory limit must be greater than 0 MB"),
+            Text::CpuTimeLimitMustBePositive => (
+                "CPU 时间限制必须大于 0 秒",
+                "CPU time limit must be greater than 0 seconds",
+            ),
+            Text::NicenessOutOfRange => (
+                "进程优先级调整值必须在 0 到 19 之间，当前为 {}",
+                "Niceness must be between 0 and 19, got {}",
+            ),
+            Text::Rc4OutputUnsupported => (
+                "RC4 加密不安全，输出只能使用 AES-128 或 AES-256",
+                "RC4 is insecure, outputs can only be encrypted with AES-128 or AES-256",
+            ),
+            Text::Aes128PasswordNotAscii => (
+                "AES-128 加密的密码只能包含 ASCII 字符",
+                "AES-128 passwords may only contain ASCII characters",
+            ),
+            Text::PdfaRequiresEmbeddedFonts => ("{} 要求嵌入全部字体", "{} requires all fonts to be embedded"),
+            Text::PdfaForbidsEncryption => ("PDF/A 文件不允许加密", "PDF/A files cannot be encrypted"),
+            Text::PdfaRequiresRgb => (
+                "{} 使用 sRGB 输出意图，不能与颜色模式 {} 同时使用",
+                "{} uses an sRGB output intent and cannot be combined with the {} color mode",
+            ),
+            Text::InvalidPageSelection => (
+                "无效的页面范围 \"{}\"，应为逗号分隔的页码或范围，例如 1-5,8,10-",
+                "Invalid page selection \"{}\", expected page numbers or ranges such as 1-5,8,10-",
+            ),
+            Text::PageRangeReversed => ("页面范围 {} 的结束页小于起始页", "Page range {} ends before it starts"),
+            Text::NoPagesSelected => (
+                "页面范围 {} 没有选中任何页面，文档共 {} 页",
+                "Page selection {} matches no pages, the document has {} pages",
+            ),
+
+            Text::NoEngineAvailable => ("没有可用的压缩引擎", "No compression engine is available"),
+            Text::CannotCreateOutputDir => ("无法创建输出目录: {}", "Cannot create output directory: {}"),
+            Text::CannotReadDirectory => ("无法读取目录 {}: {}", "Cannot read directory {}: {}"),
+            Text::OutputSameAsInput => ("输出路径与输入文件相同", "Output path is the same as the input file"),
+            Text::OutputConflict => ("输出路径与其他输入文件冲突", "Output path conflicts with another input file"),
+            Text::GhostscriptNotFound => ("未找到 Ghostscript", "Ghostscript was not found"),
+            Text::GhostscriptSpawnFailed => ("执行 Ghostscript 失败: {}", "Failed to run Ghostscript: {}"),
+            Text::GhostscriptTimedOut => (
+                "Ghostscript 运行超过 {} 秒，已终止",
+                "Ghostscript ran for more than {} seconds and was stopped",
+            ),
+            Text::GhostscriptFailed => ("Ghostscript 压缩失败: {}", "Ghostscript failed: {}"),
+            Text::LopdfTaskPanicked => ("lopdf 压缩任务异常退出: {}", "The lopdf compression task crashed: {}"),
+            Text::AnalysisTaskPanicked => ("PDF 分析任务异常退出: {}", "The PDF analysis task crashed: {}"),
+            Text::SaveFailed => ("保存压缩后的PDF失败: {}", "Failed to save the compressed PDF: {}"),
+            Text::TargetSizeUnreachable => (
+                "目标大小 {}，最低质量 (分辨率 {} DPI，JPEG 质量 {}) 下仍为 {}",
+                "target size {}, but the lowest quality ({} DPI, JPEG quality {}) still produces {}",
+            ),
+            Text::CannotReadOutputSize => (
+                "无法读取压缩后的文件信息: {}",
+                "Cannot read the compressed file: {}",
+            ),
+
+            Text::CannotCreateBackup => ("无法备份原文件: {}", "Cannot back up the original file: {}"),
+            Text::MissingPdfHeader => ("缺少 PDF 文件头", "missing PDF header"),
+            Text::MissingPdfTrailer => (
+                "缺少文件结束标记，文件可能不完整",
+                "missing end-of-file marker, the file may be truncated",
+            ),
+            Text::UnsupportedEncryption => ("不支持的加密方式: {}", "unsupported encryption: {}"),
+            Text::CannotEncrypt => ("无法加密输出文件: {}", "Cannot encrypt the output: {}"),
+            Text::PdfaUnsupportedEngine => ("{} 引擎无法输出 {}，需要 Ghostscript", "the {} engine cannot produce {}, Ghostscript is required"),
+            Text::CannotPostprocess => (
+                "无法处理 Ghostscript 的输出: {}",
+                "Cannot post-process the Ghostscript output: {}",
+            ),
+            Text::PdfaMetadataMissing => ("输出文件缺少 XMP 元数据", "the output has no XMP metadata"),
+            Text::PdfaNotDeclared => ("XMP 元数据中没有 {} 声明", "the XMP metadata does not declare {}"),
+            Text::PdfaDeclarationMismatch => (
+                "XMP 元数据声明的是 {}，要求 {}",
+                "the XMP metadata declares {} instead of {}",
+            ),
+
+            Text::PdfFileFilter => ("PDF 文件", "PDF files"),
+            Text::NoSaveLocationSelected => ("未选择保存位置", "No save location selected"),
+            Text::SaveDialogTimeout => ("选择保存位置超时", "Timed out waiting for a save location"),
+
+            Text::AppDataDirUnavailable => ("无法获取应用数据目录", "Cannot determine the application data directory"),
+            Text::HomeDirUnavailable => ("无法获取用户目录", "Cannot determine the home directory"),
+            Text::CannotCreateAppDataDir => (
+                "无法创建应用数据目录: {}",
+                "Cannot create the application data directory: {}",
+            ),
+            Text::CannotCreateGhostscriptDir => (
+                "创建 Ghostscript 目录失败: {}",
+                "Cannot create the Ghostscript directory: {}",
+            ),
+            Text::CannotSaveGhostscript => ("保存 Ghostscript 失败: {}", "Cannot save Ghostscript: {}"),
+            Text::MacosInstallHint => (
+                "macOS 用户请使用 'brew install ghostscript' 安装 Ghostscript，或从官网下载安装包",
+                "On macOS, install Ghostscript with 'brew install ghostscript' or download it from the official website",
+            ),
+            Text::LinuxInstallHint => (
+                "Linux 用户请使用包管理器安装 Ghostscript，如: sudo apt install ghostscript",
+                "On Linux, install Ghostscript with your package manager, e.g. sudo apt install ghostscript",
+            ),
+            Text::HttpStatus => ("HTTP状态码: {}", "HTTP status: {}"),
+            Text::DownloadInterrupted => ("下载过程中出错: {}", "Download interrupted: {}"),
+            Text::CannotCreateDirectory => ("创建目录失败: {}", "Cannot create directory: {}"),
+            Text::CannotSaveInstaller => ("保存安装包失败: {}", "Cannot save the installer: {}"),
+            Text::CannotCreateBatchFile => ("创建批处理文件失败: {}", "Cannot create the batch file: {}"),
+            Text::CannotWriteBatchFile => ("写入批处理文件失败: {}", "Cannot write the batch file: {}"),
+            Text::CannotRunInstaller => ("执行安装失败: {}", "Cannot run the installer: {}"),
+            Text::InstallerExitCode => ("错误码: {}", "exit code: {}"),
+            Text::CannotCopyGhostscript => (
+                "复制 Ghostscript 可执行文件失败: {}",
+                "Cannot copy the Ghostscript executable: {}",
+            ),
+            Text::CannotCopyInstaller => ("复制安装程序失败: {}", "Cannot copy the installer: {}"),
+            Text::CannotExtractArchive => ("解压失败: {}", "Cannot extract the archive: {}"),
+            Text::GhostscriptNotInArchive => (
+                "在解压的文件中找不到 Ghostscript 可执行文件",
+                "The Ghostscript executable was not found in the archive",
+            ),
+            Text::SystemRemoveFailed => ("使用系统命令删除失败: {}", "The system command could not remove it: {}"),
+            Text::SystemCommandError => ("{} (系统命令错误: {})", "{} (system command error: {})"),
+            Text::ManualInstallWindows => (
+                "Windows 用户安装说明：\n\
+                 1. 访问 https://www.ghostscript.com/releases/gsdnld.html\n\
+                 2. 下载适合您系统的版本（64位或32位）\n\
+                 3. 运行安装程序并按照提示完成安装\n\
+                 4. 重启应用以检测已安装的 Ghostscript",
+                "Installing on Windows:\n\
+                 1. Visit https://www.ghostscript.com/releases/gsdnld.html\n\
+                 2. Download the version for your system (64-bit or 32-bit)\n\
+                 3. Run the installer and follow the prompts\n\
+                 4. Restart the app to detect the installed Ghostscript",
+            ),
+            Text::ManualInstallMacos => (
+                "macOS 用户安装说明：\n\
+                 方法1 - 使用 Homebrew（推荐）：\n\
+                 1. 打开终端\n\
+                 2. 运行命令：brew install ghostscript\n\
+                 \n\
+                 方法2 - 手动安装：\n\
+                 1. 访问 https://www.ghostscript.com/releases/gsdnld.html\n\
+                 2. 下载 macOS 版本\n\
+                 3. 按照安装说明完成安装\n\
+                 4. 重启应用以检测已安装的 Ghostscript",
+                "Installing on macOS:\n\
+                 Option 1 - Homebrew (recommended):\n\
+                 1. Open Terminal\n\
+                 2. Run: brew install ghostscript\n\
+                 \n\
+                 Option 2 - Manual install:\n\
+                 1. Visit https://www.ghostscript.com/releases/gsdnld.html\n\
+                 2. Download the macOS version\n\
+                 3. Follow the installation instructions\n\
+                 4. Restart the app to detect the installed Ghostscript",
+            ),
+            Text::ManualInstallLinux => (
+                "Linux 用户安装说明：\n\
+                 Ubuntu/Debian：sudo apt install ghostscript\n\
+                 Fedora/RHEL：sudo dnf install ghostscript\n\
+                 Arch Linux：sudo pacman -S ghostscript\n\
+                 \n\
+                 安装完成后重启应用以检测已安装的 Ghostscript",
+                "Installing on Linux:\n\
+                 Ubuntu/Debian: sudo apt install ghostscript\n\
+                 Fedora/RHEL: sudo dnf install ghostscript\n\
+                 Arch Linux: sudo pacman -S ghostscript\n\
+                 \n\
+                 Restart the app after installing to detect Ghostscript",
+            ),
+
+            Text::InvalidGlob => ("无效的通配符 {}: {}", "Invalid glob pattern {}: {}"),
+            Text::NoMatchingFiles => ("没有找到匹配的文件: {}", "No files match: {}"),
+            Text::InvalidInputPath => ("无效的输入路径: {}", "Invalid input path: {}"),
+            Text::CannotReadProfile => ("无法读取压缩配置 {}: {}", "Cannot read compression profile {}: {}"),
+            Text::InvalidProfileFile => ("无效的压缩配置 {}: {}", "Invalid compression profile {}: {}"),
+            Text::InvalidSize => ("无效的文件大小: {}", "Invalid file size: {}"),
+            Text::UnknownSizeUnit => ("未知的大小单位: {}", "Unknown size unit: {}"),
+            Text::UnknownLocale => ("未知的语言: {}", "Unknown language: {}"),
+            Text::Warning => ("警告: {}", "warning: {}"),
+            Text::JsonSummaryFailed => ("无法生成 JSON 摘要: {}", "Cannot write the JSON summary: {}"),
+            Text::AnalysisSummary => ("{}: {}，PDF {}，{} 页，{} 个对象", "{}: {}, PDF {}, {} pages, {} objects"),
+            Text::CategoryImages => ("图像", "Images"),
+            Text::CategoryFonts => ("字体", "Fonts"),
+            Text::CategoryContentStreams => ("内容流", "Content streams"),
+            Text::CategoryMetadata => ("元数据", "Metadata"),
+            Text::CategoryAttachments => ("附件", "Attachments"),
+            Text::CategoryStructure => ("结构开销", "Structure overhead"),
+            Text::CategoryObjects => ("{} 个对象", "{} objects"),
+            Text::AnalysisImages => ("图像（共 {} 个）:", "Images ({} total):"),
+            Text::AnalysisFonts => ("字体（共 {} 个）:", "Fonts ({} total):"),
+            Text::AnalysisLargestObjects => ("最大的对象:", "Largest objects:"),
+            Text::ImageMask => ("蒙版", "mask"),
+            Text::FontSubset => ("子集嵌入", "embedded subset"),
+            Text::FontEmbedded => ("完整嵌入", "fully embedded"),
+            Text::FontNotEmbedded => ("未嵌入", "not embedded"),
+        };
+        locale.pick(zh, en)
+    }
+}
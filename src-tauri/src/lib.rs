@@ -1,16 +1,23 @@
+pub mod engine;
+pub mod optimize;
+pub mod error;
+pub mod i18n;
+
 use serde::{Deserialize, Serialize};
-use lopdf::Document;
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tauri::Emitter;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct CompressionResult {
-    success: bool,
-    message: String,
-}
+use error::{AppError, ErrorCode};
+use i18n::{Locale, Text};
+use engine::{
+    get_app_data_dir, get_bundled_ghostscript_path, is_ghostscript_available, refresh_ghostscript_command,
+    BatchFileResult, CancelToken, CompressionJob, CompressionOptions, CompressionProfile, CompressionProgress,
+    CompressionResult, EngineInfo, ProgressReporter, SizeAnalysis, AUTO_ENGINE, DEFAULT_TOP_OBJECTS,
+};
 
 // 全局状态，用于跟踪 Ghostscript 的下载和安装状态
 lazy_static::lazy_static! {
@@ -20,8 +27,13 @@ lazy_static::lazy_static! {
         download_progress: 0.0,
         executable_path: None,
     }));
+
+    // 正在运行的压缩任务，任务 ID -> 取消标记
+    static ref COMPRESSION_JOBS: Arc<Mutex<HashMap<String, CancelToken>>> = Arc::new(Mutex::new(HashMap::new()));
 }
 
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
 #[derive(Debug, Clone)]
 struct GhostscriptState {
     is_installed: bool,
@@ -37,13 +49,26 @@ struct GhostscriptStatus {
     download_progress: f32,
 }
 
+/// `compression-started` 和 `compression-progress` 事件中用于区分任务的 ID
+#[derive(Serialize, Debug, Clone)]
+struct JobEvent<T: Serialize> {
+    job_id: String,
+    #[serde(flatten)]
+    payload: T,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct JobStarted {
+    input_paths: Vec<String>,
+}
+
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
 #[tauri::command]
-async fn select_input_file(app_handle: tauri::AppHandle) -> Result<String, String> {
+async fn select_input_file(app_handle: tauri::AppHandle) -> Result<String, AppError> {
     use tauri_plugin_dialog::DialogExt;
     use tokio::sync::oneshot;
     
@@ -51,7 +76,7 @@ async fn select_input_file(app_handle: tauri::AppHandle) -> Result<String, Strin
     
     app_handle.dialog()
         .file()
-        .add_filter("PDF 文件", &["pdf"])
+        .add_filter(tr!(Text::PdfFileFilter), &["pdf"])
         .pick_file(move |file_path| {
             let _ = tx.send(file_path);
         });
@@ -59,14 +84,14 @@ async fn select_input_file(app_handle: tauri::AppHandle) -> Result<String, Strin
     // 等待用户选择文件
     match tokio::time::timeout(std::time::Duration::from_secs(30), rx).await {
         Ok(Ok(Some(path))) => Ok(path.to_string()),
-        Ok(Ok(None)) => Err("未选择文件".to_string()),
-        Ok(Err(_)) => Err("内部错误".to_string()),
-        Err(_) => Err("选择文件超时".to_string()),
+        Ok(Ok(None)) => Err(AppError::new(ErrorCode::DialogCancelled)),
+        Ok(Err(_)) => Err(AppError::new(ErrorCode::Internal)),
+        Err(_) => Err(AppError::new(ErrorCode::DialogTimeout)),
     }
 }
 
 #[tauri::command]
-async fn select_output_path(app_handle: tauri::AppHandle) -> Result<String, String> {
+async fn select_output_path(app_handle: tauri::AppHandle) -> Result<String, AppError> {
     use tauri_plugin_dialog::DialogExt;
     use tokio::sync::oneshot;
     
@@ -74,7 +99,7 @@ async fn select_output_path(app_handle: tauri::AppHandle) -> Result<String, Stri
     
     app_handle.dialog()
         .file()
-        .add_filter("PDF 文件", &["pdf"])
+        .add_filter(tr!(Text::PdfFileFilter), &["pdf"])
         .save_file(move |file_path| {
             let _ = tx.send(file_path);
         });
@@ -82,358 +107,165 @@ async fn select_output_path(app_handle: tauri::AppHandle) -> Result<String, Stri
     // 等待用户选择保存位置
     match tokio::time::timeout(std::time::Duration::from_secs(30), rx).await {
         Ok(Ok(Some(path))) => Ok(path.to_string()),
-        Ok(Ok(None)) => Err("未选择保存位置".to_string()),
-        Ok(Err(_)) => Err("内部错误".to_string()),
-        Err(_) => Err("选择保存位置超时".to_string()),
+        Ok(Ok(None)) => Err(AppError::with_details(ErrorCode::DialogCancelled, tr!(Text::NoSaveLocationSelected))),
+        Ok(Err(_)) => Err(AppError::new(ErrorCode::Internal)),
+        Err(_) => Err(AppError::with_details(ErrorCode::DialogTimeout, tr!(Text::SaveDialogTimeout))),
     }
 }
 
-#[tauri::command]
-async fn compress_pdf(input_path: String, output_path: String, compression_level: String) -> Result<CompressionResult, String> {
-    
-    // 检查输入文件是否存在
-    if !std::path::Path::new(&input_path).exists() {
-        return Err("输入文件不存在".to_string());
-    }
+/// 把引擎的进度转发为 `compression-progress` 事件
+fn progress_reporter(app_handle: tauri::AppHandle, job_id: String) -> ProgressReporter {
+    ProgressReporter::new(move |progress: CompressionProgress| {
+        let _ = app_handle.emit("compression-progress", JobEvent {
+            job_id: job_id.clone(),
+            payload: progress,
+        });
+    })
+}
 
-    // 检查输出目录是否存在，如果不存在则创建
-    if let Some(parent) = std::path::Path::new(&output_path).parent() {
-        if !parent.exists() {
-            if let Err(e) = std::fs::create_dir_all(parent) {
-                return Err(format!("无法创建输出目录: {}", e));
-            }
+/// 登记一个新的压缩任务，前端未指定 ID 时自动生成，并发出 `compression-started` 事件
+async fn start_job(
+    app_handle: &tauri::AppHandle,
+    job_id: Option<String>,
+    input_paths: Vec<String>,
+) -> Result<(String, CancelToken), AppError> {
+    let job_id = job_id.unwrap_or_else(|| format!("job-{}", NEXT_JOB_ID.fetch_add(1, Ordering::SeqCst)));
+    let cancel = CancelToken::new();
+
+    {
+        let mut jobs = COMPRESSION_JOBS.lock().await;
+        if jobs.contains_key(&job_id) {
+            return Err(AppError::with_details(ErrorCode::JobAlreadyRunning, job_id));
         }
+        jobs.insert(job_id.clone(), cancel.clone());
     }
 
-    // 首先检查 Ghostscript 是否可用
-    if is_ghostscript_available() {
-        // 使用 Ghostscript 进行高效压缩
-        compress_with_ghostscript(&input_path, &output_path, &compression_level).await
-    } else {
-        // 回退到 lopdf + 增强优化
-        compress_with_enhanced_lopdf(&input_path, &output_path, &compression_level).await
-    }
-}
+    let _ = app_handle.emit("compression-started", JobEvent {
+        job_id: job_id.clone(),
+        payload: JobStarted { input_paths },
+    });
 
-fn is_ghostscript_available() -> bool {
-    // 首先检查系统是否已安装 Ghostscript
-    let commands = ["gs", "gswin64c", "gswin32c"]; // 支持不同平台的 gs 命令
-    
-    for cmd in &commands {
-        if let Ok(output) = std::process::Command::new(cmd)
-            .arg("--version")
-            .output()
-        {
-            if output.status.success() {
-                return true;
-            }
-        }
-    }
-    
-    // 检查是否有捆绑的 Ghostscript
-    if let Ok(bundled_path) = get_bundled_ghostscript_path() {
-        if std::path::Path::new(&bundled_path).exists() {
-            return true;
-        }
-    }
-    
-    false
+    Ok((job_id, cancel))
 }
 
-fn get_bundled_ghostscript_path() -> Result<String, String> {
-    let app_data_dir = get_app_data_dir()?;
-    let gs_dir = app_data_dir.join("ghostscript");
-    
-    let gs_executable = if cfg!(target_os = "windows") {
-        "gs.exe"
-    } else {
-        "gs"
-    };
-    
-    let gs_path = gs_dir.join(gs_executable);
-    Ok(gs_path.to_string_lossy().to_string())
+async fn finish_job(job_id: &str) {
+    COMPRESSION_JOBS.lock().await.remove(job_id);
 }
 
-async fn compress_with_ghostscript(input_path: &str, output_path: &str, compression_level: &str) -> Result<CompressionResult, String> {
-    use std::fs;
-    
-    // 获取原始文件大小
-    let original_size = fs::metadata(input_path)
-        .map_err(|e| format!("无法读取原始文件信息: {}", e))?
-        .len();
-
-    // 根据压缩等级设置 Ghostscript 参数
-    let (pdf_settings, additional_args) = match compression_level {
-        "/screen" => ("/screen", vec![
-            "-dColorImageResolution=72",
-            "-dGrayImageResolution=72", 
-            "-dMonoImageResolution=300",
-            "-dColorImageDownsampleType=/Bicubic",
-            "-dGrayImageDownsampleType=/Bicubic",
-            "-dColorImageDownsampleThreshold=1.5",
-            "-dGrayImageDownsampleThreshold=1.5",
-            "-dEncodeColorImages=true",
-            "-dEncodeGrayImages=true",
-            "-dColorImageFilter=/DCTEncode",
-            "-dGrayImageFilter=/DCTEncode",
-            "-dJPEGQ=30",
-        ]),
-        "/ebook" => ("/ebook", vec![
-            "-dColorImageResolution=150",
-            "-dGrayImageResolution=150",
-            "-dMonoImageResolution=300",
-            "-dColorImageDownsampleType=/Bicubic",
-            "-dGrayImageDownsampleType=/Bicubic",
-            "-dJPEGQ=50",
-        ]),
-        "/printer" => ("/printer", vec![
-            "-dColorImageResolution=300",
-            "-dGrayImageResolution=300",
-            "-dMonoImageResolution=1200",
-            "-dJPEGQ=80",
-        ]),
-        "/prepress" => ("/prepress", vec![
-            "-dColorImageResolution=300",
-            "-dGrayImageResolution=300", 
-            "-dMonoImageResolution=1200",
-            "-dJPEGQ=90",
-            "-dPreserveAnnots=true",
-            "-dPreserveMarkedContent=true",
-        ]),
-        _ => ("/ebook", vec!["-dJPEGQ=50"]),
+#[tauri::command]
+async fn compress_pdf(
+    app_handle: tauri::AppHandle,
+    input_path: String,
+    output_path: String,
+    compression_level: String,
+    engine: Option<String>,
+    options: Option<CompressionOptions>,
+    job_id: Option<String>,
+) -> Result<CompressionResult, AppError> {
+    let options = options.unwrap_or_default();
+    let profile = engine::resolve_profile(&compression_level, &options)?;
+    let (job_id, cancel) = start_job(&app_handle, job_id, vec![input_path.clone()]).await?;
+
+    let job = CompressionJob {
+        input_path: PathBuf::from(input_path),
+        output_path: PathBuf::from(output_path),
+        profile,
+        options,
+        progress: Some(progress_reporter(app_handle, job_id.clone())),
+        cancel,
     };
 
-    // 构建 Ghostscript 命令
-    let gs_command = find_ghostscript_command();
-    let mut cmd = Command::new(&gs_command);
-    
-    cmd.args(&[
-        "-sDEVICE=pdfwrite",
-        "-dCompatibilityLevel=1.4",
-        &format!("-dPDFSETTINGS={}", pdf_settings),
-        "-dNOPAUSE",
-        "-dQUIET", 
-        "-dBATCH",
-        "-dSAFER",
-        "-dAutoRotatePages=/None",
-        "-dColorConversionStrategy=/LeaveColorUnchanged",
-        "-dDownsampleColorImages=true",
-        "-dDownsampleGrayImages=true",
-        "-dDownsampleMonoImages=true",
-        "-dOptimize=true",
-        "-dEmbedAllFonts=true",
-        "-dSubsetFonts=true",
-        "-dCompressFonts=true",
-        "-dNOPLATFONTS",
-    ]);
-    
-    // 添加额外参数
-    cmd.args(&additional_args);
-    
-    // 添加输出和输入文件
-    cmd.args(&[
-        &format!("-sOutputFile={}", output_path),
-        input_path,
-    ]);
-
-    // 执行压缩
-    match cmd.output() {
-        Ok(output) => {
-            if output.status.success() {
-                // 计算压缩比
-                if let Ok(compressed_size) = fs::metadata(output_path).map(|m| m.len()) {
-                    let compression_ratio = ((original_size as f64 - compressed_size as f64) / original_size as f64) * 100.0;
-                    let size_reduction = format_file_size(original_size - compressed_size);
-                    
-                    Ok(CompressionResult {
-                        success: true,
-                        message: format!(
-                            "PDF 压缩成功！压缩率: {:.1}% (节省 {})", 
-                            compression_ratio, 
-                            size_reduction
-                        ),
-                    })
-                } else {
-                    Ok(CompressionResult {
-                        success: true,
-                        message: "PDF 压缩成功！".to_string(),
-                    })
-                }
-            } else {
-                let error_msg = String::from_utf8_lossy(&output.stderr);
-                Err(format!("Ghostscript 压缩失败: {}", error_msg))
-            }
-        }
-        Err(e) => Err(format!("执行 Ghostscript 失败: {}", e)),
-    }
+    // 未指定引擎时自动选择：优先 Ghostscript，回退到 lopdf
+    let result = engine::compress_file(&job, engine.as_deref().unwrap_or(AUTO_ENGINE)).await;
+    finish_job(&job_id).await;
+    result
 }
 
-async fn compress_with_enhanced_lopdf(input_path: &str, output_path: &str, compression_level: &str) -> Result<CompressionResult, String> {
-    use std::fs;
-    
-    // 获取原始文件大小
-    let original_size = fs::metadata(input_path)
-        .map_err(|e| format!("无法读取原始文件信息: {}", e))?
-        .len();
-
-    // 使用增强的 lopdf 压缩
-    match Document::load(input_path) {
-        Ok(mut document) => {
-            // 根据压缩等级设置不同的压缩参数
-            let optimization_level = match compression_level {
-                "/screen" => "aggressive",
-                "/ebook" => "balanced", 
-                "/printer" => "quality",
-                "/prepress" => "maximum",
-                _ => "balanced",
-            };
-
-            // 执行增强的压缩优化
-            enhanced_pdf_optimization(&mut document, optimization_level);
-
-            // 保存压缩后的PDF
-            match document.save(output_path) {
-                Ok(_) => {
-                    // 计算压缩比
-                    if let Ok(compressed_size) = fs::metadata(output_path).map(|m| m.len()) {
-                        let compression_ratio = ((original_size as f64 - compressed_size as f64) / original_size as f64) * 100.0;
-                        let size_reduction = format_file_size(original_size - compressed_size);
-                        
-                        Ok(CompressionResult {
-                            success: true,
-                            message: format!(
-                                "PDF 压缩完成！压缩率: {:.1}% (节省 {}) - 注意：安装 Ghostscript 可获得更好的压缩效果", 
-                                compression_ratio, 
-                                size_reduction
-                            ),
-                        })
-                    } else {
-                        Ok(CompressionResult {
-                            success: true,
-                            message: "PDF 压缩完成！建议安装 Ghostscript 以获得更好的压缩效果".to_string(),
-                        })
-                    }
-                }
-                Err(e) => Err(format!("保存压缩后的PDF失败: {}", e)),
-            }
-        }
-        Err(e) => Err(format!("无法加载PDF文件: {}", e)),
-    }
+/// 批量压缩文件和文件夹，文件夹中的 PDF 会按原有目录结构输出到 `output_dir`
+#[tauri::command]
+async fn compress_batch(
+    app_handle: tauri::AppHandle,
+    input_paths: Vec<String>,
+    output_dir: String,
+    compression_level: String,
+    engine: Option<String>,
+    options: Option<CompressionOptions>,
+    job_id: Option<String>,
+) -> Result<Vec<BatchFileResult>, AppError> {
+    let options = options.unwrap_or_default();
+    let profile = engine::resolve_profile(&compression_level, &options)?;
+    let (job_id, cancel) = start_job(&app_handle, job_id, input_paths.clone()).await?;
+    let inputs: Vec<PathBuf> = input_paths.into_iter().map(PathBuf::from).collect();
+
+    let results = engine::compress_batch(
+        &inputs,
+        &PathBuf::from(output_dir),
+        &profile,
+        engine.as_deref().unwrap_or(AUTO_ENGINE),
+        &options,
+        Some(&progress_reporter(app_handle, job_id.clone())),
+        &cancel,
+    )
+    .await;
+    finish_job(&job_id).await;
+    results
 }
 
-fn find_ghostscript_command() -> String {
-    // 首先检查系统安装的 Ghostscript
-    let commands = ["gs", "gswin64c", "gswin32c"];
-    
-    for cmd in &commands {
-        if let Ok(output) = std::process::Command::new(cmd)
-            .arg("--version")
-            .output()
-        {
-            if output.status.success() {
-                return cmd.to_string();
-            }
-        }
-    }
-    
-    // 如果系统没有安装，尝试使用捆绑的版本
-    if let Ok(bundled_path) = get_bundled_ghostscript_path() {
-        if std::path::Path::new(&bundled_path).exists() {
-            return bundled_path;
-        }
-    }
-    
-    "gs".to_string() // 默认返回 gs
+/// 取消正在运行的压缩任务，任务会以 cancelled 状态结束并删除部分输出
+#[tauri::command]
+async fn cancel_compression(job_id: String) -> Result<(), AppError> {
+    let jobs = COMPRESSION_JOBS.lock().await;
+    let cancel = jobs
+        .get(&job_id)
+        .ok_or_else(|| AppError::with_details(ErrorCode::JobNotFound, &job_id))?;
+    cancel.cancel();
+    Ok(())
 }
 
-fn format_file_size(bytes: u64) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
-    let mut size = bytes as f64;
-    let mut unit_index = 0;
-    
-    while size >= 1024.0 && unit_index < UNITS.len() - 1 {
-        size /= 1024.0;
-        unit_index += 1;
-    }
-    
-    if unit_index == 0 {
-        format!("{} {}", size as u64, UNITS[unit_index])
-    } else {
-        format!("{:.1} {}", size, UNITS[unit_index])
-    }
+/// 分析 PDF 中图像、字体、内容流等各部分占用的空间，`top` 为列出的最大对象数量
+#[tauri::command]
+async fn analyze_pdf(input_path: String, password: Option<String>, top: Option<usize>) -> Result<SizeAnalysis, AppError> {
+    engine::analyze_file(
+        std::path::Path::new(&input_path),
+        password.as_deref(),
+        top.unwrap_or(DEFAULT_TOP_OBJECTS),
+    )
+    .await
 }
 
-fn enhanced_pdf_optimization(document: &mut Document, optimization_level: &str) {
-    // 执行基本的 PDF 结构优化
-    document.compress();
-    
-    // 根据优化级别进行不同程度的优化
-    let object_ids: Vec<_> = document.objects.keys().copied().collect();
-    
-    for object_id in object_ids {
-        if let Ok(object) = document.get_object(object_id) {
-            match object {
-                lopdf::Object::Stream(ref _stream) => {
-                    // lopdf 的流对象优化能力有限
-                    // 主要依赖 document.compress() 进行基础优化
-                }
-                lopdf::Object::Dictionary(ref _dict) => {
-                    // 字典对象的基础优化
-                    if matches!(optimization_level, "aggressive" | "balanced") {
-                        // 可以在这里添加元数据清理等操作
-                    }
-                }
-                _ => {}
-            }
-        }
-    }
-    
-    // 执行垃圾回收，移除未引用的对象
-    document.prune_objects();
+/// 返回内置的压缩配置，供前端作为自定义配置的起点
+#[tauri::command]
+async fn get_compression_profile(compression_level: String) -> Result<CompressionProfile, AppError> {
+    CompressionProfile::from_level(&compression_level)
 }
 
+#[tauri::command]
+async fn list_compression_engines() -> Result<Vec<EngineInfo>, AppError> {
+    Ok(engine::registry().engine_infos().await)
+}
 
+/// 设置后端消息使用的语言，接受 "en"、"zh-CN" 等语言标签，返回实际使用的语言
+#[tauri::command]
+async fn set_locale(locale: String) -> Result<Locale, AppError> {
+    let parsed = Locale::from_tag(&locale)
+        .ok_or_else(|| AppError::with_details(ErrorCode::InvalidOptions, tr!(Text::UnknownLocale, locale)))?;
+    i18n::set_locale(parsed);
+    Ok(parsed)
+}
 
-fn get_app_data_dir() -> Result<PathBuf, String> {
-    
-    // 获取应用数据目录
-    let app_data = if cfg!(target_os = "windows") {
-        // 在Windows上，尝试使用LOCALAPPDATA而不是APPDATA，避免漫游配置文件的问题
-        std::env::var("LOCALAPPDATA")
-            .or_else(|_| std::env::var("APPDATA"))
-            .map(PathBuf::from)
-            .map_err(|_| "无法获取应用数据目录".to_string())?
-    } else if cfg!(target_os = "macos") {
-        std::env::var("HOME")
-            .map(|home| PathBuf::from(home).join("Library").join("Application Support"))
-            .map_err(|_| "无法获取用户目录".to_string())?
-    } else {
-        std::env::var("HOME")
-            .map(|home| PathBuf::from(home).join(".local").join("share"))
-            .map_err(|_| "无法获取用户目录".to_string())?
-    };
-    
-    let app_dir = app_data.join("PDF_Compressor");
-    
-    // 确保目录存在
-    if !app_dir.exists() {
-        std::fs::create_dir_all(&app_dir)
-            .map_err(|e| format!("无法创建应用数据目录: {}", e))?;
-    }
-    
-    println!("应用数据目录: {}", app_dir.to_string_lossy());
-    
-    Ok(app_dir)
+#[tauri::command]
+async fn get_locale() -> Result<Locale, AppError> {
+    Ok(i18n::current_locale())
 }
 
-async fn extract_ghostscript_binary(target_path: &std::path::Path) -> Result<(), String> {
+async fn extract_ghostscript_binary(target_path: &std::path::Path) -> Result<(), AppError> {
     // 自动下载并安装 Ghostscript
     let gs_dir = target_path.parent().unwrap();
     
     // 确保目录存在
     if !gs_dir.exists() {
         std::fs::create_dir_all(gs_dir)
-            .map_err(|e| format!("创建 Ghostscript 目录失败: {}", e))?;
+            .map_err(|e| AppError::with_details(ErrorCode::InstallFailed, tr!(Text::CannotCreateGhostscriptDir, e)))?;
     }
     
     println!("Ghostscript 安装目录: {}", gs_dir.to_string_lossy());
@@ -477,7 +309,7 @@ async fn extract_ghostscript_binary(target_path: &std::path::Path) -> Result<(),
             // 直接保存二进制文件
             println!("保存二进制文件到: {}", target_path.to_string_lossy());
             std::fs::write(target_path, downloaded_data)
-                .map_err(|e| format!("保存 Ghostscript 失败: {}", e))?;
+                .map_err(|e| AppError::with_details(ErrorCode::InstallFailed, tr!(Text::CannotSaveGhostscript, e)))?;
         }
     }
     
@@ -492,7 +324,7 @@ async fn extract_ghostscript_binary(target_path: &std::path::Path) -> Result<(),
     Ok(())
 }
 
-fn get_ghostscript_download_info() -> Result<(String, bool), String> {
+fn get_ghostscript_download_info() -> Result<(String, bool), AppError> {
     // 根据操作系统返回对应的下载链接
     if cfg!(target_os = "windows") {
         if cfg!(target_arch = "x86_64") {
@@ -511,14 +343,20 @@ fn get_ghostscript_download_info() -> Result<(String, bool), String> {
         // macOS 使用 Homebrew 提供的预编译版本
         // 由于直接下载二进制文件比较复杂，我们使用一个简化的方案
         // 提示用户手动安装或使用系统已安装的版本
-        Err("macOS 用户请使用 'brew install ghostscript' 安装 Ghostscript".to_string())
+        Err(AppError::with_details(
+            ErrorCode::UnsupportedPlatform,
+            tr!(Text::MacosInstallHint),
+        ))
     } else {
         // Linux 用户也建议使用包管理器安装
-        Err("Linux 用户请使用包管理器安装 Ghostscript，如: sudo apt install ghostscript".to_string())
+        Err(AppError::with_details(
+            ErrorCode::UnsupportedPlatform,
+            tr!(Text::LinuxInstallHint),
+        ))
     }
 }
 
-async fn download_file(url: &str) -> Result<Vec<u8>, String> {
+async fn download_file(url: &str) -> Result<Vec<u8>, AppError> {
     use reqwest;
     use futures_util::StreamExt;
     
@@ -527,10 +365,13 @@ async fn download_file(url: &str) -> Result<Vec<u8>, String> {
         .get(url)
         .send()
         .await
-        .map_err(|e| format!("下载失败: {}", e))?;
+        .map_err(|e| AppError::with_details(ErrorCode::DownloadFailed, e))?;
     
     if !response.status().is_success() {
-        return Err(format!("下载失败，HTTP状态码: {}", response.status()));
+        return Err(AppError::with_details(
+            ErrorCode::DownloadFailed,
+            tr!(Text::HttpStatus, response.status()),
+        ));
     }
     
     // 获取内容长度（如果有）
@@ -545,7 +386,7 @@ async fn download_file(url: &str) -> Result<Vec<u8>, String> {
     let mut gs_state = GHOSTSCRIPT_STATE.lock().await;
     
     while let Some(item) = stream.next().await {
-        let chunk = item.map_err(|e| format!("下载过程中出错: {}", e))?;
+        let chunk = item.map_err(|e| AppError::with_details(ErrorCode::DownloadFailed, tr!(Text::DownloadInterrupted, e)))?;
         downloaded += chunk.len() as u64;
         bytes.extend_from_slice(&chunk);
         
@@ -561,7 +402,7 @@ async fn download_file(url: &str) -> Result<Vec<u8>, String> {
     Ok(bytes)
 }
 
-fn extract_archive(data: &[u8], extract_dir: &std::path::Path, target_path: &std::path::Path) -> Result<(), String> {
+fn extract_archive(data: &[u8], extract_dir: &std::path::Path, target_path: &std::path::Path) -> Result<(), AppError> {
     
     if cfg!(target_os = "windows") {
         // Windows 的 .exe 安装包需要特殊处理
@@ -573,7 +414,7 @@ fn extract_archive(data: &[u8], extract_dir: &std::path::Path, target_path: &std
     }
 }
 
-fn extract_windows_installer(data: &[u8], extract_dir: &std::path::Path, target_path: &std::path::Path) -> Result<(), String> {
+fn extract_windows_installer(data: &[u8], extract_dir: &std::path::Path, target_path: &std::path::Path) -> Result<(), AppError> {
     use std::process::Command;
     use std::fs;
     use std::io::Write;
@@ -581,13 +422,13 @@ fn extract_windows_installer(data: &[u8], extract_dir: &std::path::Path, target_
     // 确保目录存在
     if !extract_dir.exists() {
         fs::create_dir_all(extract_dir)
-            .map_err(|e| format!("创建目录失败: {}", e))?;
+            .map_err(|e| AppError::with_details(ErrorCode::InstallFailed, tr!(Text::CannotCreateDirectory, e)))?;
     }
     
     // 保存安装包到临时位置
     let installer_path = extract_dir.join("gs_installer.exe");
     fs::write(&installer_path, data)
-        .map_err(|e| format!("保存安装包失败: {}", e))?;
+        .map_err(|e| AppError::with_details(ErrorCode::InstallFailed, tr!(Text::CannotSaveInstaller, e)))?;
     
     // 创建一个批处理文件来执行安装，避免路径问题
     let batch_path = extract_dir.join("install_gs.bat");
@@ -601,15 +442,15 @@ fn extract_windows_installer(data: &[u8], extract_dir: &std::path::Path, target_
     );
     
     let mut batch_file = fs::File::create(&batch_path)
-        .map_err(|e| format!("创建批处理文件失败: {}", e))?;
+        .map_err(|e| AppError::with_details(ErrorCode::InstallFailed, tr!(Text::CannotCreateBatchFile, e)))?;
     batch_file.write_all(batch_content.as_bytes())
-        .map_err(|e| format!("写入批处理文件失败: {}", e))?;
+        .map_err(|e| AppError::with_details(ErrorCode::InstallFailed, tr!(Text::CannotWriteBatchFile, e)))?;
     
     // 执行批处理文件
     let output = Command::new("cmd")
-        .args(&["/C", batch_path.to_string_lossy().as_ref()])
+        .args(["/C", batch_path.to_string_lossy().as_ref()])
         .output()
-        .map_err(|e| format!("执行安装失败: {}", e))?;
+        .map_err(|e| AppError::with_details(ErrorCode::InstallFailed, tr!(Text::CannotRunInstaller, e)))?;
     
     // 输出安装日志以便调试
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -618,7 +459,7 @@ fn extract_windows_installer(data: &[u8], extract_dir: &std::path::Path, target_
     println!("安装错误: {}", stderr);
     
     if !output.status.success() {
-        return Err(format!("Ghostscript 安装失败，错误码: {}", output.status));
+        return Err(AppError::with_details(ErrorCode::InstallFailed, tr!(Text::InstallerExitCode, output.status)));
     }
     
     // 查找安装后的 gs.exe 文件
@@ -637,7 +478,7 @@ fn extract_windows_installer(data: &[u8], extract_dir: &std::path::Path, target_
         if path.exists() {
             println!("找到 Ghostscript 可执行文件: {}", path.to_string_lossy());
             fs::copy(path, target_path)
-                .map_err(|e| format!("复制 Ghostscript 可执行文件失败: {}", e))?;
+                .map_err(|e| AppError::with_details(ErrorCode::InstallFailed, tr!(Text::CannotCopyGhostscript, e)))?;
             
             // 清理临时文件
             let _ = fs::remove_file(&installer_path);
@@ -651,7 +492,7 @@ fn extract_windows_installer(data: &[u8], extract_dir: &std::path::Path, target_
     if let Some(found_path) = find_gs_executable_recursive(extract_dir) {
         println!("在非标准位置找到 Ghostscript: {}", found_path.to_string_lossy());
         fs::copy(&found_path, target_path)
-            .map_err(|e| format!("复制 Ghostscript 可执行文件失败: {}", e))?;
+            .map_err(|e| AppError::with_details(ErrorCode::InstallFailed, tr!(Text::CannotCopyGhostscript, e)))?;
         
         // 清理临时文件
         let _ = fs::remove_file(&installer_path);
@@ -663,7 +504,7 @@ fn extract_windows_installer(data: &[u8], extract_dir: &std::path::Path, target_
     // 如果找不到，尝试直接复制安装程序作为备用方案
     println!("未找到 Ghostscript 可执行文件，使用安装程序作为备用");
     fs::copy(&installer_path, target_path)
-        .map_err(|e| format!("复制安装程序失败: {}", e))?;
+        .map_err(|e| AppError::with_details(ErrorCode::InstallFailed, tr!(Text::CannotCopyInstaller, e)))?;
     
     // 清理临时文件
     let _ = fs::remove_file(&batch_path);
@@ -697,7 +538,7 @@ fn find_gs_executable_recursive(dir: &std::path::Path) -> Option<std::path::Path
     None
 }
 
-fn extract_tar_gz(data: &[u8], extract_dir: &std::path::Path, target_path: &std::path::Path) -> Result<(), String> {
+fn extract_tar_gz(data: &[u8], extract_dir: &std::path::Path, target_path: &std::path::Path) -> Result<(), AppError> {
     use std::io::Cursor;
     use flate2::read::GzDecoder;
     use tar::Archive;
@@ -708,7 +549,7 @@ fn extract_tar_gz(data: &[u8], extract_dir: &std::path::Path, target_path: &std:
     
     // 解压到临时目录
     archive.unpack(extract_dir)
-        .map_err(|e| format!("解压失败: {}", e))?;
+        .map_err(|e| AppError::with_details(ErrorCode::InstallFailed, tr!(Text::CannotExtractArchive, e)))?;
     
     // 查找 gs 可执行文件
     find_and_copy_gs_binary(extract_dir, target_path)?;
@@ -716,7 +557,7 @@ fn extract_tar_gz(data: &[u8], extract_dir: &std::path::Path, target_path: &std:
     Ok(())
 }
 
-fn find_and_copy_gs_binary(search_dir: &std::path::Path, target_path: &std::path::Path) -> Result<(), String> {
+fn find_and_copy_gs_binary(search_dir: &std::path::Path, target_path: &std::path::Path) -> Result<(), AppError> {
     use std::fs;
     
     // 递归查找 gs 可执行文件
@@ -742,17 +583,17 @@ fn find_and_copy_gs_binary(search_dir: &std::path::Path, target_path: &std::path
     
     if let Some(gs_path) = find_gs_recursive(search_dir) {
         fs::copy(&gs_path, target_path)
-            .map_err(|e| format!("复制 Ghostscript 可执行文件失败: {}", e))?;
+            .map_err(|e| AppError::with_details(ErrorCode::InstallFailed, tr!(Text::CannotCopyGhostscript, e)))?;
         Ok(())
     } else {
-        Err("在解压的文件中找不到 Ghostscript 可执行文件".to_string())
+        Err(AppError::with_details(ErrorCode::InstallFailed, tr!(Text::GhostscriptNotInArchive)))
     }
 }
 
 #[tauri::command]
-async fn check_ghostscript_status() -> Result<GhostscriptStatus, String> {
+async fn check_ghostscript_status() -> Result<GhostscriptStatus, AppError> {
     // 检查系统安装的 Ghostscript
-    let system_gs_available = is_ghostscript_available();
+    let system_gs_available = is_ghostscript_available().await;
     
     // 检查捆绑的 Ghostscript
     let bundled_gs_available = if let Ok(path) = get_bundled_ghostscript_path() {
@@ -769,38 +610,20 @@ async fn check_ghostscript_status() -> Result<GhostscriptStatus, String> {
 }
 
 #[tauri::command]
-async fn get_manual_install_instructions() -> Result<String, String> {
+async fn get_manual_install_instructions() -> Result<String, AppError> {
     let instructions = if cfg!(target_os = "windows") {
-        "Windows 用户安装说明：\n\
-         1. 访问 https://www.ghostscript.com/releases/gsdnld.html\n\
-         2. 下载适合您系统的版本（64位或32位）\n\
-         3. 运行安装程序并按照提示完成安装\n\
-         4. 重启应用以检测已安装的 Ghostscript"
+        Text::ManualInstallWindows
     } else if cfg!(target_os = "macos") {
-        "macOS 用户安装说明：\n\
-         方法1 - 使用 Homebrew（推荐）：\n\
-         1. 打开终端\n\
-         2. 运行命令：brew install ghostscript\n\
-         \n\
-         方法2 - 手动安装：\n\
-         1. 访问 https://www.ghostscript.com/releases/gsdnld.html\n\
-         2. 下载 macOS 版本\n\
-         3. 按照安装说明完成安装\n\
-         4. 重启应用以检测已安装的 Ghostscript"
+        Text::ManualInstallMacos
     } else {
-        "Linux 用户安装说明：\n\
-         Ubuntu/Debian：sudo apt install ghostscript\n\
-         Fedora/RHEL：sudo dnf install ghostscript\n\
-         Arch Linux：sudo pacman -S ghostscript\n\
-         \n\
-         安装完成后重启应用以检测已安装的 Ghostscript"
+        Text::ManualInstallLinux
     };
     
-    Ok(instructions.to_string())
+    Ok(tr!(instructions))
 }
 
 #[tauri::command]
-async fn uninstall_ghostscript() -> Result<bool, String> {
+async fn uninstall_ghostscript() -> Result<bool, AppError> {
     use std::fs;
     
     // 获取应用数据目录
@@ -822,13 +645,13 @@ async fn uninstall_ghostscript() -> Result<bool, String> {
         println!("尝试关闭 Ghostscript 进程...");
         // 尝试关闭可能正在运行的 Ghostscript 进程
         let _ = Command::new("taskkill")
-            .args(&["/F", "/IM", "gswin64c.exe"])
+            .args(["/F", "/IM", "gswin64c.exe"])
             .output();
         let _ = Command::new("taskkill")
-            .args(&["/F", "/IM", "gswin32c.exe"])
+            .args(["/F", "/IM", "gswin32c.exe"])
             .output();
         let _ = Command::new("taskkill")
-            .args(&["/F", "/IM", "gs.exe"])
+            .args(["/F", "/IM", "gs.exe"])
             .output();
     }
     
@@ -840,6 +663,7 @@ async fn uninstall_ghostscript() -> Result<bool, String> {
             let mut gs_state = GHOSTSCRIPT_STATE.lock().await;
             gs_state.is_installed = false;
             gs_state.executable_path = None;
+            refresh_ghostscript_command().await;
             
             Ok(true)
         },
@@ -852,7 +676,7 @@ async fn uninstall_ghostscript() -> Result<bool, String> {
                 use std::process::Command;
                 
                 let output = Command::new("cmd")
-                    .args(&["/C", "rmdir", "/S", "/Q", &gs_dir.to_string_lossy()])
+                    .args(["/C", "rmdir", "/S", "/Q", &gs_dir.to_string_lossy()])
                     .output();
                 
                 match output {
@@ -862,23 +686,27 @@ async fn uninstall_ghostscript() -> Result<bool, String> {
                         let mut gs_state = GHOSTSCRIPT_STATE.lock().await;
                         gs_state.is_installed = false;
                         gs_state.executable_path = None;
+                        refresh_ghostscript_command().await;
                         
                         Ok(true)
                     },
-                    Ok(_) => Err(format!("使用系统命令删除失败: {}", e)),
-                    Err(e2) => Err(format!("卸载 Ghostscript 失败: {} (系统命令错误: {})", e, e2)),
+                    Ok(_) => Err(AppError::with_details(ErrorCode::UninstallFailed, tr!(Text::SystemRemoveFailed, e))),
+                    Err(e2) => Err(AppError::with_details(
+                        ErrorCode::UninstallFailed,
+                        tr!(Text::SystemCommandError, e, e2),
+                    )),
                 }
             } else {
-                Err(format!("卸载 Ghostscript 失败: {}", e))
+                Err(AppError::with_details(ErrorCode::UninstallFailed, e))
             }
         },
     }
 }
 
 #[tauri::command]
-async fn download_ghostscript(app_handle: tauri::AppHandle) -> Result<GhostscriptStatus, String> {
+async fn download_ghostscript(app_handle: tauri::AppHandle) -> Result<GhostscriptStatus, AppError> {
     // 检查是否已经安装
-    if is_ghostscript_available() {
+    if is_ghostscript_available().await {
         return Ok(GhostscriptStatus {
             is_installed: true,
             is_downloading: false,
@@ -889,9 +717,15 @@ async fn download_ghostscript(app_handle: tauri::AppHandle) -> Result<Ghostscrip
     // 检查平台是否支持自动下载
     if !cfg!(target_os = "windows") {
         if cfg!(target_os = "macos") {
-            return Err("macOS 用户请使用 'brew install ghostscript' 安装 Ghostscript，或从官网下载安装包".to_string());
+            return Err(AppError::with_details(
+                ErrorCode::UnsupportedPlatform,
+                tr!(Text::MacosInstallHint),
+            ));
         } else {
-            return Err("Linux 用户请使用包管理器安装 Ghostscript，如: sudo apt install ghostscript".to_string());
+            return Err(AppError::with_details(
+                ErrorCode::UnsupportedPlatform,
+                tr!(Text::LinuxInstallHint),
+            ));
         }
     }
     
@@ -926,13 +760,15 @@ async fn download_ghostscript(app_handle: tauri::AppHandle) -> Result<Ghostscrip
                     gs_state.is_installed = true;
                     gs_state.download_progress = 100.0;
                     gs_state.executable_path = Some(gs_path);
+                    refresh_ghostscript_command().await;
                     
                     // 发送安装完成事件
                     let _ = app_handle_clone.emit("ghostscript-installed", true);
                 } else {
-                    // 发送安装失败事件
-                    let _ = app_handle_clone.emit("ghostscript-install-failed", 
-                        result.err().unwrap_or_else(|| "Unknown error".to_string()));
+                    // 发送安装失败事件，载荷与命令返回的错误相同
+                    if let Err(e) = result {
+                        let _ = app_handle_clone.emit("ghostscript-install-failed", e);
+                    }
                 }
             }
         }
@@ -956,6 +792,13 @@ pub fn run() {
             select_input_file, 
             select_output_path, 
             compress_pdf,
+            compress_batch,
+            cancel_compression,
+            analyze_pdf,
+            get_compression_profile,
+            list_compression_engines,
+            set_locale,
+            get_locale,
             check_ghostscript_status,
             download_ghostscript,
             get_manual_install_instructions,
@@ -0,0 +1,568 @@
+// PDF 体积分析
+//
+// 按类别统计文件中各部分占用的字节数：图像、字体、内容流、元数据、附件和结构开销，
+// 并列出每个图像和字体的详细信息以及最大的对象，帮助用户判断应该降采样图像、转换颜色、
+// 删除附件还是清理元数据。
+//
+// 对象大小按保存时的写法估算，流数据按文件中存储的（压缩后的）字节数计算；
+// 对象流中的对象按估算大小分摊对象流的实际大小。交叉引用表、文件头和无法归属的部分计入结构开销。
+// 字体文件、ICC 配置等没有类型标记的对象，归属到第一个引用它们的图像、字体、附件或元数据对象。
+
+use super::images::{effective_image_dpi, image_filters, mask_image_ids};
+use lopdf::xref::XrefEntry;
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+/// 默认列出的最大对象数量
+pub const DEFAULT_TOP_OBJECTS: usize = 10;
+
+/// 流对象中 `stream`、`endstream` 关键字和换行占用的字节数
+const STREAM_KEYWORDS: u64 = 19;
+
+/// 间接对象中 `obj`、`endobj` 关键字和换行占用的字节数，不含对象编号
+const OBJECT_KEYWORDS: u64 = 14;
+
+/// 遍历引用时不跟随的键，避免从资源对象回到页面树
+const BACK_REFERENCE_KEYS: [&[u8]; 2] = [b"Parent", b"P"];
+
+/// 占用空间的类别
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeCategory {
+    /// 图像及其软蒙版和 ICC 配置
+    Images,
+    /// 字体字典、字体描述符和嵌入的字体文件
+    Fonts,
+    /// 页面内容流、表单 XObject 和平铺图案
+    ContentStreams,
+    /// XMP 元数据流和文档信息字典
+    Metadata,
+    /// 嵌入的文件及其文件规范
+    Attachments,
+    /// 页面树、注释、书签、交叉引用表等其他部分
+    Structure,
+}
+
+impl SizeCategory {
+    const ALL: [SizeCategory; 6] = [
+        SizeCategory::Images,
+        SizeCategory::Fonts,
+        SizeCategory::ContentStreams,
+        SizeCategory::Metadata,
+        SizeCategory::Attachments,
+        SizeCategory::Structure,
+    ];
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CategorySize {
+    pub category: SizeCategory,
+    pub bytes: u64,
+    /// 该类别的对象数量，结构开销中不属于任何对象的部分不计数
+    pub objects: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImageInfo {
+    pub id: ObjectId,
+    pub width: i64,
+    pub height: i64,
+    pub bits_per_component: Option<i64>,
+    /// 色彩空间名称，数组形式的色彩空间取其族名，例如 ICCBased、Indexed
+    pub color_space: Option<String>,
+    pub filters: Vec<String>,
+    pub bytes: u64,
+    /// 图像在页面上的最低有效分辨率，没有直接绘制在页面上的图像（如软蒙版）为 None
+    pub effective_dpi: Option<f32>,
+    /// 是否为软蒙版、蒙版或模板图像
+    pub mask: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FontInfo {
+    pub id: ObjectId,
+    /// 字体的 BaseFont 名称，子集字体带有 `ABCDEF+` 前缀
+    pub name: String,
+    /// 字体类型，例如 Type1、TrueType、Type0、Type3
+    pub subtype: String,
+    pub embedded: bool,
+    pub subset: bool,
+    /// 字体字典、描述符、字体文件等相关对象的总大小
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ObjectSize {
+    pub id: ObjectId,
+    pub category: SizeCategory,
+    /// 对象的 /Subtype 或 /Type，两者都没有时为 None
+    pub kind: Option<String>,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SizeAnalysis {
+    pub file_size: u64,
+    pub version: String,
+    pub page_count: usize,
+    pub object_count: usize,
+    /// 各类别的大小，按字节数从大到小排列
+    pub categories: Vec<CategorySize>,
+    /// 所有图像，按字节数从大到小排列
+    pub images: Vec<ImageInfo>,
+    /// 所有字体（复合字体的后代字体计入父字体），按字节数从大到小排列
+    pub fonts: Vec<FontInfo>,
+    /// 最大的若干个对象
+    pub largest_objects: Vec<ObjectSize>,
+}
+
+/// 分析文档中各部分占用的空间，`file_size` 是文件的实际大小，`top` 是列出的最大对象数量
+pub fn analyze(document: &Document, file_size: u64, top: usize) -> SizeAnalysis {
+    let sizes = object_sizes(document, file_size);
+    let categories = classify(document, &sizes);
+    let category_of = |id: &ObjectId| categories.get(id).copied().unwrap_or(SizeCategory::Structure);
+
+    let mut totals: HashMap<SizeCategory, (u64, usize)> = HashMap::new();
+    for (id, &bytes) in &sizes {
+        let total = totals.entry(category_of(id)).or_default();
+        total.0 += bytes;
+        total.1 += 1;
+    }
+    let attributed: u64 = sizes.values().sum();
+    totals.entry(SizeCategory::Structure).or_default().0 += file_size.saturating_sub(attributed);
+
+    let mut category_sizes: Vec<CategorySize> = SizeCategory::ALL
+        .into_iter()
+        .map(|category| {
+            let (bytes, objects) = totals.get(&category).copied().unwrap_or_default();
+            CategorySize { category, bytes, objects }
+        })
+        .collect();
+    category_sizes.sort_by_key(|size| Reverse(size.bytes));
+
+    let mut largest_objects: Vec<ObjectSize> = sizes
+        .iter()
+        .map(|(&id, &bytes)| ObjectSize {
+            id,
+            category: category_of(&id),
+            kind: document.get_object(id).ok().and_then(object_kind),
+            bytes,
+        })
+        .collect();
+    largest_objects.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.id.cmp(&b.id)));
+    largest_objects.truncate(top);
+
+    SizeAnalysis {
+        file_size,
+        version: document.version.clone(),
+        page_count: document.get_pages().len(),
+        object_count: sizes.len(),
+        categories: category_sizes,
+        images: images(document, &sizes, &categories),
+        fonts: fonts(document, &sizes, &categories),
+        largest_objects,
+    }
+}
+
+/// 每个对象在文件中占用的字节数，对象流和交叉引用流本身不计入
+fn object_sizes(document: &Document, file_size: u64) -> HashMap<ObjectId, u64> {
+    let mut sizes: HashMap<ObjectId, u64> = document
+        .objects
+        .iter()
+        .filter(|(_, object)| !is_container(object))
+        .map(|(&id, object)| (id, indirect_object_size(id, object)))
+        .collect();
+
+    // 对象流中的对象没有单独的存储空间，按估算大小的比例分摊对象流的实际大小；
+    // 对象流中的对象和对象流本身的生成号都为 0
+    let mut members: HashMap<ObjectId, Vec<ObjectId>> = HashMap::new();
+    for (&number, entry) in &document.reference_table.entries {
+        if let XrefEntry::Compressed { container, .. } = *entry {
+            if sizes.contains_key(&(number, 0)) {
+                members.entry((container, 0)).or_default().push((number, 0));
+            }
+        }
+    }
+    if members.is_empty() {
+        return sizes;
+    }
+
+    // lopdf 加载时已经解压了对象流，对象流的实际大小取它到下一个对象的偏移量之差，
+    // 增量更新中被替换的旧对象也可能落在这个范围内，所以不超过解压后的大小
+    let mut offsets: Vec<u32> = document
+        .reference_table
+        .entries
+        .values()
+        .filter_map(|entry| match *entry {
+            XrefEntry::Normal { offset, .. } => Some(offset),
+            _ => None,
+        })
+        .collect();
+    offsets.sort_unstable();
+    for (container_id, ids) in members {
+        let (Ok(container), Some(&XrefEntry::Normal { offset, .. })) =
+            (document.get_object(container_id), document.reference_table.get(container_id.0))
+        else {
+            continue;
+        };
+        let next = offsets.iter().find(|&&next| next > offset).map_or(file_size, |&next| next as u64);
+        let stored = next.saturating_sub(offset as u64).min(indirect_object_size(container_id, container));
+        let estimated: u64 = ids.iter().filter_map(|id| sizes.get(id)).sum();
+        if estimated == 0 {
+            continue;
+        }
+        for id in ids {
+            if let Some(size) = sizes.get_mut(&id) {
+                *size = *size * stored / estimated;
+            }
+        }
+    }
+
+    sizes
+}
+
+/// 对象流和交叉引用流，它们的内容已经展开为其他对象
+fn is_container(object: &Object) -> bool {
+    object
+        .as_stream()
+        .is_ok_and(|stream| stream.dict.type_is(b"ObjStm") || stream.dict.type_is(b"XRef"))
+}
+
+fn indirect_object_size(id: ObjectId, object: &Object) -> u64 {
+    digits(id.0 as u64) + digits(id.1 as u64) + OBJECT_KEYWORDS + serialized_size(object)
+}
+
+/// 对象按 PDF 语法写出时的字节数
+fn serialized_size(object: &Object) -> u64 {
+    match object {
+        Object::Null => 4,
+        Object::Boolean(value) => if *value { 4 } else { 5 },
+        Object::Integer(value) => value.to_string().len() as u64,
+        Object::Real(value) => value.to_string().len() as u64,
+        Object::Name(name) => name.len() as u64 + 1,
+        Object::String(bytes, StringFormat::Literal) => bytes.len() as u64 + 2,
+        Object::String(bytes, StringFormat::Hexadecimal) => bytes.len() as u64 * 2 + 2,
+        Object::Array(array) => 2 + array.iter().map(|item| serialized_size(item) + 1).sum::<u64>(),
+        Object::Dictionary(dict) => dictionary_size(dict),
+        Object::Stream(stream) => dictionary_size(&stream.dict) + stream.content.len() as u64 + STREAM_KEYWORDS,
+        Object::Reference(id) => digits(id.0 as u64) + digits(id.1 as u64) + 3,
+    }
+}
+
+fn dictionary_size(dict: &Dictionary) -> u64 {
+    4 + dict
+        .iter()
+        .map(|(key, value)| key.len() as u64 + 2 + serialized_size(value))
+        .sum::<u64>()
+}
+
+fn digits(value: u64) -> u64 {
+    value.checked_ilog10().map_or(1, |log| log as u64 + 1)
+}
+
+/// 确定每个对象的类别，未出现在结果中的对象属于结构开销
+fn classify(document: &Document, sizes: &HashMap<ObjectId, u64>) -> HashMap<ObjectId, SizeCategory> {
+    let mut categories = HashMap::new();
+
+    for (&id, object) in &document.objects {
+        if let Some(category) = direct_category(object) {
+            categories.insert(id, category);
+        }
+    }
+    for page_id in document.page_iter() {
+        for content_id in document.get_page_contents(page_id) {
+            categories.insert(content_id, SizeCategory::ContentStreams);
+        }
+    }
+    if let Ok(info_id) = document.trailer.get(b"Info").and_then(Object::as_reference) {
+        categories.insert(info_id, SizeCategory::Metadata);
+    }
+
+    // 没有类型标记的对象归属到引用它们的对象，按顺序处理使共享的 ICC 配置等优先归入图像
+    for category in [SizeCategory::Images, SizeCategory::Fonts, SizeCategory::Attachments, SizeCategory::Metadata] {
+        let roots: Vec<ObjectId> = categories
+            .iter()
+            .filter(|(_, &root_category)| root_category == category)
+            .map(|(&id, _)| id)
+            .collect();
+        for root in roots {
+            let claimed = reachable(document, root, |id, object| {
+                sizes.contains_key(&id) && !categories.contains_key(&id) && !is_page_node(object)
+            });
+            for id in claimed {
+                categories.entry(id).or_insert(category);
+            }
+        }
+    }
+
+    categories
+}
+
+/// 可以仅凭对象本身确定的类别
+fn direct_category(object: &Object) -> Option<SizeCategory> {
+    match object {
+        Object::Stream(stream) => {
+            let dict = &stream.dict;
+            match dict.get(b"Subtype").and_then(Object::as_name) {
+                Ok(b"Image") => return Some(SizeCategory::Images),
+                Ok(b"Form") => return Some(SizeCategory::ContentStreams),
+                _ => {}
+            }
+            if dict.type_is(b"Metadata") {
+                Some(SizeCategory::Metadata)
+            } else if dict.type_is(b"EmbeddedFile") {
+                Some(SizeCategory::Attachments)
+            } else if dict.has(b"PatternType") {
+                Some(SizeCategory::ContentStreams)
+            } else {
+                None
+            }
+        }
+        Object::Dictionary(dict) => {
+            if dict.type_is(b"Font") || dict.type_is(b"FontDescriptor") {
+                Some(SizeCategory::Fonts)
+            } else if dict.type_is(b"Filespec") && dict.has(b"EF") {
+                Some(SizeCategory::Attachments)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_page_node(object: &Object) -> bool {
+    object
+        .as_dict()
+        .is_ok_and(|dict| dict.type_is(b"Page") || dict.type_is(b"Pages"))
+}
+
+/// 从 `root` 出发经过 `admit` 接受的对象能到达的所有对象，包括 `root` 本身
+fn reachable(document: &Document, root: ObjectId, admit: impl Fn(ObjectId, &Object) -> bool) -> Vec<ObjectId> {
+    let mut visited = HashSet::from([root]);
+    let mut stack = vec![root];
+    let mut found = Vec::new();
+
+    while let Some(id) = stack.pop() {
+        found.push(id);
+        let Ok(object) = document.get_object(id) else {
+            continue;
+        };
+        let mut references = Vec::new();
+        collect_references(object, &mut references);
+        for reference in references {
+            let Ok(target) = document.get_object(reference) else {
+                continue;
+            };
+            if admit(reference, target) && visited.insert(reference) {
+                stack.push(reference);
+            }
+        }
+    }
+
+    found
+}
+
+fn collect_references(object: &Object, references: &mut Vec<ObjectId>) {
+    let dict = match object {
+        Object::Reference(id) => return references.push(*id),
+        Object::Array(array) => {
+            for item in array {
+                collect_references(item, references);
+            }
+            return;
+        }
+        Object::Dictionary(dict) => dict,
+        Object::Stream(stream) => &stream.dict,
+        _ => return,
+    };
+    for (key, value) in dict.iter() {
+        if !BACK_REFERENCE_KEYS.contains(&key.as_slice()) {
+            collect_references(value, references);
+        }
+    }
+}
+
+fn object_kind(object: &Object) -> Option<String> {
+    let dict = match object {
+        Object::Dictionary(dict) => dict,
+        Object::Stream(stream) => &stream.dict,
+        _ => return None,
+    };
+    dict.get(b"Subtype")
+        .or_else(|_| dict.get(b"Type"))
+        .and_then(Object::as_name)
+        .ok()
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+}
+
+fn images(
+    document: &Document,
+    sizes: &HashMap<ObjectId, u64>,
+    categories: &HashMap<ObjectId, SizeCategory>,
+) -> Vec<ImageInfo> {
+    let dpi = effective_image_dpi(document);
+    let masks = mask_image_ids(document);
+
+    let mut images: Vec<ImageInfo> = categories
+        .iter()
+        .filter(|(_, &category)| category == SizeCategory::Images)
+        .filter_map(|(&id, _)| {
+            let stream = document.get_object(id).and_then(Object::as_stream).ok()?;
+            let dict = &stream.dict;
+            if dict.get(b"Subtype").and_then(Object::as_name).ok()? != b"Image" {
+                return None;
+            }
+            let image_mask = dict.get(b"ImageMask").and_then(Object::as_bool).unwrap_or(false);
+            Some(ImageInfo {
+                id,
+                width: dict.get(b"Width").and_then(Object::as_i64).unwrap_or(0),
+                height: dict.get(b"Height").and_then(Object::as_i64).unwrap_or(0),
+                bits_per_component: dict.get(b"BitsPerComponent").and_then(Object::as_i64).ok(),
+                color_space: dict.get(b"ColorSpace").ok().and_then(|color_space| color_space_name(document, color_space)),
+                filters: image_filters(stream),
+                bytes: sizes.get(&id).copied().unwrap_or_default(),
+                effective_dpi: dpi.get(&id).map(|dpi| dpi.round()),
+                mask: image_mask || masks.contains(&id),
+            })
+        })
+        .collect();
+    images.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.id.cmp(&b.id)));
+    images
+}
+
+fn color_space_name(document: &Document, color_space: &Object) -> Option<String> {
+    let (_, color_space) = document.dereference(color_space).ok()?;
+    let name = match color_space {
+        Object::Name(name) => name,
+        Object::Array(array) => array.first()?.as_name().ok()?,
+        _ => return None,
+    };
+    Some(String::from_utf8_lossy(name).into_owned())
+}
+
+fn fonts(
+    document: &Document,
+    sizes: &HashMap<ObjectId, u64>,
+    categories: &HashMap<ObjectId, SizeCategory>,
+) -> Vec<FontInfo> {
+    // 复合字体的后代字体作为父字体的一部分统计
+    let descendants: HashSet<ObjectId> = document
+        .objects
+        .values()
+        .filter_map(|object| object.as_dict().ok())
+        .filter_map(|dict| dict.get(b"DescendantFonts").ok())
+        .filter_map(|fonts| document.dereference(fonts).ok()?.1.as_array().ok())
+        .flatten()
+        .filter_map(|font| font.as_reference().ok())
+        .collect();
+    let top_level = |id: ObjectId| {
+        document
+            .get_dictionary(id)
+            .is_ok_and(|dict| dict.type_is(b"Font") && !descendants.contains(&id))
+    };
+
+    let mut fonts: Vec<FontInfo> = document
+        .objects
+        .keys()
+        .copied()
+        .filter(|&id| top_level(id))
+        .filter_map(|id| {
+            let dict = document.get_dictionary(id).ok()?;
+            let name = dict
+                .get(b"BaseFont")
+                .and_then(Object::as_name)
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .unwrap_or_default();
+            let subtype = dict
+                .get(b"Subtype")
+                .and_then(Object::as_name)
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .unwrap_or_default();
+            let related = reachable(document, id, |related, _| {
+                categories.get(&related) == Some(&SizeCategory::Fonts) && !top_level(related)
+            });
+            Some(FontInfo {
+                id,
+                embedded: subtype == "Type3" || font_descriptor(document, dict).is_some_and(has_font_file),
+                subset: is_subset_name(&name),
+                name,
+                subtype,
+                bytes: related.iter().filter_map(|id| sizes.get(id)).sum(),
+            })
+        })
+        .collect();
+    fonts.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.id.cmp(&b.id)));
+    fonts
+}
+
+/// 字体的描述符，复合字体取第一个后代字体的描述符
+///
+/// Type0 字体只有一层后代字体 (PDF 1.7 第 9.7.1 节)，后代字体本身不再查找后代，
+/// 避免损坏的文件中 `/DescendantFonts` 引用自身时无限递归。
+fn font_descriptor<'a>(document: &'a Document, font: &'a Dictionary) -> Option<&'a Dictionary> {
+    if font.has(b"FontDescriptor") {
+        return own_font_descriptor(document, font);
+    }
+    let descendants = document.dereference(font.get(b"DescendantFonts").ok()?).ok()?.1.as_array().ok()?;
+    let descendant = document.dereference(descendants.first()?).ok()?.1.as_dict().ok()?;
+    own_font_descriptor(document, descendant)
+}
+
+fn own_font_descriptor<'a>(document: &'a Document, font: &'a Dictionary) -> Option<&'a Dictionary> {
+    document
+        .dereference(font.get(b"FontDescriptor").ok()?)
+        .ok()?
+        .1
+        .as_dict()
+        .ok()
+}
+
+fn has_font_file(descriptor: &Dictionary) -> bool {
+    [b"FontFile".as_slice(), b"FontFile2", b"FontFile3"]
+        .into_iter()
+        .any(|key| descriptor.has(key))
+}
+
+/// 子集字体的名称以六个大写字母和加号开头，例如 `ABCDEF+Arial`
+fn is_subset_name(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    bytes.len() > 7 && bytes[6] == b'+' && bytes[..6].iter().all(u8::is_ascii_uppercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    #[test]
+    fn composite_font_uses_descendant_descriptor() {
+        let mut document = Document::with_version("1.5");
+        let descriptor_id = document.add_object(dictionary! { "Type" => "FontDescriptor", "FontName" => "Demo" });
+        let descendant_id = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "CIDFontType2",
+            "FontDescriptor" => descriptor_id,
+        });
+        let font =
+            dictionary! { "Type" => "Font", "Subtype" => "Type0", "DescendantFonts" => vec![descendant_id.into()] };
+
+        let descriptor = font_descriptor(&document, &font).unwrap();
+        assert_eq!(descriptor, document.get_dictionary(descriptor_id).unwrap());
+    }
+
+    #[test]
+    fn self_referencing_descendant_fonts_terminate() {
+        let mut document = Document::with_version("1.5");
+        let font_id = document.new_object_id();
+        document.objects.insert(
+            font_id,
+            Object::Dictionary(dictionary! { "Subtype" => "Type0", "DescendantFonts" => vec![font_id.into()] }),
+        );
+
+        let font = document.get_dictionary(font_id).unwrap();
+        assert!(font_descriptor(&document, font).is_none());
+    }
+}
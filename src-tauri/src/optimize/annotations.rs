@@ -0,0 +1,429 @@
+// 注释和表单处理
+//
+// 表单域可以保留、平展或删除：平展时把控件注释 (Widget) 的正常外观作为表单 XObject
+// 绘制到页面内容的最上层，然后删除控件注释和文档的 AcroForm 字典，结果与打印效果一致。
+// 没有外观流的可见控件无法平展，保留为可填写的表单域。
+// 批注类注释（文字批注、高亮、图章、手绘等及其弹出窗口）可以单独删除，链接等其他注释始终保留。
+
+use super::pages::inherit_attributes;
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// 批注类注释的子类型
+pub const COMMENT_SUBTYPES: &[&str] = &[
+    "Text",
+    "FreeText",
+    "Line",
+    "Square",
+    "Circle",
+    "Polygon",
+    "PolyLine",
+    "Highlight",
+    "Underline",
+    "Squiggly",
+    "StrikeOut",
+    "Caret",
+    "Stamp",
+    "Ink",
+    "Popup",
+    "FileAttachment",
+    "Sound",
+    "Redact",
+];
+
+/// PDF 2.0 定义的其他注释子类型，与 `COMMENT_SUBTYPES` 一起构成全部注释子类型
+pub const NON_COMMENT_SUBTYPES: &[&str] = &[
+    "Link",
+    "Movie",
+    "Screen",
+    "Widget",
+    "PrinterMark",
+    "TrapNet",
+    "Watermark",
+    "3D",
+    "Projection",
+    "RichMedia",
+];
+
+/// 注释标志中的 Hidden 和 NoView，带有这些标志的控件不显示，平展时直接删除
+const HIDDEN_FLAGS: i64 = (1 << 1) | (1 << 5);
+
+/// 表单域的处理方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FormMode {
+    /// 保留可填写的表单
+    Keep,
+    /// 把表单域的当前外观绘制到页面内容中
+    Flatten,
+    /// 删除表单域，页面上不再显示
+    Remove,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AnnotationReport {
+    /// 平展到页面内容中的表单控件数量
+    pub fields_flattened: usize,
+    /// 删除的表单控件数量，包括平展时隐藏的控件
+    pub widgets_removed: usize,
+    /// 删除的批注数量
+    pub comments_removed: usize,
+    /// 平展时没有外观流而保留为表单域的可见控件数量，不计入 `total()`
+    pub fields_not_flattened: usize,
+}
+
+impl AnnotationReport {
+    pub fn total(&self) -> usize {
+        self.fields_flattened + self.widgets_removed + self.comments_removed
+    }
+}
+
+/// 一个需要绘制到页面上的外观流及其变换矩阵
+struct FlattenedAppearance {
+    stream_id: ObjectId,
+    matrix: [f32; 6],
+}
+
+/// 按选项平展或删除表单域，并删除批注
+pub fn process_annotations(document: &mut Document, form_mode: FormMode, remove_comments: bool) -> AnnotationReport {
+    let mut report = AnnotationReport::default();
+    if form_mode == FormMode::Keep && !remove_comments {
+        return report;
+    }
+
+    let mut removed_widgets = BTreeSet::new();
+    let page_ids: Vec<ObjectId> = document.get_pages().into_values().collect();
+    for page_id in page_ids {
+        let Some(annotations) = page_annotations(document, page_id) else {
+            continue;
+        };
+
+        let mut kept = Vec::with_capacity(annotations.len());
+        let mut flattened = Vec::new();
+        for annotation in annotations {
+            let dict = match &annotation {
+                Object::Reference(id) => document.get_dictionary(*id).ok(),
+                Object::Dictionary(dict) => Some(dict),
+                _ => None,
+            };
+            let subtype = dict
+                .and_then(|dict| dict.get(b"Subtype").and_then(Object::as_name).ok())
+                .unwrap_or_default();
+
+            if subtype == b"Widget" && form_mode != FormMode::Keep {
+                let flatten = form_mode == FormMode::Flatten && !dict.is_some_and(is_hidden);
+                match dict.filter(|_| flatten).and_then(|dict| flattened_appearance(document, dict)) {
+                    Some(appearance) => {
+                        flattened.push(appearance);
+                        report.fields_flattened += 1;
+                    }
+                    // 没有可绘制的外观，删除会让填写的内容消失，保留控件
+                    None if flatten => {
+                        report.fields_not_flattened += 1;
+                        kept.push(annotation);
+                        continue;
+                    }
+                    None => report.widgets_removed += 1,
+                }
+                removed_widgets.extend(annotation.as_reference().ok());
+            } else if remove_comments && COMMENT_SUBTYPES.iter().any(|name| name.as_bytes() == subtype) {
+                report.comments_removed += 1;
+            } else {
+                kept.push(annotation);
+            }
+        }
+
+        set_annotations(document, page_id, kept);
+        if !flattened.is_empty() {
+            draw_appearances(document, page_id, &flattened);
+        }
+    }
+
+    // 控件注释已经全部平展或删除时表单字典不再有意义，否则只去掉已经不在页面上的表单域
+    if form_mode != FormMode::Keep && report.fields_not_flattened == 0 {
+        if let Ok(catalog) = document.catalog_mut() {
+            catalog.remove(b"AcroForm");
+        }
+    } else if !removed_widgets.is_empty() {
+        remove_form_fields(document, &removed_widgets);
+    }
+
+    report
+}
+
+/// 从 AcroForm 的 /Fields 中去掉指定的表单域，只处理控件与表单域合并在同一对象中的顶层表单域
+fn remove_form_fields(document: &mut Document, removed: &BTreeSet<ObjectId>) {
+    let Ok(form) = document.catalog().and_then(|catalog| catalog.get(b"AcroForm")).cloned() else {
+        return;
+    };
+    let form = match form {
+        Object::Reference(id) => document.get_object_mut(id).and_then(Object::as_dict_mut),
+        _ => document.catalog_mut().and_then(|catalog| catalog.get_mut(b"AcroForm")).and_then(Object::as_dict_mut),
+    };
+    if let Ok(Object::Array(fields)) = form.and_then(|form| form.get_mut(b"Fields")) {
+        fields.retain(|field| !field.as_reference().is_ok_and(|id| removed.contains(&id)));
+    }
+}
+
+/// 注释带有 Hidden 或 NoView 标志
+fn is_hidden(annotation: &Dictionary) -> bool {
+    annotation.get(b"F").and_then(Object::as_i64).unwrap_or(0) & HIDDEN_FLAGS != 0
+}
+
+/// 页面的注释数组，没有注释时为 None
+pub(crate) fn page_annotations(document: &Document, page_id: ObjectId) -> Option<Vec<Object>> {
+    let annotations = document.get_dictionary(page_id).ok()?.get(b"Annots").ok()?;
+    resolve(document, annotations)
+        .as_array()
+        .ok()
+        .filter(|array| !array.is_empty())
+        .cloned()
+}
+
+/// 替换页面的注释数组，数组可能被多个页面共享，所以总是写成页面自己的数组
+pub(crate) fn set_annotations(document: &mut Document, page_id: ObjectId, annotations: Vec<Object>) {
+    if let Ok(page) = document.get_object_mut(page_id).and_then(Object::as_dict_mut) {
+        if annotations.is_empty() {
+            page.remove(b"Annots");
+        } else {
+            page.set("Annots", annotations);
+        }
+    }
+}
+
+/// 控件注释当前状态的正常外观和把外观放到注释矩形中的矩阵 (PDF 1.7 第 12.5.5 节)
+fn flattened_appearance(document: &Document, annotation: &Dictionary) -> Option<FlattenedAppearance> {
+    let normal = annotation
+        .get(b"AP")
+        .ok()
+        .and_then(|appearances| resolve(document, appearances).as_dict().ok())?
+        .get(b"N")
+        .ok()?;
+
+    // 复选框等有多个状态的控件，正常外观是以状态名为键的字典
+    let stream_id = match resolve(document, normal) {
+        Object::Stream(_) => normal.as_reference().ok()?,
+        Object::Dictionary(states) => {
+            let state = annotation.get(b"AS").and_then(Object::as_name).ok()?;
+            states.get(state).and_then(Object::as_reference).ok()?
+        }
+        _ => return None,
+    };
+
+    let stream = document.get_object(stream_id).and_then(Object::as_stream).ok()?;
+    let rect = number_array::<4>(document, annotation.get(b"Rect").ok()?)?;
+    let bbox = number_array::<4>(document, stream.dict.get(b"BBox").ok()?)?;
+    let form_matrix = stream
+        .dict
+        .get(b"Matrix")
+        .ok()
+        .and_then(|matrix| number_array::<6>(document, matrix))
+        .unwrap_or([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    // 外观的边界框经过自身矩阵变换后，再缩放平移到注释矩形
+    let corners = [(bbox[0], bbox[1]), (bbox[2], bbox[1]), (bbox[0], bbox[3]), (bbox[2], bbox[3])]
+        .map(|(x, y)| transform(&form_matrix, x, y));
+    let (min_x, max_x) = min_max(corners.iter().map(|point| point.0));
+    let (min_y, max_y) = min_max(corners.iter().map(|point| point.1));
+    let (rect_x0, rect_x1) = min_max([rect[0], rect[2]].into_iter());
+    let (rect_y0, rect_y1) = min_max([rect[1], rect[3]].into_iter());
+    if max_x - min_x <= f32::EPSILON || max_y - min_y <= f32::EPSILON {
+        return None;
+    }
+
+    let scale_x = (rect_x1 - rect_x0) / (max_x - min_x);
+    let scale_y = (rect_y1 - rect_y0) / (max_y - min_y);
+    Some(FlattenedAppearance {
+        stream_id,
+        matrix: [scale_x, 0.0, 0.0, scale_y, rect_x0 - min_x * scale_x, rect_y0 - min_y * scale_y],
+    })
+}
+
+/// 把外观流作为表单 XObject 加入页面资源，并在页面内容之后绘制
+fn draw_appearances(document: &mut Document, page_id: ObjectId, appearances: &[FlattenedAppearance]) {
+    // 资源可能继承自上级节点或被多个页面共享，先复制为页面自己的字典
+    inherit_attributes(document, page_id);
+    let mut resources = document
+        .get_dictionary(page_id)
+        .ok()
+        .and_then(|page| page.get(b"Resources").ok())
+        .and_then(|resources| resolve(document, resources).as_dict().ok())
+        .cloned()
+        .unwrap_or_default();
+    let mut xobjects = resources
+        .get(b"XObject")
+        .ok()
+        .and_then(|xobjects| resolve(document, xobjects).as_dict().ok())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut operations = Vec::new();
+    for appearance in appearances {
+        // 外观流本身就是表单 XObject，部分生成器省略了类型
+        if let Ok(stream) = document.get_object_mut(appearance.stream_id).and_then(Object::as_stream_mut) {
+            stream.dict.set("Type", Object::Name(b"XObject".to_vec()));
+            stream.dict.set("Subtype", Object::Name(b"Form".to_vec()));
+        }
+
+        let name = (0..)
+            .map(|index| format!("Fm{}_{}", appearance.stream_id.0, index))
+            .find(|name| !xobjects.has(name.as_bytes()))
+            .unwrap_or_default();
+        xobjects.set(name.clone(), appearance.stream_id);
+        operations.extend([
+            Operation::new("q", vec![]),
+            Operation::new("cm", appearance.matrix.iter().map(|&value| Object::Real(value)).collect()),
+            Operation::new("Do", vec![Object::Name(name.into_bytes())]),
+            Operation::new("Q", vec![]),
+        ]);
+    }
+    resources.set("XObject", xobjects);
+
+    // 原内容可能没有恢复图形状态，用 q/Q 包起来，并补上原内容中未配对的 Q
+    let existing: Vec<Object> = match document.get_dictionary(page_id).ok().and_then(|page| page.get(b"Contents").ok()) {
+        Some(Object::Reference(id)) => vec![Object::Reference(*id)],
+        Some(Object::Array(array)) => array.clone(),
+        _ => Vec::new(),
+    };
+    if !existing.is_empty() {
+        let depth = unbalanced_saves(document, page_id) + 1;
+        operations.splice(0..0, (0..depth).map(|_| Operation::new("Q", vec![])));
+    }
+    let Ok(encoded) = (Content { operations }).encode() else {
+        return;
+    };
+
+    let mut contents = Vec::with_capacity(existing.len() + 2);
+    if !existing.is_empty() {
+        contents.push(Object::Reference(document.add_object(Stream::new(Dictionary::new(), b"q".to_vec()))));
+        contents.extend(existing);
+    }
+    contents.push(Object::Reference(document.add_object(Stream::new(Dictionary::new(), encoded))));
+
+    if let Ok(page) = document.get_object_mut(page_id).and_then(Object::as_dict_mut) {
+        page.set("Resources", resources);
+        page.set("Contents", contents);
+    }
+}
+
+/// 页面内容结束时仍未恢复的图形状态数量
+fn unbalanced_saves(document: &Document, page_id: ObjectId) -> usize {
+    let Ok(content) = document.get_and_decode_page_content(page_id) else {
+        return 0;
+    };
+    content.operations.iter().fold(0, |depth: usize, operation| match operation.operator.as_str() {
+        "q" => depth + 1,
+        "Q" => depth.saturating_sub(1),
+        _ => depth,
+    })
+}
+
+/// 解析间接引用，引用的对象不存在时返回引用本身
+pub(crate) fn resolve<'a>(document: &'a Document, object: &'a Object) -> &'a Object {
+    match object {
+        Object::Reference(id) => document.get_object(*id).unwrap_or(object),
+        _ => object,
+    }
+}
+
+fn number_array<const N: usize>(document: &Document, object: &Object) -> Option<[f32; N]> {
+    let array = resolve(document, object).as_array().ok()?;
+    let mut numbers = [0.0; N];
+    for (number, value) in numbers.iter_mut().zip(array) {
+        *number = resolve(document, value).as_float().ok()?;
+    }
+    (array.len() >= N).then_some(numbers)
+}
+
+fn transform(matrix: &[f32; 6], x: f32, y: f32) -> (f32, f32) {
+    (
+        matrix[0] * x + matrix[2] * y + matrix[4],
+        matrix[1] * x + matrix[3] * y + matrix[5],
+    )
+}
+
+fn min_max(values: impl Iterator<Item = f32>) -> (f32, f32) {
+    values.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| (min.min(value), max.max(value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    /// 一个页面上两个文本域，第一个带有外观流，第二个没有
+    fn document_with_fields() -> (Document, ObjectId, [ObjectId; 2]) {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let page_id = document.new_object_id();
+        let appearance = document.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => vec![0.into(), 0.into(), 100.into(), 20.into()],
+            },
+            b"BT /F1 10 Tf (Filled) Tj ET".to_vec(),
+        ));
+        let widgets = [Some(appearance), None].map(|appearance| {
+            let mut widget = dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Widget",
+                "FT" => "Tx",
+                "P" => page_id,
+                "Rect" => vec![10.into(), 10.into(), 110.into(), 30.into()],
+            };
+            if let Some(appearance) = appearance {
+                widget.set("AP", dictionary! { "N" => appearance });
+            }
+            document.add_object(widget)
+        });
+        let widget_refs: Vec<Object> = widgets.iter().map(|&id| Object::Reference(id)).collect();
+        let content = document.add_object(Stream::new(dictionary! {}, b"0 0 10 10 re f".to_vec()));
+        document.objects.insert(page_id, Object::Dictionary(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content,
+            "Annots" => widget_refs.clone(),
+        }));
+        document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![Object::Reference(page_id)],
+            "Count" => 1,
+        }));
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "AcroForm" => dictionary! { "Fields" => widget_refs },
+        });
+        document.trailer.set("Root", catalog_id);
+        (document, page_id, widgets)
+    }
+
+    #[test]
+    fn flattening_keeps_fields_without_appearance() {
+        let (mut document, page_id, widgets) = document_with_fields();
+
+        let report = process_annotations(&mut document, FormMode::Flatten, false);
+
+        assert_eq!(report.fields_flattened, 1);
+        assert_eq!(report.fields_not_flattened, 1);
+        assert_eq!(report.total(), 1);
+        let kept = vec![Object::Reference(widgets[1])];
+        assert_eq!(document.get_dictionary(page_id).unwrap().get(b"Annots").unwrap().as_array().unwrap(), &kept);
+        let form = document.catalog().unwrap().get(b"AcroForm").unwrap().as_dict().unwrap();
+        assert_eq!(form.get(b"Fields").unwrap().as_array().unwrap(), &kept);
+    }
+
+    #[test]
+    fn removing_forms_drops_every_widget_and_the_form() {
+        let (mut document, page_id, _) = document_with_fields();
+
+        let report = process_annotations(&mut document, FormMode::Remove, false);
+
+        assert_eq!(report.widgets_removed, 2);
+        assert!(!document.get_dictionary(page_id).unwrap().has(b"Annots"));
+        assert!(!document.catalog().unwrap().has(b"AcroForm"));
+    }
+}
@@ -0,0 +1,167 @@
+// 元数据和冗余数据清理
+//
+// 这些数据对页面显示没有影响，但在设计软件导出的 PDF 中往往占据可观的空间。
+
+use lopdf::{Dictionary, Document, Object};
+use serde::{Deserialize, Serialize};
+
+/// Adobe Illustrator 等软件写入的私有数据键前缀
+const PRIVATE_DATA_PREFIXES: &[&[u8]] = &[b"AIPrivateData", b"AIPDFPrivateData", b"AIMetaData"];
+
+/// 各类清理项的开关，未指定的字段默认开启
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct CleanupOptions {
+    /// XMP 元数据流 (/Metadata)
+    pub xmp_metadata: bool,
+    /// 应用程序私有的页面片段数据 (/PieceInfo)
+    pub piece_info: bool,
+    /// 页面缩略图 (/Thumb)
+    pub thumbnails: bool,
+    /// 文档信息字典 (/Info) 中的字段
+    pub info_fields: bool,
+    /// 打开文档时执行的 JavaScript (/OpenAction)
+    pub open_action_javascript: bool,
+    /// Illustrator 等软件的私有数据
+    pub private_data: bool,
+}
+
+impl CleanupOptions {
+    pub fn all() -> Self {
+        Self {
+            xmp_metadata: true,
+            piece_info: true,
+            thumbnails: true,
+            info_fields: true,
+            open_action_javascript: true,
+            private_data: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        Self {
+            xmp_metadata: false,
+            piece_info: false,
+            thumbnails: false,
+            info_fields: false,
+            open_action_javascript: false,
+            private_data: false,
+        }
+    }
+}
+
+impl Default for CleanupOptions {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// 每类被移除的条目数量
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct CleanupReport {
+    pub xmp_metadata: usize,
+    pub piece_info: usize,
+    pub thumbnails: usize,
+    pub info_fields: usize,
+    pub open_action_javascript: usize,
+    pub private_data: usize,
+}
+
+impl CleanupReport {
+    pub fn total(&self) -> usize {
+        self.xmp_metadata
+            + self.piece_info
+            + self.thumbnails
+            + self.info_fields
+            + self.open_action_javascript
+            + self.private_data
+    }
+}
+
+/// 按选项移除元数据和冗余数据，被移除的对象由之后的 `prune_objects` 回收
+pub fn strip_metadata(document: &mut Document, options: &CleanupOptions) -> CleanupReport {
+    let mut report = CleanupReport::default();
+
+    if options.open_action_javascript {
+        report.open_action_javascript = remove_javascript_open_action(document);
+    }
+
+    if options.info_fields {
+        report.info_fields = remove_info_fields(document);
+    }
+
+    for object in document.objects.values_mut() {
+        let dict = match object {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => &mut stream.dict,
+            _ => continue,
+        };
+
+        if options.xmp_metadata && dict.remove(b"Metadata").is_some() {
+            report.xmp_metadata += 1;
+        }
+
+        if options.piece_info && dict.remove(b"PieceInfo").is_some() {
+            report.piece_info += 1;
+        }
+
+        if options.thumbnails && dict.type_is(b"Page") && dict.remove(b"Thumb").is_some() {
+            report.thumbnails += 1;
+        }
+
+        if options.private_data {
+            report.private_data += remove_private_data(dict);
+        }
+    }
+
+    report
+}
+
+fn remove_private_data(dict: &mut Dictionary) -> usize {
+    let keys: Vec<Vec<u8>> = dict
+        .iter()
+        .map(|(key, _)| key)
+        .filter(|key| PRIVATE_DATA_PREFIXES.iter().any(|prefix| key.starts_with(prefix)))
+        .cloned()
+        .collect();
+
+    for key in &keys {
+        dict.remove(key);
+    }
+
+    keys.len()
+}
+
+fn remove_info_fields(document: &mut Document) -> usize {
+    let Some(info) = document.trailer.remove(b"Info") else {
+        return 0;
+    };
+
+    document
+        .dereference(&info)
+        .and_then(|(_, info)| info.as_dict())
+        .map(Dictionary::len)
+        .unwrap_or(0)
+}
+
+/// 只移除 JavaScript 类型的打开动作，跳转到指定页面等普通动作保持不变
+fn remove_javascript_open_action(document: &mut Document) -> usize {
+    let is_javascript = document
+        .catalog()
+        .and_then(|catalog| catalog.get(b"OpenAction"))
+        .and_then(|action| document.dereference(action))
+        .and_then(|(_, action)| action.as_dict())
+        .and_then(|action| action.get(b"S"))
+        .and_then(Object::as_name)
+        .map(|action_type| action_type == b"JavaScript")
+        .unwrap_or(false);
+
+    if !is_javascript {
+        return 0;
+    }
+
+    match document.catalog_mut() {
+        Ok(catalog) => catalog.remove(b"OpenAction").map_or(0, |_| 1),
+        Err(_) => 0,
+    }
+}
@@ -0,0 +1,572 @@
+// 颜色模式转换
+//
+// 把页面和表单内容流中的设备颜色操作符以及 8 位图像转换为 RGB、灰度或黑白。
+// 只处理设备色彩空间和等效的 Cal/ICCBased 色彩空间，索引色、专色和图案保持原样并计入报告；
+// 黑白模式下矢量内容只转换为灰度，避免浅色文字和线条消失，只有图像被二值化。
+
+use super::images::{color_space_components, decode_samples, image_filters, mask_image_ids};
+use image::codecs::jpeg::JpegEncoder;
+use image::{ExtendedColorType, ImageFormat};
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// 输出文件的颜色模式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// 保持原有颜色
+    Leave,
+    /// 转换为 RGB，CMYK 按不带特性文件的简单公式换算
+    Rgb,
+    /// 转换为灰度
+    Gray,
+    /// 图像二值化为 1 位黑白，矢量内容转换为灰度，适合扫描件
+    Bilevel,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColorReport {
+    /// 转换了颜色的图像数量
+    pub images_converted: usize,
+    /// 色彩空间或编码不受支持而保持原样的图像数量
+    pub images_skipped: usize,
+    /// 改写的颜色操作符数量
+    pub operators_converted: usize,
+    /// 色彩空间无法转换（专色、索引色、图案等）而保持原样的颜色设置操作符数量
+    pub operators_skipped: usize,
+}
+
+impl ColorReport {
+    pub fn total(&self) -> usize {
+        self.images_converted + self.operators_converted
+    }
+}
+
+impl ColorMode {
+    /// 选项中使用的名称
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ColorMode::Leave => "leave",
+            ColorMode::Rgb => "rgb",
+            ColorMode::Gray => "gray",
+            ColorMode::Bilevel => "bilevel",
+        }
+    }
+
+    /// 转换后的颜色分量数
+    fn target_components(self, components: u8) -> u8 {
+        match self {
+            ColorMode::Leave => components,
+            ColorMode::Rgb => components.min(3),
+            ColorMode::Gray | ColorMode::Bilevel => 1,
+        }
+    }
+}
+
+/// 转换文档中的图像和内容流颜色
+///
+/// 必须在图像降采样之前、`Document::compress()` 之前调用，转换后的图像由降采样步骤继续处理。
+pub fn convert_colors(document: &mut Document, mode: ColorMode, jpeg_quality: u8) -> ColorReport {
+    if mode == ColorMode::Leave {
+        return ColorReport::default();
+    }
+
+    let mut report = convert_images(document, mode, jpeg_quality);
+    convert_content_streams(document, mode, &mut report);
+    report
+}
+
+/// 只转换图像颜色，用于 Ghostscript 已经转换了矢量颜色的输出
+///
+/// 原来是 JPEG 的图像重新编码为 JPEG，其余图像使用 Flate 压缩。
+pub fn convert_images(document: &mut Document, mode: ColorMode, jpeg_quality: u8) -> ColorReport {
+    let mut report = ColorReport::default();
+    if mode == ColorMode::Leave {
+        return report;
+    }
+
+    let masks = mask_image_ids(document);
+    let image_ids: Vec<ObjectId> = document
+        .objects
+        .iter()
+        .filter(|(id, _)| !masks.contains(id))
+        .filter_map(|(id, object)| {
+            let dict = &object.as_stream().ok()?.dict;
+            let is_image = dict.get(b"Subtype").and_then(Object::as_name).ok()? == b"Image";
+            // 图像蒙版没有自己的颜色，使用内容流中的填充色
+            let is_stencil = dict.get(b"ImageMask").and_then(Object::as_bool).unwrap_or(false);
+            (is_image && !is_stencil).then_some(*id)
+        })
+        .collect();
+
+    for id in image_ids {
+        let Some(Object::Stream(stream)) = document.objects.get(&id) else {
+            continue;
+        };
+        let bits = stream.dict.get(b"BitsPerComponent").and_then(Object::as_i64).unwrap_or(8);
+        let components = stream
+            .dict
+            .get(b"ColorSpace")
+            .ok()
+            .and_then(|color_space| color_space_components(document, color_space));
+
+        // 1 位灰度图像已经是黑白的
+        if components == Some(1) && bits == 1 {
+            continue;
+        }
+        if components.is_some_and(|components| mode.target_components(components) == components)
+            && mode != ColorMode::Bilevel
+        {
+            continue;
+        }
+
+        let converted = components
+            .filter(|_| bits == 8 && !stream.dict.has(b"Decode"))
+            .and_then(|components| convert_image(stream, components, mode, jpeg_quality));
+        let Some(converted) = converted else {
+            report.images_skipped += 1;
+            continue;
+        };
+
+        if let Some(Object::Stream(stream)) = document.objects.get_mut(&id) {
+            stream.dict.set("ColorSpace", Object::Name(converted.color_space.to_vec()));
+            stream.dict.set("BitsPerComponent", converted.bits as i64);
+            stream.dict.set("Filter", converted.filter);
+            stream.dict.remove(b"DecodeParms");
+            stream.set_content(converted.data);
+            stream.allows_compression = false;
+            report.images_converted += 1;
+        }
+    }
+
+    report
+}
+
+struct ConvertedImage {
+    color_space: &'static [u8],
+    bits: u8,
+    filter: &'static str,
+    data: Vec<u8>,
+}
+
+fn convert_image(stream: &Stream, components: u8, mode: ColorMode, jpeg_quality: u8) -> Option<ConvertedImage> {
+    let width = stream.dict.get(b"Width").and_then(Object::as_i64).ok()? as u32;
+    let height = stream.dict.get(b"Height").and_then(Object::as_i64).ok()? as u32;
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let is_jpeg = image_filters(stream).as_slice() == ["DCTDecode"];
+    let samples = if is_jpeg {
+        // CMYK 的 JPEG 常带有 Adobe 反相标记，解码结果不可靠，保持原样
+        let image = image::load_from_memory_with_format(&stream.content, ImageFormat::Jpeg).ok()?;
+        match components {
+            1 => image.to_luma8().into_raw(),
+            3 => image.to_rgb8().into_raw(),
+            _ => return None,
+        }
+    } else {
+        decode_samples(stream, width, height, components)?
+    };
+
+    let target = mode.target_components(components);
+    let pixels = samples.chunks_exact(components as usize);
+    let samples: Vec<u8> = match (components, target) {
+        (4, 3) => pixels.flat_map(|pixel| cmyk_to_rgb(&to_unit(pixel)).map(to_byte)).collect(),
+        (_, 1) => pixels.map(|pixel| to_byte(luminance(&to_unit(pixel), components))).collect(),
+        _ => samples,
+    };
+
+    if mode == ColorMode::Bilevel {
+        return Some(ConvertedImage {
+            color_space: b"DeviceGray",
+            bits: 1,
+            filter: "FlateDecode",
+            data: deflate(&binarize(&samples, width as usize))?,
+        });
+    }
+
+    let color_space: &'static [u8] = if target == 1 { b"DeviceGray" } else { b"DeviceRGB" };
+    if is_jpeg {
+        let mut data = Vec::new();
+        let color_type = if target == 1 { ExtendedColorType::L8 } else { ExtendedColorType::Rgb8 };
+        JpegEncoder::new_with_quality(&mut data, jpeg_quality.clamp(1, 100))
+            .encode(&samples, width, height, color_type)
+            .ok()?;
+        return Some(ConvertedImage { color_space, bits: 8, filter: "DCTDecode", data });
+    }
+
+    Some(ConvertedImage {
+        color_space,
+        bits: 8,
+        filter: "FlateDecode",
+        data: deflate(&samples)?,
+    })
+}
+
+/// 用 Otsu 方法选择阈值，把灰度图像转换为按行打包的 1 位数据（1 为白色）
+fn binarize(gray: &[u8], width: usize) -> Vec<u8> {
+    let mut histogram = [0u64; 256];
+    for &value in gray {
+        histogram[value as usize] += 1;
+    }
+
+    let total = gray.len() as f64;
+    let sum: f64 = histogram.iter().enumerate().map(|(value, &count)| value as f64 * count as f64).sum();
+    let (mut weight, mut weighted_sum) = (0.0, 0.0);
+    let (mut threshold, mut best_variance) = (128, 0.0);
+    for (value, &count) in histogram.iter().enumerate() {
+        weight += count as f64;
+        weighted_sum += value as f64 * count as f64;
+        if weight == 0.0 || weight == total {
+            continue;
+        }
+        let dark_mean = weighted_sum / weight;
+        let light_mean = (sum - weighted_sum) / (total - weight);
+        let variance = weight * (total - weight) * (dark_mean - light_mean).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            threshold = value;
+        }
+    }
+
+    let row_bytes = width.div_ceil(8);
+    let mut packed = Vec::with_capacity(row_bytes * gray.len() / width.max(1));
+    for row in gray.chunks(width) {
+        let start = packed.len();
+        packed.resize(start + row_bytes, 0);
+        for (x, &value) in row.iter().enumerate() {
+            if value as usize > threshold {
+                packed[start + x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+    }
+    packed
+}
+
+fn deflate(data: &[u8]) -> Option<Vec<u8>> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(data).ok()?;
+    encoder.finish().ok()
+}
+
+/// 资源中命名色彩空间的原始分量数，无法按设备色彩空间转换的为 None
+type ColorSpaces = BTreeMap<Vec<u8>, Option<u8>>;
+
+/// 转换所有页面内容流和表单 XObject（包括注释外观）中的颜色操作符
+///
+/// 一个页面的多个内容流相当于拼接在一起执行，颜色状态在流之间延续。
+fn convert_content_streams(document: &mut Document, mode: ColorMode, report: &mut ColorReport) {
+    let mut groups: Vec<(Vec<ObjectId>, ColorSpaces)> = document
+        .page_iter()
+        .map(|page_id| (document.get_page_contents(page_id), page_color_spaces(document, page_id)))
+        .collect();
+    groups.extend(document.objects.iter().filter_map(|(id, object)| {
+        let dict = &object.as_stream().ok()?.dict;
+        if dict.get(b"Subtype").and_then(Object::as_name).ok()? != b"Form" {
+            return None;
+        }
+        let color_spaces = dict
+            .get(b"Resources")
+            .and_then(|resources| document.dereference(resources))
+            .and_then(|(_, resources)| resources.as_dict())
+            .map_or_else(|_| ColorSpaces::new(), |resources| color_spaces(document, resources));
+        Some((vec![*id], color_spaces))
+    }));
+
+    // 被多个页面共享的内容流只按第一次出现时的颜色状态改写，之后只用原内容推进颜色状态
+    let mut originals: BTreeMap<ObjectId, Option<Vec<Operation>>> = BTreeMap::new();
+    for (ids, color_spaces) in groups {
+        let mut state = ContentState::default();
+        for id in ids {
+            let (first, operations) = match originals.entry(id) {
+                Entry::Occupied(entry) => (false, entry.get().clone()),
+                Entry::Vacant(entry) => (true, entry.insert(decode_operations(document, id)).clone()),
+            };
+            let Some(mut operations) = operations else {
+                continue;
+            };
+
+            let mut counts = ColorReport::default();
+            convert_operations(&mut operations, mode, &color_spaces, &mut state, &mut counts);
+            if !first {
+                continue;
+            }
+            report.operators_skipped += counts.operators_skipped;
+            if counts.operators_converted == 0 {
+                continue;
+            }
+            let encoded = Content { operations }.encode();
+            if let (Ok(encoded), Some(Object::Stream(stream))) = (encoded, document.objects.get_mut(&id)) {
+                stream.set_plain_content(encoded);
+                report.operators_converted += counts.operators_converted;
+            }
+        }
+    }
+}
+
+fn decode_operations(document: &Document, id: ObjectId) -> Option<Vec<Operation>> {
+    let stream = document.get_object(id).and_then(Object::as_stream).ok()?;
+    let content = Content::decode(&stream.decompressed_content().unwrap_or_else(|_| stream.content.clone())).ok()?;
+    Some(content.operations)
+}
+
+/// 页面可以使用的命名色彩空间，页面自己的资源优先于从页面树继承的资源
+fn page_color_spaces(document: &Document, page_id: ObjectId) -> ColorSpaces {
+    let Ok((resources, inherited)) = document.get_page_resources(page_id) else {
+        return ColorSpaces::new();
+    };
+
+    let mut merged = ColorSpaces::new();
+    let inherited = inherited.into_iter().filter_map(|id| document.get_dictionary(id).ok());
+    for resources in resources.into_iter().chain(inherited) {
+        for (name, components) in color_spaces(document, resources) {
+            merged.entry(name).or_insert(components);
+        }
+    }
+    merged
+}
+
+fn color_spaces(document: &Document, resources: &Dictionary) -> ColorSpaces {
+    resources
+        .get(b"ColorSpace")
+        .and_then(|color_spaces| document.dereference(color_spaces))
+        .and_then(|(_, color_spaces)| color_spaces.as_dict())
+        .map(|color_spaces| {
+            color_spaces
+                .iter()
+                .map(|(name, color_space)| (name.clone(), color_space_components(document, color_space)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 当前填充和描边色彩空间的原始分量数，None 表示无法转换的色彩空间
+#[derive(Clone, Copy, Default)]
+struct ColorState {
+    fill: Option<u8>,
+    stroke: Option<u8>,
+}
+
+/// 内容流执行过程中的颜色状态和 q/Q 保存的状态
+#[derive(Default)]
+struct ContentState {
+    color: ColorState,
+    stack: Vec<ColorState>,
+}
+
+fn convert_operations(
+    operations: &mut [Operation],
+    mode: ColorMode,
+    color_spaces: &ColorSpaces,
+    state: &mut ContentState,
+    report: &mut ColorReport,
+) {
+    for operation in operations {
+        match operation.operator.as_str() {
+            "q" => state.stack.push(state.color),
+            "Q" => state.color = state.stack.pop().unwrap_or_default(),
+            _ => {}
+        }
+
+        let stroke = operation.operator.chars().all(|c| c.is_ascii_uppercase());
+        let current = if stroke { &mut state.color.stroke } else { &mut state.color.fill };
+        match operation.operator.as_str() {
+            "g" | "G" | "rg" | "RG" | "k" | "K" => {
+                let components = operation.operands.len() as u8;
+                *current = Some(components);
+                if let Some(values) = convert_operands(&operation.operands, components, mode) {
+                    operation.operator = color_operator(values.len(), stroke).to_string();
+                    operation.operands = values;
+                    report.operators_converted += 1;
+                }
+            }
+            "cs" | "CS" => {
+                let Some(name) = operation.operands.first().and_then(|name| name.as_name().ok()) else {
+                    continue;
+                };
+                let components = device_components(name).or_else(|| color_spaces.get(name).copied().flatten());
+                *current = components;
+                match components {
+                    Some(components) if mode.target_components(components) != components => {
+                        let target = mode.target_components(components);
+                        operation.operands = vec![Object::Name(device_name(target).to_vec())];
+                        report.operators_converted += 1;
+                    }
+                    Some(_) => {}
+                    None => report.operators_skipped += 1,
+                }
+            }
+            "sc" | "SC" | "scn" | "SCN" => {
+                let Some(components) = *current else {
+                    continue;
+                };
+                if let Some(values) = convert_operands(&operation.operands, components, mode) {
+                    operation.operands = values;
+                    report.operators_converted += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 需要转换时返回新的操作数，操作数不全是数字（例如图案名称）时保持原样
+fn convert_operands(operands: &[Object], components: u8, mode: ColorMode) -> Option<Vec<Object>> {
+    let target = mode.target_components(components);
+    if target == components || operands.len() != components as usize {
+        return None;
+    }
+
+    let values = operands
+        .iter()
+        .map(|operand| operand.as_float().ok())
+        .collect::<Option<Vec<f32>>>()?;
+    let converted = match target {
+        1 => vec![luminance(&values, components)],
+        _ => cmyk_to_rgb(&values).to_vec(),
+    };
+    Some(converted.into_iter().map(|value| Object::Real((value * 1000.0).round() / 1000.0)).collect())
+}
+
+fn color_operator(components: usize, stroke: bool) -> &'static str {
+    match (components, stroke) {
+        (1, false) => "g",
+        (1, true) => "G",
+        (3, false) => "rg",
+        (3, true) => "RG",
+        (_, false) => "k",
+        (_, true) => "K",
+    }
+}
+
+fn device_components(name: &[u8]) -> Option<u8> {
+    match name {
+        b"DeviceGray" => Some(1),
+        b"DeviceRGB" => Some(3),
+        b"DeviceCMYK" => Some(4),
+        _ => None,
+    }
+}
+
+fn device_name(components: u8) -> &'static [u8] {
+    match components {
+        1 => b"DeviceGray",
+        3 => b"DeviceRGB",
+        _ => b"DeviceCMYK",
+    }
+}
+
+/// 0 到 1 之间的颜色值转换为亮度，按 ITU-R BT.601 加权
+fn luminance(values: &[f32], components: u8) -> f32 {
+    match components {
+        1 => values[0],
+        3 => 0.299 * values[0] + 0.587 * values[1] + 0.114 * values[2],
+        _ => luminance(&cmyk_to_rgb(values), 3),
+    }
+}
+
+fn cmyk_to_rgb(values: &[f32]) -> [f32; 3] {
+    let black = 1.0 - values[3].clamp(0.0, 1.0);
+    [0, 1, 2].map(|index| (1.0 - values[index].clamp(0.0, 1.0)) * black)
+}
+
+/// 8 位采样转换为 0 到 1 之间的颜色值，最多 4 个分量
+fn to_unit(pixel: &[u8]) -> [f32; 4] {
+    let mut values = [0.0; 4];
+    for (value, &sample) in values.iter_mut().zip(pixel) {
+        *value = sample as f32 / 255.0;
+    }
+    values
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    /// 一个页面，内容流依次为 `contents`，页面资源为 `resources`
+    fn document_with_page(contents: &[&[u8]], resources: Dictionary) -> (Document, Vec<ObjectId>) {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let content_ids: Vec<ObjectId> = contents
+            .iter()
+            .map(|content| document.add_object(Stream::new(dictionary! {}, content.to_vec())))
+            .collect();
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_ids.iter().map(|&id| Object::Reference(id)).collect::<Vec<_>>(),
+            "Resources" => resources,
+        });
+        document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![Object::Reference(page_id)],
+            "Count" => 1,
+        }));
+        let catalog_id = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        document.trailer.set("Root", catalog_id);
+        (document, content_ids)
+    }
+
+    fn operations(document: &Document, id: ObjectId) -> Vec<Operation> {
+        decode_operations(document, id).unwrap()
+    }
+
+    fn values(operation: &Operation) -> Vec<f32> {
+        operation.operands.iter().map(|operand| operand.as_float().unwrap()).collect()
+    }
+
+    #[test]
+    fn color_space_carries_across_page_streams() {
+        let (mut document, ids) = document_with_page(&[b"/DeviceCMYK cs", b"0 0 0 1 sc 0 0 10 10 re f"], dictionary! {});
+
+        let report = convert_colors(&mut document, ColorMode::Gray, 75);
+
+        assert_eq!(report.operators_converted, 2);
+        let second = operations(&document, ids[1]);
+        assert_eq!(second[0].operator, "sc");
+        assert_eq!(values(&second[0]), [0.0]);
+    }
+
+    #[test]
+    fn named_color_spaces_are_converted_or_reported() {
+        let mut profile = Stream::new(dictionary! { "N" => 4 }, Vec::new());
+        profile.allows_compression = false;
+        let (mut document, ids) = document_with_page(&[b"/CS0 cs 0 0 0 1 sc /CS1 CS 1 SC"], dictionary! {});
+        let profile_id = document.add_object(profile);
+        let page_id = document.page_iter().next().unwrap();
+        let page = document.get_dictionary_mut(page_id).unwrap();
+        page.set(
+            "Resources",
+            dictionary! {
+                "ColorSpace" => dictionary! {
+                    "CS0" => vec![Object::Name(b"ICCBased".to_vec()), Object::Reference(profile_id)],
+                    "CS1" => vec![
+                        Object::Name(b"Separation".to_vec()),
+                        Object::Name(b"Spot".to_vec()),
+                        Object::Name(b"DeviceCMYK".to_vec()),
+                        Object::Null,
+                    ],
+                },
+            },
+        );
+
+        let report = convert_colors(&mut document, ColorMode::Gray, 75);
+
+        assert_eq!(report.operators_converted, 2);
+        assert_eq!(report.operators_skipped, 1);
+        let converted = operations(&document, ids[0]);
+        assert_eq!(converted[0].operands, [Object::Name(b"DeviceGray".to_vec())]);
+        assert_eq!(values(&converted[1]), [0.0]);
+        assert_eq!(values(&converted[3]), [1.0]);
+    }
+}
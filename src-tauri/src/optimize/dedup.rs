@@ -0,0 +1,329 @@
+// 重复对象合并
+//
+// 合并生成的报告经常把同一个 Logo 或字体嵌入几十次。这里对流内容和字典计算哈希，
+// 把完全相同的对象合并为一个并改写所有引用。由于父对象只有在子对象合并之后才会变得相同
+// （例如字体描述符引用各自的字体文件），所以会重复执行直到没有新的重复对象。
+
+use super::object_streams::write_object;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// 最多迭代的轮数，每一轮可以让嵌套更深一层的对象变得相同
+const MAX_ROUNDS: usize = 8;
+
+/// 这些对象即使内容相同也代表不同的实体，不能合并
+///
+/// 可选内容组 (图层) 也按对象身份区分：名称相同的两个图层合并后，`/OCProperties` 中的
+/// `/OFF`、`/Order` 等只能指向同一个图层，另一个图层的可见性就会改变。
+const UNIQUE_TYPES: &[&[u8]] = &[
+    b"Catalog",
+    b"Pages",
+    b"Page",
+    b"Annot",
+    b"StructElem",
+    b"OBJR",
+    b"MCR",
+    b"OCG",
+    b"OCMD",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct DedupReport {
+    /// 被合并掉的重复对象数量
+    pub objects_merged: usize,
+    /// 重复对象序列化后的总大小
+    pub bytes_saved: u64,
+}
+
+/// 合并重复对象并改写引用，之后需要调用 `prune_objects` 移除无用对象
+pub fn deduplicate_objects(document: &mut Document) -> DedupReport {
+    let mut report = DedupReport::default();
+
+    // 加密文档中相同的明文会得到不同的密文，合并没有意义
+    if document.trailer.has(b"Encrypt") {
+        return report;
+    }
+
+    for _ in 0..MAX_ROUNDS {
+        let replacements = find_duplicates(document, &mut report);
+        if replacements.is_empty() {
+            break;
+        }
+
+        for object in document.objects.values_mut() {
+            replace_references(object, &replacements);
+        }
+        for (_, value) in document.trailer.iter_mut() {
+            replace_references(value, &replacements);
+        }
+        for duplicate in replacements.keys() {
+            document.objects.remove(duplicate);
+        }
+    }
+
+    report
+}
+
+/// 返回 重复对象 -> 保留对象 的映射
+fn find_duplicates(document: &Document, report: &mut DedupReport) -> HashMap<ObjectId, ObjectId> {
+    let mut buckets: HashMap<u64, Vec<ObjectId>> = HashMap::new();
+    let mut replacements = HashMap::new();
+
+    for (&id, object) in &document.objects {
+        if !is_mergeable(object) {
+            continue;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        hash_object(object, &mut hasher);
+        let candidates = buckets.entry(hasher.finish()).or_default();
+
+        // 哈希相同时再比较完整内容，避免哈希碰撞
+        match candidates
+            .iter()
+            .find(|candidate| document.objects.get(candidate).is_some_and(|other| same_content(other, object)))
+        {
+            Some(&canonical) => {
+                replacements.insert(id, canonical);
+                report.objects_merged += 1;
+                report.bytes_saved += serialized_size(object);
+            }
+            None => candidates.push(id),
+        }
+    }
+
+    replacements
+}
+
+fn is_mergeable(object: &Object) -> bool {
+    let dict = match object {
+        Object::Dictionary(dict) => dict,
+        Object::Stream(stream) => &stream.dict,
+        _ => return false,
+    };
+
+    // 表单字段、大纲项等通过 /Parent 构成树结构，各自都是独立的节点
+    if dict.has(b"Parent") {
+        return false;
+    }
+
+    !matches!(dict.get(b"Type").and_then(Object::as_name), Ok(name) if UNIQUE_TYPES.contains(&name))
+}
+
+/// 流对象只比较字典和内容，忽略其在原文件中的位置等状态
+fn same_content(a: &Object, b: &Object) -> bool {
+    match (a, b) {
+        (Object::Stream(a), Object::Stream(b)) => a.dict == b.dict && a.content == b.content,
+        _ => a == b,
+    }
+}
+
+fn serialized_size(object: &Object) -> u64 {
+    let mut buffer = Vec::new();
+    match write_object(&mut buffer, object) {
+        Ok(()) => buffer.len() as u64,
+        Err(_) => 0,
+    }
+}
+
+fn replace_references(object: &mut Object, replacements: &HashMap<ObjectId, ObjectId>) {
+    match object {
+        Object::Reference(id) => {
+            if let Some(canonical) = replacements.get(id) {
+                *id = *canonical;
+            }
+        }
+        Object::Array(array) => {
+            for item in array {
+                replace_references(item, replacements);
+            }
+        }
+        Object::Dictionary(dict) => replace_dictionary_references(dict, replacements),
+        Object::Stream(stream) => replace_dictionary_references(&mut stream.dict, replacements),
+        _ => {}
+    }
+}
+
+fn replace_dictionary_references(dict: &mut Dictionary, replacements: &HashMap<ObjectId, ObjectId>) {
+    for (_, value) in dict.iter_mut() {
+        replace_references(value, replacements);
+    }
+}
+
+/// 字典按键排序后再计算哈希，使键顺序不同的相同字典得到相同的哈希
+fn hash_object<H: Hasher>(object: &Object, hasher: &mut H) {
+    std::mem::discriminant(object).hash(hasher);
+    match object {
+        Object::Null => {}
+        Object::Boolean(value) => value.hash(hasher),
+        Object::Integer(value) => value.hash(hasher),
+        Object::Real(value) => value.to_bits().hash(hasher),
+        Object::Name(name) => name.hash(hasher),
+        Object::String(text, _) => text.hash(hasher),
+        Object::Array(array) => {
+            array.len().hash(hasher);
+            for item in array {
+                hash_object(item, hasher);
+            }
+        }
+        Object::Dictionary(dict) => hash_dictionary(dict, hasher),
+        Object::Stream(stream) => {
+            hash_dictionary(&stream.dict, hasher);
+            stream.content.hash(hasher);
+        }
+        Object::Reference(id) => id.hash(hasher),
+    }
+}
+
+fn hash_dictionary<H: Hasher>(dict: &Dictionary, hasher: &mut H) {
+    let mut entries: Vec<_> = dict.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries.len().hash(hasher);
+    for (key, value) in entries {
+        key.hash(hasher);
+        hash_object(value, hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream};
+
+    /// 两个内容相同的页面，各自引用相同的内容流和字体，字体描述符引用相同的字体文件；
+    /// 另有两个相同的大纲项通过 /Parent 挂在大纲下
+    fn document_with_duplicates() -> (Document, [ObjectId; 2], [ObjectId; 2]) {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let outlines_id = document.new_object_id();
+
+        let mut pages = [(0, 0); 2];
+        for page in &mut pages {
+            let font_file = document.add_object(Stream::new(dictionary! {}, b"font program".to_vec()));
+            let descriptor = document.add_object(dictionary! {
+                "Type" => "FontDescriptor",
+                "FontName" => "ABCDEF+Demo",
+                "FontFile2" => font_file,
+            });
+            let font = document.add_object(dictionary! {
+                "Type" => "Font",
+                "Subtype" => "TrueType",
+                "BaseFont" => "ABCDEF+Demo",
+                "FontDescriptor" => descriptor,
+            });
+            let content = document.add_object(Stream::new(dictionary! {}, b"BT /F1 12 Tf (Same) Tj ET".to_vec()));
+            *page = document.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content,
+                "Resources" => dictionary! { "Font" => dictionary! { "F1" => font } },
+            });
+        }
+        document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => pages.iter().map(|&id| Object::Reference(id)).collect::<Vec<_>>(),
+            "Count" => 2,
+        }));
+
+        let items = [0, 1].map(|_| {
+            document.add_object(dictionary! { "Title" => Object::string_literal("Chapter"), "Parent" => outlines_id })
+        });
+        document.objects.insert(outlines_id, Object::Dictionary(dictionary! {
+            "Type" => "Outlines",
+            "First" => items[0],
+            "Last" => items[1],
+            "Count" => 2,
+        }));
+        let catalog = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "Outlines" => outlines_id,
+        });
+        document.trailer.set("Root", catalog);
+        (document, pages, items)
+    }
+
+    fn page_reference(document: &Document, page_id: ObjectId, key: &[u8]) -> ObjectId {
+        document.get_dictionary(page_id).unwrap().get(key).unwrap().as_reference().unwrap()
+    }
+
+    #[test]
+    fn merges_nested_duplicates() {
+        let (mut document, pages, _) = document_with_duplicates();
+        let report = deduplicate_objects(&mut document);
+
+        // 字体文件、描述符、字体和内容流各合并一个，页面本身不合并
+        assert_eq!(report.objects_merged, 4);
+        assert!(report.bytes_saved > 0);
+        assert_eq!(page_reference(&document, pages[0], b"Contents"), page_reference(&document, pages[1], b"Contents"));
+        let fonts: Vec<&Object> = pages
+            .iter()
+            .map(|&page| {
+                let page = document.get_dictionary(page).unwrap();
+                page.get_deref(b"Resources", &document)
+                    .and_then(Object::as_dict)
+                    .and_then(|resources| resources.get(b"Font"))
+                    .and_then(Object::as_dict)
+                    .and_then(|fonts| fonts.get(b"F1"))
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(fonts[0], fonts[1]);
+    }
+
+    #[test]
+    fn keeps_page_tree_and_parent_linked_nodes() {
+        let (mut document, pages, items) = document_with_duplicates();
+        deduplicate_objects(&mut document);
+
+        let remaining: Vec<ObjectId> = document.get_pages().into_values().collect();
+        assert_eq!(remaining, pages);
+        for id in pages.into_iter().chain(items) {
+            assert!(document.objects.contains_key(&id), "{:?} was merged", id);
+        }
+        let outlines = document.catalog().unwrap().get(b"Outlines").unwrap().as_reference().unwrap();
+        let outlines = document.get_dictionary(outlines).unwrap();
+        assert_eq!(outlines.get(b"First").unwrap().as_reference().unwrap(), items[0]);
+        assert_eq!(outlines.get(b"Last").unwrap().as_reference().unwrap(), items[1]);
+    }
+
+    #[test]
+    fn skips_encrypted_documents() {
+        let (mut document, _, _) = document_with_duplicates();
+        document.trailer.set("Encrypt", dictionary! {});
+        assert_eq!(deduplicate_objects(&mut document).objects_merged, 0);
+    }
+
+    #[test]
+    fn keeps_identical_layers_apart() {
+        let (mut document, _, _) = document_with_duplicates();
+        let layers = [0, 1].map(|_| {
+            document.add_object(dictionary! {
+                "Type" => "OCG",
+                "Name" => Object::string_literal("Notes"),
+                "Intent" => "View",
+            })
+        });
+        let layer_refs: Vec<Object> = layers.iter().map(|&id| Object::Reference(id)).collect();
+        let catalog_id = document.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        document.get_dictionary_mut(catalog_id).unwrap().set(
+            "OCProperties",
+            dictionary! {
+                "OCGs" => layer_refs.clone(),
+                "D" => dictionary! { "Order" => layer_refs, "OFF" => vec![Object::Reference(layers[1])] },
+            },
+        );
+
+        deduplicate_objects(&mut document);
+
+        for id in layers {
+            assert!(document.objects.contains_key(&id), "{:?} was merged", id);
+        }
+        let properties = document.catalog().unwrap().get(b"OCProperties").and_then(Object::as_dict).unwrap();
+        let off = properties.get(b"D").and_then(Object::as_dict).unwrap().get(b"OFF").unwrap();
+        assert_eq!(off, &Object::Array(vec![Object::Reference(layers[1])]));
+    }
+}
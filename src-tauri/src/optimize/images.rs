@@ -0,0 +1,509 @@
+// 图像降采样与 JPEG 重新编码
+//
+// 通过解析页面内容流中的变换矩阵计算每个图像的实际显示分辨率，
+// 超过目标分辨率的图像会被缩小，然后以指定质量重新编码为 JPEG。
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage, ImageFormat, RgbImage};
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::ops::ControlFlow;
+
+/// 未能从页面内容中找到图像位置时，假定图像最多铺满一张 A4 纸的长边
+const FALLBACK_PAGE_INCHES: f32 = 11.69;
+
+/// Form XObject 的最大嵌套深度，防止循环引用
+const MAX_FORM_DEPTH: usize = 8;
+
+/// 图像处理参数
+#[derive(Debug, Clone, Copy)]
+pub struct ImageSettings {
+    /// 目标分辨率 (DPI)
+    pub target_dpi: f32,
+    /// 实际分辨率超过 `target_dpi * downsample_threshold` 时才降采样
+    pub downsample_threshold: f32,
+    /// JPEG 编码质量 (1-100)
+    pub jpeg_quality: u8,
+    /// 是否将无损压缩的图像也重新编码为 JPEG
+    pub reencode_lossless: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImageStats {
+    /// 被替换的图像数量
+    pub images_processed: usize,
+    /// 其中被降采样的图像数量
+    pub images_downsampled: usize,
+    pub bytes_saved: u64,
+}
+
+/// 对文档中的所有图像执行降采样和重新编码
+///
+/// 必须在 `Document::compress()` 之前调用，只有在新数据更小时才会替换原图像。
+pub fn optimize_images(document: &mut Document, settings: &ImageSettings) -> ImageStats {
+    let (stats, _) = optimize_images_with_progress(document, settings, &mut |_, _| ControlFlow::Continue(()));
+    stats
+}
+
+/// 与 `optimize_images` 相同，处理每个图像对象前和全部完成后调用 `progress(已处理数量, 总数)`
+///
+/// `progress` 返回 `ControlFlow::Break` 时立即停止，已替换的图像保持替换后的状态。
+pub fn optimize_images_with_progress(
+    document: &mut Document,
+    settings: &ImageSettings,
+    progress: &mut dyn FnMut(usize, usize) -> ControlFlow<()>,
+) -> (ImageStats, ControlFlow<()>) {
+    let mut stats = ImageStats::default();
+    let effective_dpi = effective_image_dpi(document);
+    let masks = mask_image_ids(document);
+
+    let candidates: Vec<(ObjectId, u8)> = document
+        .objects
+        .iter()
+        .filter(|(id, _)| !masks.contains(id))
+        .filter_map(|(id, object)| {
+            let stream = object.as_stream().ok()?;
+            let components = reencodable_components(document, &stream.dict)?;
+            Some((*id, components))
+        })
+        .collect();
+
+    let total = candidates.len();
+    for (index, (object_id, components)) in candidates.into_iter().enumerate() {
+        if progress(index, total).is_break() {
+            return (stats, ControlFlow::Break(()));
+        }
+
+        let Some(Object::Stream(stream)) = document.objects.get(&object_id) else {
+            continue;
+        };
+
+        let Some(replacement) = reencode_image(stream, components, effective_dpi.get(&object_id).copied(), settings) else {
+            continue;
+        };
+
+        let original_len = stream.content.len() as u64;
+        let new_len = replacement.data.len() as u64;
+        if new_len >= original_len {
+            continue;
+        }
+
+        if let Some(Object::Stream(stream)) = document.objects.get_mut(&object_id) {
+            stream.dict.set("Width", replacement.width as i64);
+            stream.dict.set("Height", replacement.height as i64);
+            stream.dict.set("BitsPerComponent", 8);
+            stream.dict.set("Filter", "DCTDecode");
+            stream.dict.remove(b"DecodeParms");
+            stream.set_content(replacement.data);
+            // JPEG 数据不应再被 Flate 压缩
+            stream.allows_compression = false;
+
+            stats.images_processed += 1;
+            if replacement.downsampled {
+                stats.images_downsampled += 1;
+            }
+            stats.bytes_saved += original_len - new_len;
+        }
+    }
+    let flow = progress(total, total);
+
+    (stats, flow)
+}
+
+struct ReencodedImage {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+    downsampled: bool,
+}
+
+fn reencode_image(stream: &Stream, components: u8, dpi: Option<f32>, settings: &ImageSettings) -> Option<ReencodedImage> {
+    let width = stream.dict.get(b"Width").and_then(Object::as_i64).ok()? as u32;
+    let height = stream.dict.get(b"Height").and_then(Object::as_i64).ok()? as u32;
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let is_jpeg = image_filters(stream).as_slice() == ["DCTDecode"];
+
+    // 计算缩放比例
+    let scale = match dpi {
+        Some(dpi) if dpi > settings.target_dpi * settings.downsample_threshold => settings.target_dpi / dpi,
+        Some(_) => 1.0,
+        None => {
+            let max_pixels = settings.target_dpi * FALLBACK_PAGE_INCHES;
+            let long_side = width.max(height) as f32;
+            if long_side > max_pixels * settings.downsample_threshold {
+                max_pixels / long_side
+            } else {
+                1.0
+            }
+        }
+    };
+    let downsampled = scale < 1.0;
+
+    if !downsampled && !is_jpeg && !settings.reencode_lossless {
+        return None;
+    }
+
+    let mut image = decode_image(stream, width, height, components)?;
+
+    if downsampled {
+        let new_width = ((width as f32 * scale).round() as u32).max(1);
+        let new_height = ((height as f32 * scale).round() as u32).max(1);
+        image = image.resize_exact(new_width, new_height, FilterType::CatmullRom);
+    }
+
+    let mut data = Vec::new();
+    let encoder = JpegEncoder::new_with_quality(&mut data, settings.jpeg_quality.clamp(1, 100));
+    image.write_with_encoder(encoder).ok()?;
+
+    Some(ReencodedImage {
+        width: image.width(),
+        height: image.height(),
+        data,
+        downsampled,
+    })
+}
+
+pub(crate) fn image_filters(stream: &Stream) -> Vec<String> {
+    stream.filters().unwrap_or_default()
+}
+
+/// 将图像流解码为像素数据，支持 DCT、Flate 和未压缩的图像
+fn decode_image(stream: &Stream, width: u32, height: u32, components: u8) -> Option<DynamicImage> {
+    if image_filters(stream).as_slice() == ["DCTDecode"] {
+        let image = image::load_from_memory_with_format(&stream.content, ImageFormat::Jpeg).ok()?;
+        return Some(match components {
+            1 => DynamicImage::ImageLuma8(image.to_luma8()),
+            _ => DynamicImage::ImageRgb8(image.to_rgb8()),
+        });
+    }
+
+    let raw = decode_samples(stream, width, height, components)?;
+    match components {
+        1 => GrayImage::from_raw(width, height, raw).map(DynamicImage::ImageLuma8),
+        3 => RgbImage::from_raw(width, height, raw).map(DynamicImage::ImageRgb8),
+        _ => None,
+    }
+}
+
+/// 解码 Flate 压缩或未压缩的 8 位图像，返回逐行排列的采样数据
+pub(crate) fn decode_samples(stream: &Stream, width: u32, height: u32, components: u8) -> Option<Vec<u8>> {
+    let filters = image_filters(stream);
+    let filters: Vec<&str> = filters.iter().map(String::as_str).collect();
+
+    let raw = match filters.as_slice() {
+        ["FlateDecode"] => {
+            // 按图像尺寸限制解压大小（PNG 预测器每行多一个字节），防止构造的数据解压出超大内容
+            let limit = height as u64 * (width as u64 * components as u64 + 1);
+            let mut data = Vec::new();
+            flate2::read::ZlibDecoder::new(stream.content.as_slice())
+                .take(limit + 1)
+                .read_to_end(&mut data)
+                .ok()?;
+            if data.len() as u64 > limit {
+                return None;
+            }
+            apply_predictor(stream, data, width, components)?
+        }
+        [] => stream.content.clone(),
+        _ => return None,
+    };
+
+    let expected = width as usize * height as usize * components as usize;
+    if raw.len() < expected {
+        return None;
+    }
+    let mut raw = raw;
+    raw.truncate(expected);
+    Some(raw)
+}
+
+fn apply_predictor(stream: &Stream, data: Vec<u8>, width: u32, components: u8) -> Option<Vec<u8>> {
+    let predictor = stream
+        .dict
+        .get(b"DecodeParms")
+        .and_then(Object::as_dict)
+        .and_then(|params| params.get(b"Predictor"))
+        .and_then(Object::as_i64)
+        .unwrap_or(1);
+
+    match predictor {
+        1 => Some(data),
+        10..=15 => lopdf::filters::png::decode_frame(&data, components as usize, width as usize).ok(),
+        // TIFF 预测器较少见，不做处理
+        _ => None,
+    }
+}
+
+/// 判断图像是否可以被重新编码，返回颜色分量数
+fn reencodable_components(document: &Document, dict: &Dictionary) -> Option<u8> {
+    if dict.get(b"Subtype").and_then(Object::as_name).ok()? != b"Image" {
+        return None;
+    }
+
+    // 图像蒙版、自定义解码数组和非 8 位图像保持原样
+    if dict.get(b"ImageMask").and_then(Object::as_bool).unwrap_or(false)
+        || dict.has(b"Decode")
+        || dict.get(b"BitsPerComponent").and_then(Object::as_i64).ok()? != 8
+    {
+        return None;
+    }
+
+    let color_space = dict.get(b"ColorSpace").ok()?;
+    color_space_components(document, color_space).filter(|components| matches!(components, 1 | 3))
+}
+
+/// 灰度、RGB 和 CMYK 色彩空间的颜色分量数，索引色、专色等返回 None
+pub(crate) fn color_space_components(document: &Document, color_space: &Object) -> Option<u8> {
+    let (_, color_space) = document.dereference(color_space).ok()?;
+
+    match color_space {
+        Object::Name(name) => match name.as_slice() {
+            b"DeviceGray" | b"CalGray" => Some(1),
+            b"DeviceRGB" | b"CalRGB" => Some(3),
+            b"DeviceCMYK" => Some(4),
+            _ => None,
+        },
+        Object::Array(array) => {
+            let family = array.first()?.as_name().ok()?;
+            match family {
+                b"CalGray" => Some(1),
+                b"CalRGB" => Some(3),
+                b"ICCBased" => {
+                    let (_, profile) = document.dereference(array.get(1)?).ok()?;
+                    match profile.as_stream().ok()?.dict.get(b"N").and_then(Object::as_i64).ok()? {
+                        n @ (1 | 3 | 4) => Some(n as u8),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// 被其他图像用作 /SMask 或 /Mask 的图像，重新编码会破坏透明度
+pub(crate) fn mask_image_ids(document: &Document) -> HashSet<ObjectId> {
+    document
+        .objects
+        .values()
+        .filter_map(|object| object.as_stream().ok())
+        .flat_map(|stream| {
+            [b"SMask".as_slice(), b"Mask".as_slice()]
+                .into_iter()
+                .filter_map(|key| stream.dict.get(key).and_then(Object::as_reference).ok())
+        })
+        .collect()
+}
+
+type Matrix = [f32; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// 计算 `m × ctm`
+fn multiply(m: &Matrix, ctm: &Matrix) -> Matrix {
+    [
+        m[0] * ctm[0] + m[1] * ctm[2],
+        m[0] * ctm[1] + m[1] * ctm[3],
+        m[2] * ctm[0] + m[3] * ctm[2],
+        m[2] * ctm[1] + m[3] * ctm[3],
+        m[4] * ctm[0] + m[5] * ctm[2] + ctm[4],
+        m[4] * ctm[1] + m[5] * ctm[3] + ctm[5],
+    ]
+}
+
+fn matrix_from_operands(operands: &[Object]) -> Option<Matrix> {
+    if operands.len() != 6 {
+        return None;
+    }
+    let mut matrix = IDENTITY;
+    for (value, operand) in matrix.iter_mut().zip(operands) {
+        *value = operand.as_float().ok()?;
+    }
+    Some(matrix)
+}
+
+/// 计算每个图像在所有页面上的最低有效分辨率 (DPI)
+///
+/// 同一图像在多处以不同尺寸显示时取最低值，保证最大的显示尺寸不失真。
+pub(crate) fn effective_image_dpi(document: &Document) -> HashMap<ObjectId, f32> {
+    let mut dpi = HashMap::new();
+
+    for page_id in document.page_iter() {
+        let Ok(content) = document.get_page_content(page_id) else {
+            continue;
+        };
+        let xobjects = page_xobjects(document, page_id);
+        scan_content(document, &content, &xobjects, IDENTITY, 0, &mut dpi);
+    }
+
+    dpi
+}
+
+/// 收集页面（包括继承的）资源中的 XObject 名称
+fn page_xobjects(document: &Document, page_id: ObjectId) -> HashMap<Vec<u8>, ObjectId> {
+    let mut xobjects = HashMap::new();
+    let Ok((inline, resource_ids)) = document.get_page_resources(page_id) else {
+        return xobjects;
+    };
+
+    let resources = inline
+        .into_iter()
+        .chain(resource_ids.into_iter().filter_map(|id| document.get_dictionary(id).ok()));
+
+    // 越靠近页面的资源优先级越高，所以已存在的名称不覆盖
+    for resource in resources {
+        for (name, id) in resource_xobjects(document, resource) {
+            xobjects.entry(name).or_insert(id);
+        }
+    }
+
+    xobjects
+}
+
+fn resource_xobjects(document: &Document, resources: &Dictionary) -> HashMap<Vec<u8>, ObjectId> {
+    let Ok(xobject_dict) = resources
+        .get(b"XObject")
+        .and_then(|object| document.dereference(object))
+        .and_then(|(_, object)| object.as_dict())
+    else {
+        return HashMap::new();
+    };
+
+    xobject_dict
+        .iter()
+        .filter_map(|(name, object)| Some((name.clone(), object.as_reference().ok()?)))
+        .collect()
+}
+
+fn scan_content(
+    document: &Document,
+    content: &[u8],
+    xobjects: &HashMap<Vec<u8>, ObjectId>,
+    base: Matrix,
+    depth: usize,
+    dpi: &mut HashMap<ObjectId, f32>,
+) {
+    let Ok(content) = Content::decode(content) else {
+        return;
+    };
+
+    let mut ctm = base;
+    let mut stack: Vec<Matrix> = Vec::new();
+
+    for operation in &content.operations {
+        match operation.operator.as_str() {
+            "q" => stack.push(ctm),
+            "Q" => ctm = stack.pop().unwrap_or(base),
+            "cm" => {
+                if let Some(matrix) = matrix_from_operands(&operation.operands) {
+                    ctm = multiply(&matrix, &ctm);
+                }
+            }
+            "Do" => {
+                let Some(id) = operation
+                    .operands
+                    .first()
+                    .and_then(|name| name.as_name().ok())
+                    .and_then(|name| xobjects.get(name))
+                else {
+                    continue;
+                };
+                let Ok(stream) = document.get_object(*id).and_then(Object::as_stream) else {
+                    continue;
+                };
+
+                match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+                    Ok(b"Image") => record_image_dpi(*id, stream, &ctm, dpi),
+                    Ok(b"Form") if depth < MAX_FORM_DEPTH => {
+                        let form_matrix = stream
+                            .dict
+                            .get(b"Matrix")
+                            .and_then(Object::as_array)
+                            .ok()
+                            .and_then(|operands| matrix_from_operands(operands))
+                            .unwrap_or(IDENTITY);
+                        // 表单没有自己的资源时沿用父级资源
+                        let form_xobjects = stream
+                            .dict
+                            .get(b"Resources")
+                            .and_then(|object| document.dereference(object))
+                            .and_then(|(_, object)| object.as_dict())
+                            .map(|resources| resource_xobjects(document, resources))
+                            .unwrap_or_else(|_| xobjects.clone());
+                        let form_content = stream
+                            .decompressed_content()
+                            .unwrap_or_else(|_| stream.content.clone());
+                        scan_content(
+                            document,
+                            &form_content,
+                            &form_xobjects,
+                            multiply(&form_matrix, &ctm),
+                            depth + 1,
+                            dpi,
+                        );
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn record_image_dpi(id: ObjectId, stream: &Stream, ctm: &Matrix, dpi: &mut HashMap<ObjectId, f32>) {
+    let width = stream.dict.get(b"Width").and_then(Object::as_i64).unwrap_or(0) as f32;
+    let height = stream.dict.get(b"Height").and_then(Object::as_i64).unwrap_or(0) as f32;
+
+    // 图像的单位正方形经过 CTM 变换后的显示尺寸（单位：点，1/72 英寸）
+    let shown_width = (ctm[0] * ctm[0] + ctm[1] * ctm[1]).sqrt();
+    let shown_height = (ctm[2] * ctm[2] + ctm[3] * ctm[3]).sqrt();
+    if width <= 0.0 || height <= 0.0 || shown_width <= 0.0 || shown_height <= 0.0 {
+        return;
+    }
+
+    let dpi_x = width / (shown_width / 72.0);
+    let dpi_y = height / (shown_height / 72.0);
+    let image_dpi = dpi_x.min(dpi_y);
+
+    dpi.entry(id)
+        .and_modify(|existing| *existing = existing.min(image_dpi))
+        .or_insert(image_dpi);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn flate_image(width: u32, height: u32, pixels: &[u8]) -> Stream {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(pixels).unwrap();
+        let mut dict = Dictionary::new();
+        dict.set("Filter", Object::Name(b"FlateDecode".to_vec()));
+        dict.set("Width", width as i64);
+        dict.set("Height", height as i64);
+        Stream::new(dict, encoder.finish().unwrap())
+    }
+
+    #[test]
+    fn decodes_flate_image_within_limit() {
+        let stream = flate_image(2, 2, &[0, 64, 128, 255]);
+        let image = decode_image(&stream, 2, 2, 1).unwrap();
+        assert_eq!(image.as_bytes(), [0, 64, 128, 255]);
+    }
+
+    #[test]
+    fn rejects_flate_image_that_expands_past_its_size() {
+        let stream = flate_image(2, 2, &vec![0; 1 << 20]);
+        assert!(decode_image(&stream, 2, 2, 1).is_none());
+    }
+}
@@ -0,0 +1,11 @@
+// 基于 lopdf 文档模型的优化步骤，由 lopdf 引擎按压缩等级组合调用
+
+pub mod analysis;
+pub mod annotations;
+pub mod cleanup;
+pub mod color;
+pub mod dedup;
+pub mod images;
+pub mod object_streams;
+pub mod pages;
+pub mod sanitize;
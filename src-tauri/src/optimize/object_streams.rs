@@ -0,0 +1,368 @@
+// 使用对象流 (/ObjStm) 和交叉引用流 (/XRef) 保存文档
+//
+// lopdf 的 `Document::save` 会把每个对象单独写成间接对象并使用传统的 xref 表，
+// 对于包含大量小字典的文档非常浪费。这里把所有非流对象打包进压缩的对象流，
+// 并写出压缩的交叉引用流，需要 PDF 1.5 及以上版本。
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// 每个对象流中最多容纳的对象数量
+const OBJECTS_PER_STREAM: usize = 100;
+
+
+enum XrefEntry {
+    Free,
+    Normal { offset: u64, generation: u16 },
+    Compressed { container: u32, index: u16 },
+}
+
+/// 以对象流和交叉引用流的形式保存文档
+///
+/// 加密文档的对象必须单独加密，此时回退到 lopdf 的默认保存方式。
+pub fn save_with_object_streams(document: &mut Document, path: &Path) -> io::Result<()> {
+    if document.trailer.has(b"Encrypt") {
+        return document.save(path).map(|_| ());
+    }
+
+    let mut file = BufWriter::new(File::create(path)?);
+    write_document(document, &mut file)?;
+    file.flush()
+}
+
+fn write_document<W: Write>(document: &mut Document, target: &mut W) -> io::Result<()> {
+    if document.version.as_str() < "1.5" {
+        document.version = "1.5".to_string();
+    }
+
+    let mut writer = CountingWriter { inner: target, written: 0 };
+    let mut entries: BTreeMap<u32, XrefEntry> = BTreeMap::new();
+
+    writer.write_all(format!("%PDF-{}\n", document.version).as_bytes())?;
+    // 二进制注释，提示传输工具按二进制处理文件
+    writer.write_all(b"%\xE2\xE3\xCF\xD3\n")?;
+
+    // 流对象和代数不为 0 的对象不能放入对象流
+    let mut packable: Vec<(ObjectId, &Object)> = Vec::new();
+    for (&(id, generation), object) in &document.objects {
+        if is_transient(object) {
+            continue;
+        }
+
+        if matches!(object, Object::Stream(_)) || generation != 0 {
+            let offset = writer.written;
+            write_indirect_object(&mut writer, (id, generation), object)?;
+            entries.insert(id, XrefEntry::Normal { offset, generation });
+        } else {
+            packable.push(((id, generation), object));
+        }
+    }
+
+    let mut next_id = document.max_id + 1;
+
+    for chunk in packable.chunks(OBJECTS_PER_STREAM) {
+        let container = next_id;
+        next_id += 1;
+
+        let mut header = Vec::new();
+        let mut body = Vec::new();
+        for (index, ((id, _), object)) in chunk.iter().enumerate() {
+            write!(header, "{} {} ", id, body.len())?;
+            write_object(&mut body, object)?;
+            body.push(b'\n');
+            entries.insert(
+                *id,
+                XrefEntry::Compressed {
+                    container,
+                    index: index as u16,
+                },
+            );
+        }
+
+        let first = header.len();
+        header.extend_from_slice(&body);
+
+        let mut dict = Dictionary::new();
+        dict.set("Type", Object::Name(b"ObjStm".to_vec()));
+        dict.set("N", chunk.len() as i64);
+        dict.set("First", first as i64);
+
+        let offset = writer.written;
+        write_compressed_stream(&mut writer, (container, 0), dict, &header)?;
+        entries.insert(container, XrefEntry::Normal { offset, generation: 0 });
+    }
+
+    // 交叉引用流本身也需要一个对象编号
+    let xref_id = next_id;
+    let xref_offset = writer.written;
+    entries.insert(
+        xref_id,
+        XrefEntry::Normal {
+            offset: xref_offset,
+            generation: 0,
+        },
+    );
+
+    let size = xref_id + 1;
+    let rows: Vec<(u64, u64, u64)> = (0..size)
+        .map(|id| match entries.get(&id).unwrap_or(&XrefEntry::Free) {
+            XrefEntry::Free => (0, 0, if id == 0 { 65535 } else { 0 }),
+            XrefEntry::Normal { offset, generation } => (1, *offset, *generation as u64),
+            XrefEntry::Compressed { container, index } => (2, *container as u64, *index as u64),
+        })
+        .collect();
+
+    // 交叉引用流中每个字段的字节宽度：类型、偏移量/容器编号、代数/索引，
+    // 第二个字段按最大的偏移量确定宽度，超过 4 GiB 的文件也能正确寻址
+    let largest = rows.iter().map(|&(_, field2, _)| field2).max().unwrap_or(0);
+    let widths = [1, field_width(largest), 2];
+
+    let mut xref_data = Vec::with_capacity(rows.len() * widths.iter().sum::<usize>());
+    for (kind, field2, field3) in rows {
+        push_field(&mut xref_data, kind, widths[0]);
+        push_field(&mut xref_data, field2, widths[1]);
+        push_field(&mut xref_data, field3, widths[2]);
+    }
+
+    let mut xref_dict = Dictionary::new();
+    for key in [b"Root".as_slice(), b"Info", b"ID"] {
+        if let Ok(value) = document.trailer.get(key) {
+            xref_dict.set(key, value.clone());
+        }
+    }
+    xref_dict.set("Type", Object::Name(b"XRef".to_vec()));
+    xref_dict.set("Size", size as i64);
+    xref_dict.set("W", Object::Array(widths.iter().map(|width| Object::Integer(*width as i64)).collect()));
+
+    write_compressed_stream(&mut writer, (xref_id, 0), xref_dict, &xref_data)?;
+    writer.write_all(format!("startxref\n{}\n%%EOF\n", xref_offset).as_bytes())?;
+
+    Ok(())
+}
+
+/// 旧的对象流、交叉引用流和线性化字典会在保存时重新生成或失效
+pub(crate) fn is_transient(object: &Object) -> bool {
+    object
+        .type_name()
+        .map(|name| ["ObjStm", "XRef", "Linearized"].contains(&name))
+        .unwrap_or(false)
+}
+
+/// 写下 `value` 所需的最少字节数，至少为 1
+fn field_width(value: u64) -> usize {
+    (8 - value.leading_zeros() as usize / 8).max(1)
+}
+
+fn push_field(buffer: &mut Vec<u8>, value: u64, width: usize) {
+    buffer.extend_from_slice(&value.to_be_bytes()[8 - width..]);
+}
+
+fn write_compressed_stream<W: Write>(writer: &mut W, id: ObjectId, mut dict: Dictionary, data: &[u8]) -> io::Result<()> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data)?;
+    let compressed = encoder.finish()?;
+
+    dict.set("Filter", Object::Name(b"FlateDecode".to_vec()));
+    dict.set("Length", compressed.len() as i64);
+
+    writeln!(writer, "{} {} obj", id.0, id.1)?;
+    write_dictionary(writer, &dict)?;
+    writer.write_all(b"\nstream\n")?;
+    writer.write_all(&compressed)?;
+    writer.write_all(b"\nendstream\nendobj\n")
+}
+
+fn write_indirect_object<W: Write>(writer: &mut W, id: ObjectId, object: &Object) -> io::Result<()> {
+    writeln!(writer, "{} {} obj", id.0, id.1)?;
+    write_object(writer, object)?;
+    writer.write_all(b"\nendobj\n")
+}
+
+pub(crate) fn write_object<W: Write>(writer: &mut W, object: &Object) -> io::Result<()> {
+    match object {
+        Object::Null => writer.write_all(b"null"),
+        Object::Boolean(value) => writer.write_all(if *value { b"true" } else { b"false" }),
+        Object::Integer(value) => write!(writer, "{}", value),
+        Object::Real(value) => write!(writer, "{}", value),
+        Object::Name(name) => write_name(writer, name),
+        Object::String(text, format) => write_string(writer, text, *format),
+        Object::Array(array) => {
+            writer.write_all(b"[")?;
+            for (index, item) in array.iter().enumerate() {
+                if index > 0 {
+                    writer.write_all(b" ")?;
+                }
+                write_object(writer, item)?;
+            }
+            writer.write_all(b"]")
+        }
+        Object::Dictionary(dict) => write_dictionary(writer, dict),
+        Object::Stream(stream) => {
+            let mut dict = stream.dict.clone();
+            dict.set("Length", stream.content.len() as i64);
+            write_dictionary(writer, &dict)?;
+            writer.write_all(b"\nstream\n")?;
+            writer.write_all(&stream.content)?;
+            writer.write_all(b"\nendstream")
+        }
+        Object::Reference(id) => write!(writer, "{} {} R", id.0, id.1),
+    }
+}
+
+fn write_dictionary<W: Write>(writer: &mut W, dict: &Dictionary) -> io::Result<()> {
+    writer.write_all(b"<<")?;
+    for (key, value) in dict.iter() {
+        write_name(writer, key)?;
+        writer.write_all(b" ")?;
+        write_object(writer, value)?;
+    }
+    writer.write_all(b">>")
+}
+
+fn write_name<W: Write>(writer: &mut W, name: &[u8]) -> io::Result<()> {
+    writer.write_all(b"/")?;
+    for &byte in name {
+        // 空白、分隔符和不可打印字符使用 #xx 转义
+        if b" \t\n\r\x0C()<>[]{}/%#".contains(&byte) || !(33..=126).contains(&byte) {
+            write!(writer, "#{:02X}", byte)?;
+        } else {
+            writer.write_all(&[byte])?;
+        }
+    }
+    Ok(())
+}
+
+fn write_string<W: Write>(writer: &mut W, text: &[u8], format: StringFormat) -> io::Result<()> {
+    match format {
+        StringFormat::Literal => {
+            writer.write_all(b"(")?;
+            for &byte in text {
+                match byte {
+                    b'(' | b')' | b'\\' => writer.write_all(&[b'\\', byte])?,
+                    b'\r' => writer.write_all(b"\\r")?,
+                    _ => writer.write_all(&[byte])?,
+                }
+            }
+            writer.write_all(b")")
+        }
+        StringFormat::Hexadecimal => {
+            writer.write_all(b"<")?;
+            for &byte in text {
+                write!(writer, "{:02X}", byte)?;
+            }
+            writer.write_all(b">")
+        }
+    }
+}
+
+/// 记录已写入字节数，用于计算对象在文件中的偏移量
+struct CountingWriter<W: Write> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream};
+
+    /// 包含页面树、内容流、需要转义的名称和字符串，以及超过一个对象流容量的小对象
+    fn sample_document() -> Document {
+        let mut document = Document::with_version("1.4");
+        let pages_id = document.new_object_id();
+        let content_id = document.add_object(Stream::new(dictionary! {}, b"BT /F1 12 Tf (Hi) Tj ET".to_vec()));
+        let font_id = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), Object::Real(841.5)],
+            "Contents" => content_id,
+            "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+        });
+        document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }));
+        let catalog_id = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        document.trailer.set("Root", catalog_id);
+
+        for index in 0..OBJECTS_PER_STREAM + 20 {
+            document.add_object(dictionary! {
+                "Index" => index as i64,
+                "Name with space" => Object::Name(b"A#B (c)".to_vec()),
+                "Literal" => Object::String(b"paren ( ) \\ \r end".to_vec(), StringFormat::Literal),
+                "Hex" => Object::String(vec![0, 0xFF, 0x10], StringFormat::Hexadecimal),
+                "Flags" => vec![true.into(), false.into(), Object::Null],
+            });
+        }
+        document
+    }
+
+    #[test]
+    fn saved_document_reloads_with_same_objects() {
+        let mut document = sample_document();
+        let mut saved = Vec::new();
+        write_document(&mut document, &mut saved).unwrap();
+        let reloaded = Document::load_mem(&saved).unwrap();
+
+        assert_eq!(reloaded.version, "1.5");
+        assert_eq!(reloaded.trailer.get(b"Root").unwrap(), document.trailer.get(b"Root").unwrap());
+        assert_eq!(reloaded.get_pages().len(), 1);
+
+        let object_streams = reloaded.objects.values().filter(|object| object.type_name().ok() == Some("ObjStm"));
+        assert_eq!(object_streams.count(), 2);
+
+        for (id, object) in &document.objects {
+            let reloaded_object = reloaded.get_object(*id).unwrap();
+            match (object, reloaded_object) {
+                (Object::Stream(original), Object::Stream(reloaded_stream)) => {
+                    assert_eq!(original.dict, reloaded_stream.dict, "stream {:?}", id);
+                    assert_eq!(original.content, reloaded_stream.content, "stream {:?}", id);
+                }
+                _ => assert_eq!(object, reloaded_object, "object {:?}", id),
+            }
+        }
+    }
+
+    #[test]
+    fn transient_objects_are_not_written() {
+        let mut document = sample_document();
+        let stale_id = document.add_object(Stream::new(dictionary! { "Type" => "ObjStm" }, Vec::new()));
+        let mut saved = Vec::new();
+        write_document(&mut document, &mut saved).unwrap();
+
+        let reloaded = Document::load_mem(&saved).unwrap();
+        assert!(reloaded.get_object(stale_id).is_err());
+    }
+
+    #[test]
+    fn xref_offset_width_grows_with_the_file() {
+        assert_eq!(field_width(0), 1);
+        assert_eq!(field_width(255), 1);
+        assert_eq!(field_width(256), 2);
+        assert_eq!(field_width(u32::MAX as u64), 4);
+        assert_eq!(field_width(1 << 32), 5);
+    }
+}
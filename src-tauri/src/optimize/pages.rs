@@ -0,0 +1,308 @@
+// 页面选择
+//
+// 页面选择表达式由逗号分隔的页码和范围组成，例如 `1-5,8,10-`，`10-` 表示从第 10 页到最后一页。
+// 页码从 1 开始，页面始终按原文档顺序输出，超出文档页数的部分被忽略。
+// lopdf 引擎把选中的页面直接挂到根页面树节点下，删除的页面替换为 null，
+// 其内容、资源和图像在之后的 `prune_objects` 中被移除。
+
+use crate::i18n::Text;
+use crate::tr;
+use lopdf::{Document, Object, ObjectId};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// 可以从上级页面树节点继承的页面属性
+const INHERITABLE_KEYS: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// 页码闭区间，`last` 为 None 表示到最后一页
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PageRange {
+    first: usize,
+    last: Option<usize>,
+}
+
+impl PageRange {
+    fn contains(&self, page: usize) -> bool {
+        page >= self.first && self.last.is_none_or(|last| page <= last)
+    }
+}
+
+/// 解析后的页面选择，区间按起始页排序并合并了重叠和相邻的部分
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageSelection {
+    ranges: Vec<PageRange>,
+}
+
+impl PageSelection {
+    /// 解析页面选择表达式，失败时返回面向用户的错误信息
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let mut ranges = Vec::new();
+        for part in expression.split(',').map(str::trim) {
+            let number = |text: &str| {
+                text.trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&page| page > 0)
+                    .ok_or_else(|| tr!(Text::InvalidPageSelection, expression))
+            };
+            let range = match part.split_once('-') {
+                Some((first, "")) => PageRange { first: number(first)?, last: None },
+                Some((first, last)) => {
+                    let (first, last) = (number(first)?, number(last)?);
+                    if last < first {
+                        return Err(tr!(Text::PageRangeReversed, part));
+                    }
+                    PageRange { first, last: Some(last) }
+                }
+                None => {
+                    let page = number(part)?;
+                    PageRange { first: page, last: Some(page) }
+                }
+            };
+            ranges.push(range);
+        }
+
+        ranges.sort_by_key(|range| range.first);
+        let mut merged: Vec<PageRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(previous) if previous.last.is_none_or(|last| range.first <= last.saturating_add(1)) => {
+                    previous.last = previous.last.zip(range.last).map(|(a, b)| a.max(b));
+                }
+                _ => merged.push(range),
+            }
+        }
+
+        Ok(Self { ranges: merged })
+    }
+
+    pub fn contains(&self, page: usize) -> bool {
+        self.ranges.iter().any(|range| range.contains(page))
+    }
+
+    /// 第 `first` 到 `last` 页中被选中的页数
+    pub fn count_between(&self, first: usize, last: usize) -> usize {
+        self.ranges
+            .iter()
+            .map(|range| {
+                let start = range.first.max(first);
+                let end = range.last.map_or(last, |range_last| range_last.min(last));
+                (end + 1).saturating_sub(start)
+            })
+            .sum()
+    }
+
+    /// 只有一个连续区间时返回其起止页
+    pub fn single_range(&self) -> Option<(usize, Option<usize>)> {
+        match self.ranges.as_slice() {
+            [range] => Some((range.first, range.last)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PageSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, range) in self.ranges.iter().enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+            match range.last {
+                Some(last) if last == range.first => write!(f, "{}", last)?,
+                Some(last) => write!(f, "{}-{}", range.first, last)?,
+                None => write!(f, "{}-", range.first)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 只保留选中的页面，返回保留的页数；没有选中任何页面时不修改文档并返回 0
+pub fn select_pages(document: &mut Document, selection: &PageSelection) -> usize {
+    let pages = document.get_pages();
+    let kept: Vec<ObjectId> = pages
+        .iter()
+        .filter(|(&number, _)| selection.contains(number as usize))
+        .map(|(_, &page_id)| page_id)
+        .collect();
+    if kept.is_empty() || kept.len() == pages.len() {
+        return kept.len();
+    }
+
+    let Ok(root_id) = document
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Pages"))
+        .and_then(Object::as_reference)
+    else {
+        return pages.len();
+    };
+
+    // 页面不再挂在原来的中间节点下，继承的属性要先写到页面本身
+    for &page_id in &kept {
+        inherit_attributes(document, page_id);
+        if let Ok(page) = document.get_object_mut(page_id).and_then(Object::as_dict_mut) {
+            page.set("Parent", root_id);
+        }
+    }
+
+    if let Ok(root) = document.get_object_mut(root_id).and_then(Object::as_dict_mut) {
+        root.set("Kids", kept.iter().map(|&page_id| Object::Reference(page_id)).collect::<Vec<_>>());
+        root.set("Count", kept.len() as i64);
+    }
+
+    // 书签、链接和结构树可能仍引用删除的页面，替换为 null 后这些引用仍然有效，
+    // 页面引用的内容和资源则不再可达
+    let kept: BTreeSet<ObjectId> = kept.into_iter().collect();
+    for page_id in pages.into_values().filter(|page_id| !kept.contains(page_id)) {
+        document.objects.insert(page_id, Object::Null);
+    }
+
+    kept.len()
+}
+
+/// 把页面从上级节点继承的属性复制到页面字典中
+pub(crate) fn inherit_attributes(document: &mut Document, page_id: ObjectId) {
+    let Ok(page) = document.get_dictionary(page_id) else {
+        return;
+    };
+
+    let mut missing: Vec<&[u8]> = INHERITABLE_KEYS.into_iter().filter(|key| !page.has(key)).collect();
+    let mut inherited = Vec::new();
+    let mut visited = BTreeSet::new();
+    let mut parent = page.get(b"Parent").and_then(Object::as_reference).ok();
+
+    while let Some(node_id) = parent.filter(|node_id| visited.insert(*node_id)) {
+        let Ok(node) = document.get_dictionary(node_id) else {
+            break;
+        };
+        missing.retain(|key| match node.get(key) {
+            Ok(value) => {
+                inherited.push((key.to_vec(), value.clone()));
+                false
+            }
+            Err(_) => true,
+        });
+        if missing.is_empty() {
+            break;
+        }
+        parent = node.get(b"Parent").and_then(Object::as_reference).ok();
+    }
+
+    if let Ok(page) = document.get_object_mut(page_id).and_then(Object::as_dict_mut) {
+        for (key, value) in inherited {
+            page.set(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    fn normalized(expression: &str) -> String {
+        PageSelection::parse(expression).unwrap().to_string()
+    }
+
+    #[test]
+    fn parses_and_normalizes_ranges() {
+        assert_eq!(normalized("1-5,8,10-"), "1-5,8,10-");
+        assert_eq!(normalized("8,1-3"), "1-3,8");
+        assert_eq!(normalized("5-7,1-3,4,10-,12"), "1-7,10-");
+        assert_eq!(normalized("3,3,3-3"), "3");
+    }
+
+    #[test]
+    fn ignores_whitespace_around_numbers() {
+        assert_eq!(normalized(" 1 - 3 , 5 ,\t7- "), "1-3,5,7-");
+        assert!(PageSelection::parse("1 2").is_err());
+    }
+
+    #[test]
+    fn rejects_reversed_ranges() {
+        assert_eq!(PageSelection::parse("1,5-2"), Err(tr!(Text::PageRangeReversed, "5-2")));
+    }
+
+    #[test]
+    fn rejects_empty_and_malformed_parts() {
+        for expression in ["", " ", "1,,2", ",", "1,", "-3", "1-2-3", "a", "1-b", "1.5"] {
+            assert_eq!(
+                PageSelection::parse(expression),
+                Err(tr!(Text::InvalidPageSelection, expression)),
+                "{:?}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_numbers() {
+        for expression in ["0", "0-3", "2-0", "99999999999999999999999"] {
+            assert!(PageSelection::parse(expression).is_err(), "{:?}", expression);
+        }
+        // 最大页码不会在合并相邻区间时溢出
+        assert_eq!(normalized(&format!("1-{},5", usize::MAX)), format!("1-{}", usize::MAX));
+    }
+
+    #[test]
+    fn counts_only_pages_inside_document() {
+        let selection = PageSelection::parse("2-4,8-").unwrap();
+        assert_eq!(selection.count_between(1, 5), 3);
+        assert_eq!(selection.count_between(1, 10), 6);
+        assert_eq!(PageSelection::parse("20-30").unwrap().count_between(1, 5), 0);
+        assert_eq!(selection.single_range(), None);
+        assert_eq!(PageSelection::parse("3-").unwrap().single_range(), Some((3, None)));
+    }
+
+    /// 三页文档，页面挂在一个带 MediaBox 的中间节点下
+    fn three_page_document() -> (Document, Vec<ObjectId>) {
+        let mut document = Document::with_version("1.5");
+        let root_id = document.new_object_id();
+        let node_id = document.new_object_id();
+        let pages: Vec<ObjectId> = (0..3)
+            .map(|_| document.add_object(dictionary! { "Type" => "Page", "Parent" => node_id }))
+            .collect();
+        document.objects.insert(
+            node_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Parent" => root_id,
+                "Kids" => pages.iter().map(|&id| Object::Reference(id)).collect::<Vec<_>>(),
+                "Count" => 3,
+                "MediaBox" => vec![0.into(), 0.into(), 300.into(), 400.into()],
+            }),
+        );
+        document.objects.insert(
+            root_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![node_id.into()],
+                "Count" => 3,
+            }),
+        );
+        let catalog_id = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => root_id });
+        document.trailer.set("Root", catalog_id);
+        (document, pages)
+    }
+
+    #[test]
+    fn selects_pages_and_keeps_inherited_attributes() {
+        let (mut document, pages) = three_page_document();
+        let selection = PageSelection::parse("3,1,7-").unwrap();
+        assert_eq!(select_pages(&mut document, &selection), 2);
+
+        let remaining: Vec<ObjectId> = document.get_pages().into_values().collect();
+        assert_eq!(remaining, vec![pages[0], pages[2]]);
+        let page = document.get_dictionary(pages[2]).unwrap();
+        assert!(page.has(b"MediaBox"));
+        assert_eq!(document.get_object(pages[1]).unwrap(), &Object::Null);
+    }
+
+    #[test]
+    fn selection_past_last_page_leaves_document_unchanged() {
+        let (mut document, _) = three_page_document();
+        let before = document.objects.clone();
+        assert_eq!(select_pages(&mut document, &PageSelection::parse("5-").unwrap()), 0);
+        assert_eq!(document.objects, before);
+    }
+}
@@ -0,0 +1,563 @@
+// 文档净化
+//
+// 对外发送的文件不应携带脚本、附件、多媒体或看不见的内容。这里移除文档级 JavaScript、
+// 会执行脚本或启动程序的动作、嵌入文件、多媒体注释，以及页面上属于默认隐藏的可选内容图层 (OCG)
+// 的内容。图层本身保留在默认配置的 /OFF 中，成为不影响显示的空图层：删除图层需要改写
+// 所有配置、用法和界面顺序中的引用，留下的 null 引用会让部分阅读器无法打开图层面板。
+// 被移除的对象由之后的 `prune_objects` 回收。
+
+use super::annotations::{page_annotations, resolve, set_annotations};
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// 会执行脚本、启动程序、提交数据或播放多媒体的动作类型
+const UNSAFE_ACTIONS: &[&[u8]] = &[
+    b"JavaScript",
+    b"Launch",
+    b"ImportData",
+    b"SubmitForm",
+    b"GoToE",
+    b"Rendition",
+    b"RichMediaExecute",
+    b"Movie",
+    b"Sound",
+];
+
+/// 多媒体注释的子类型
+const MEDIA_ANNOTATIONS: &[&[u8]] = &[b"RichMedia", b"Screen", b"Movie", b"Sound", b"3D"];
+
+/// 每类被移除的条目数量
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct SanitizeReport {
+    /// 文档级 JavaScript 名称树中的脚本
+    pub javascript: usize,
+    /// 打开动作、附加动作 (/AA) 和链接、书签上的危险动作
+    pub actions: usize,
+    /// 附件，包括嵌入文件名称树、关联文件和文件附件注释
+    pub embedded_files: usize,
+    /// RichMedia、Screen 等多媒体注释
+    pub media_annotations: usize,
+    /// 内容被删除的默认隐藏图层，图层本身保留
+    pub hidden_layers: usize,
+}
+
+impl SanitizeReport {
+    pub fn total(&self) -> usize {
+        self.javascript + self.actions + self.embedded_files + self.media_annotations + self.hidden_layers
+    }
+}
+
+/// 移除脚本、附件、多媒体和隐藏图层
+pub fn sanitize(document: &mut Document) -> SanitizeReport {
+    let mut report = SanitizeReport {
+        javascript: remove_name_tree(document, b"JavaScript"),
+        embedded_files: remove_name_tree(document, b"EmbeddedFiles"),
+        ..SanitizeReport::default()
+    };
+
+    // 文档集合 (PDF Portfolio) 只是附件的展示方式，关联文件 (/AF) 同样是附件
+    if let Ok(catalog) = document.catalog_mut() {
+        catalog.remove(b"Collection");
+    }
+    for object in document.objects.values_mut() {
+        let dict = match object {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => &mut stream.dict,
+            _ => continue,
+        };
+        if let Some(files) = dict.remove(b"AF") {
+            report.embedded_files += files.as_array().map_or(1, Vec::len);
+        }
+    }
+
+    report.actions = remove_unsafe_actions(document);
+    let (embedded_files, media_annotations) = remove_annotations(document);
+    report.embedded_files += embedded_files;
+    report.media_annotations = media_annotations;
+    report.hidden_layers = remove_hidden_layers(document);
+
+    report
+}
+
+/// 删除目录 /Names 中的名称树，返回其中的条目数
+fn remove_name_tree(document: &mut Document, key: &[u8]) -> usize {
+    let names_id = document
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Names"))
+        .and_then(Object::as_reference)
+        .ok();
+    let names = match names_id {
+        Some(id) => document.get_object_mut(id).and_then(Object::as_dict_mut),
+        None => document
+            .catalog_mut()
+            .and_then(|catalog| catalog.get_mut(b"Names"))
+            .and_then(Object::as_dict_mut),
+    };
+    let Some(tree) = names.ok().and_then(|names| names.remove(key)) else {
+        return 0;
+    };
+
+    count_name_tree(document, &tree, &mut BTreeSet::new())
+}
+
+fn count_name_tree(document: &Document, node: &Object, visited: &mut BTreeSet<ObjectId>) -> usize {
+    let node = match node {
+        Object::Reference(id) if !visited.insert(*id) => return 0,
+        Object::Reference(id) => match document.get_object(*id) {
+            Ok(node) => node,
+            Err(_) => return 0,
+        },
+        node => node,
+    };
+    let Ok(node) = node.as_dict() else {
+        return 0;
+    };
+
+    let leaves = node.get(b"Names").and_then(Object::as_array).map_or(0, |names| names.len() / 2);
+    let kids = node
+        .get(b"Kids")
+        .and_then(Object::as_array)
+        .map(|kids| kids.iter().map(|kid| count_name_tree(document, kid, visited)).sum())
+        .unwrap_or(0);
+    leaves + kids
+}
+
+/// 删除所有附加动作 (/AA)，以及打开动作、注释和书签上包含危险动作的 /OpenAction 和 /A
+fn remove_unsafe_actions(document: &mut Document) -> usize {
+    let mut unsafe_keys: Vec<(ObjectId, &[u8])> = Vec::new();
+    for (id, object) in &document.objects {
+        let dict = match object {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => &stream.dict,
+            _ => continue,
+        };
+        for key in [b"OpenAction".as_slice(), b"A"] {
+            if dict.get(key).is_ok_and(|action| is_unsafe_action(document, action, &mut BTreeSet::new())) {
+                unsafe_keys.push((*id, key));
+            }
+        }
+    }
+
+    let mut removed = 0;
+    for object in document.objects.values_mut() {
+        let dict = match object {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => &mut stream.dict,
+            _ => continue,
+        };
+        if dict.remove(b"AA").is_some() {
+            removed += 1;
+        }
+    }
+
+    // 动作对象本身不再被引用，由 prune_objects 回收
+    for (id, key) in unsafe_keys {
+        if let Ok(dict) = document.get_object_mut(id).and_then(Object::as_dict_mut) {
+            if dict.remove(key).is_some() {
+                removed += 1;
+            }
+        }
+    }
+
+    removed
+}
+
+/// 动作或其后续动作 (/Next) 中是否包含危险动作
+fn is_unsafe_action(document: &Document, action: &Object, visited: &mut BTreeSet<ObjectId>) -> bool {
+    let action = match action {
+        Object::Reference(id) if !visited.insert(*id) => return false,
+        Object::Reference(id) => match document.get_object(*id) {
+            Ok(action) => action,
+            Err(_) => return false,
+        },
+        action => action,
+    };
+    let Ok(action) = action.as_dict() else {
+        return false;
+    };
+    // 只有带 /S 的字典才是动作，避免把页面等普通对象当作动作
+    let Ok(action_type) = action.get(b"S").and_then(Object::as_name) else {
+        return false;
+    };
+    if UNSAFE_ACTIONS.contains(&action_type) {
+        return true;
+    }
+
+    match action.get(b"Next") {
+        Ok(Object::Array(actions)) => actions.iter().any(|next| is_unsafe_action(document, next, visited)),
+        Ok(next) => is_unsafe_action(document, next, visited),
+        Err(_) => false,
+    }
+}
+
+/// 删除文件附件注释和多媒体注释，返回 (附件数, 多媒体注释数)
+fn remove_annotations(document: &mut Document) -> (usize, usize) {
+    let (mut files, mut media) = (0, 0);
+    let page_ids: Vec<ObjectId> = document.get_pages().into_values().collect();
+    for page_id in page_ids {
+        let Some(annotations) = page_annotations(document, page_id) else {
+            continue;
+        };
+
+        let total = annotations.len();
+        let kept: Vec<Object> = annotations
+            .into_iter()
+            .filter(|annotation| {
+                let subtype = resolve(document, annotation)
+                    .as_dict()
+                    .and_then(|dict| dict.get(b"Subtype"))
+                    .and_then(Object::as_name)
+                    .unwrap_or_default();
+                if subtype == b"FileAttachment" {
+                    files += 1;
+                    false
+                } else if MEDIA_ANNOTATIONS.contains(&subtype) {
+                    media += 1;
+                    false
+                } else {
+                    true
+                }
+            })
+            .collect();
+
+        if kept.len() < total {
+            set_annotations(document, page_id, kept);
+        }
+    }
+    (files, media)
+}
+
+/// 删除页面和表单中属于默认隐藏图层的内容，返回隐藏的图层数
+fn remove_hidden_layers(document: &mut Document) -> usize {
+    let hidden = hidden_layer_ids(document);
+    if hidden.is_empty() {
+        return 0;
+    }
+
+    // 页面内容可能分成多个流，标记内容可以跨流，合并后整体处理
+    let page_ids: Vec<ObjectId> = document.get_pages().into_values().collect();
+    for &page_id in &page_ids {
+        let Ok(content) = document.get_and_decode_page_content(page_id) else {
+            continue;
+        };
+        let resources = page_resources(document, page_id);
+        let Some(operations) = remove_hidden_content(document, &content.operations, &resources, &hidden) else {
+            continue;
+        };
+        if let Ok(encoded) = (Content { operations }).encode() {
+            let stream_id = document.add_object(Stream::new(Dictionary::new(), encoded));
+            if let Ok(page) = document.get_object_mut(page_id).and_then(Object::as_dict_mut) {
+                page.set("Contents", stream_id);
+            }
+        }
+    }
+
+    let form_ids: Vec<ObjectId> = document
+        .objects
+        .iter()
+        .filter(|(_, object)| {
+            object
+                .as_stream()
+                .ok()
+                .and_then(|stream| stream.dict.get(b"Subtype").and_then(Object::as_name).ok())
+                == Some(b"Form".as_slice())
+        })
+        .map(|(id, _)| *id)
+        .collect();
+    for form_id in form_ids {
+        let Ok(stream) = document.get_object(form_id).and_then(Object::as_stream) else {
+            continue;
+        };
+        let Ok(content) = Content::decode(&stream.decompressed_content().unwrap_or_else(|_| stream.content.clone()))
+        else {
+            continue;
+        };
+        let resources: Vec<Dictionary> = stream
+            .dict
+            .get(b"Resources")
+            .ok()
+            .and_then(|resources| resolve(document, resources).as_dict().ok())
+            .cloned()
+            .into_iter()
+            .collect();
+        let Some(operations) = remove_hidden_content(document, &content.operations, &resources, &hidden) else {
+            continue;
+        };
+        if let (Ok(encoded), Ok(stream)) = (
+            (Content { operations }).encode(),
+            document.get_object_mut(form_id).and_then(Object::as_stream_mut),
+        ) {
+            stream.set_plain_content(encoded);
+        }
+    }
+
+    // 属于隐藏图层的注释
+    for page_id in page_ids {
+        let Some(annotations) = page_annotations(document, page_id) else {
+            continue;
+        };
+        let total = annotations.len();
+        let kept: Vec<Object> = annotations
+            .into_iter()
+            .filter(|annotation| {
+                let content = resolve(document, annotation).as_dict().ok().and_then(|dict| dict.get(b"OC").ok());
+                !content.is_some_and(|content| is_hidden(document, content, &hidden))
+            })
+            .collect();
+        if kept.len() < total {
+            set_annotations(document, page_id, kept);
+        }
+    }
+
+    // 属于隐藏图层的表单 XObject 不再被绘制，替换为 null
+    let hidden_xobjects: Vec<ObjectId> = document
+        .objects
+        .iter()
+        .filter(|(_, object)| {
+            object
+                .as_stream()
+                .ok()
+                .and_then(|stream| stream.dict.get(b"OC").ok())
+                .is_some_and(|content| is_hidden(document, content, &hidden))
+        })
+        .map(|(id, _)| *id)
+        .collect();
+    for id in hidden_xobjects {
+        document.objects.insert(id, Object::Null);
+    }
+
+    hidden.len()
+}
+
+/// 默认配置 (/D) 中关闭的图层
+fn hidden_layer_ids(document: &Document) -> BTreeSet<ObjectId> {
+    let Some(properties) = document
+        .catalog()
+        .and_then(|catalog| catalog.get(b"OCProperties"))
+        .ok()
+        .and_then(|properties| resolve(document, properties).as_dict().ok())
+    else {
+        return BTreeSet::new();
+    };
+    let Some(config) = properties
+        .get(b"D")
+        .ok()
+        .and_then(|config| resolve(document, config).as_dict().ok())
+    else {
+        return BTreeSet::new();
+    };
+
+    let references = |key: &[u8]| -> BTreeSet<ObjectId> {
+        properties_array(document, config, key)
+            .iter()
+            .filter_map(|object| object.as_reference().ok())
+            .collect()
+    };
+
+    // BaseState 为 OFF 时，没有列在 ON 中的图层都是隐藏的
+    if config.get(b"BaseState").and_then(Object::as_name).ok() == Some(b"OFF".as_slice()) {
+        let on = references(b"ON");
+        properties_array(document, properties, b"OCGs")
+            .iter()
+            .filter_map(|object| object.as_reference().ok())
+            .filter(|id| !on.contains(id))
+            .collect()
+    } else {
+        references(b"OFF")
+    }
+}
+
+fn properties_array<'a>(document: &'a Document, dict: &'a Dictionary, key: &[u8]) -> &'a [Object] {
+    dict.get(key)
+        .ok()
+        .and_then(|array| resolve(document, array).as_array().ok())
+        .map_or(&[], Vec::as_slice)
+}
+
+/// 可选内容组或成员字典 (OCMD) 在默认配置下是否隐藏
+fn is_hidden(document: &Document, content: &Object, hidden: &BTreeSet<ObjectId>) -> bool {
+    if let Object::Reference(id) = content {
+        if hidden.contains(id) {
+            return true;
+        }
+    }
+    let Ok(dict) = resolve(document, content).as_dict() else {
+        return false;
+    };
+    if !dict.type_is(b"OCMD") {
+        return false;
+    }
+
+    let groups: Vec<bool> = match dict.get(b"OCGs").map(|groups| resolve(document, groups)) {
+        Ok(Object::Array(groups)) => groups
+            .iter()
+            .map(|group| group.as_reference().is_ok_and(|id| hidden.contains(&id)))
+            .collect(),
+        Ok(_) => vec![dict.get(b"OCGs").and_then(Object::as_reference).is_ok_and(|id| hidden.contains(&id))],
+        Err(_) => return false,
+    };
+    if groups.is_empty() {
+        return false;
+    }
+
+    // 默认策略 AnyOn：任一图层显示时内容可见
+    match dict.get(b"P").and_then(Object::as_name).unwrap_or(b"AnyOn") {
+        b"AllOn" => groups.iter().any(|&hidden| hidden),
+        b"AnyOff" => groups.iter().all(|&hidden| !hidden),
+        b"AllOff" => groups.iter().any(|&hidden| !hidden),
+        _ => groups.iter().all(|&hidden| hidden),
+    }
+}
+
+/// 去掉属于隐藏图层的标记内容和表单 XObject，没有变化时返回 None
+fn remove_hidden_content(
+    document: &Document,
+    operations: &[Operation],
+    resources: &[Dictionary],
+    hidden: &BTreeSet<ObjectId>,
+) -> Option<Vec<Operation>> {
+    let lookup = |category: &[u8], name: &Object| -> Option<&Object> {
+        let name = name.as_name().ok()?;
+        resources.iter().find_map(|resources| {
+            let entries = resolve(document, resources.get(category).ok()?).as_dict().ok()?;
+            entries.get(name).ok()
+        })
+    };
+
+    let mut kept = Vec::with_capacity(operations.len());
+    let mut depth = 0;
+    let mut skip_until: Option<usize> = None;
+    for operation in operations {
+        match operation.operator.as_str() {
+            "BMC" | "BDC" => {
+                depth += 1;
+                let hidden_content = operation.operator == "BDC"
+                    && operation.operands.first().and_then(|tag| tag.as_name().ok()) == Some(b"OC".as_slice())
+                    && operation
+                        .operands
+                        .get(1)
+                        .and_then(|property| lookup(b"Properties", property))
+                        .is_some_and(|property| is_hidden(document, property, hidden));
+                if skip_until.is_none() && hidden_content {
+                    skip_until = Some(depth);
+                }
+                if skip_until.is_some() {
+                    continue;
+                }
+            }
+            "EMC" => {
+                let level = depth;
+                depth = depth.saturating_sub(1);
+                if let Some(skip_level) = skip_until {
+                    if level <= skip_level {
+                        skip_until = None;
+                    }
+                    continue;
+                }
+            }
+            _ if skip_until.is_some() => continue,
+            "Do" => {
+                let hidden_xobject = operation
+                    .operands
+                    .first()
+                    .and_then(|name| lookup(b"XObject", name))
+                    .and_then(|xobject| resolve(document, xobject).as_stream().ok())
+                    .and_then(|stream| stream.dict.get(b"OC").ok())
+                    .is_some_and(|content| is_hidden(document, content, hidden));
+                if hidden_xobject {
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        kept.push(operation.clone());
+    }
+
+    (kept.len() < operations.len()).then_some(kept)
+}
+
+/// 页面及其继承的资源字典
+fn page_resources(document: &Document, page_id: ObjectId) -> Vec<Dictionary> {
+    let Ok((inline, ids)) = document.get_page_resources(page_id) else {
+        return Vec::new();
+    };
+    inline
+        .cloned()
+        .into_iter()
+        .chain(ids.into_iter().filter_map(|id| document.get_dictionary(id).ok().cloned()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    /// 一个页面，内容中有一段属于默认隐藏图层的标记内容，打开文档时运行脚本
+    fn document_with_hidden_layer() -> (Document, ObjectId, ObjectId) {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let layer_id = document.add_object(dictionary! { "Type" => "OCG", "Name" => Object::string_literal("Draft") });
+        let content = document.add_object(Stream::new(
+            dictionary! {},
+            b"/OC /L1 BDC 0 0 10 10 re f EMC 20 20 10 10 re f".to_vec(),
+        ));
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content,
+            "Resources" => dictionary! { "Properties" => dictionary! { "L1" => layer_id } },
+        });
+        document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![Object::Reference(page_id)],
+            "Count" => 1,
+        }));
+        let script = document.add_object(dictionary! {
+            "S" => "JavaScript",
+            "JS" => Object::string_literal("app.alert(1)"),
+        });
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "OpenAction" => script,
+            "OCProperties" => dictionary! {
+                "OCGs" => vec![Object::Reference(layer_id)],
+                "D" => dictionary! { "OFF" => vec![Object::Reference(layer_id)] },
+            },
+        });
+        document.trailer.set("Root", catalog_id);
+        (document, page_id, layer_id)
+    }
+
+    #[test]
+    fn hidden_layer_content_is_removed_but_the_layer_is_kept() {
+        let (mut document, page_id, layer_id) = document_with_hidden_layer();
+
+        let report = sanitize(&mut document);
+
+        assert_eq!(report.hidden_layers, 1);
+        let operators: Vec<String> = document
+            .get_and_decode_page_content(page_id)
+            .unwrap()
+            .operations
+            .into_iter()
+            .map(|operation| operation.operator)
+            .collect();
+        assert_eq!(operators, ["re", "f"]);
+        assert!(document.get_dictionary(layer_id).unwrap().type_is(b"OCG"));
+        assert_eq!(hidden_layer_ids(&document), BTreeSet::from([layer_id]));
+    }
+
+    #[test]
+    fn script_open_action_is_removed() {
+        let (mut document, _, _) = document_with_hidden_layer();
+
+        let report = sanitize(&mut document);
+
+        assert_eq!(report.actions, 1);
+        assert!(!document.catalog().unwrap().has(b"OpenAction"));
+    }
+}
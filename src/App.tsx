@@ -9,6 +9,58 @@ interface CompressionSettings {
   outputPath: string;
 }
 
+interface CompressionProgress {
+  input_path: string;
+  unit: "page" | "object";
+  current: number;
+  total: number;
+  elapsed_ms: number;
+  eta_ms: number | null;
+}
+
+interface CompressionResult {
+  success: boolean;
+  status: "completed" | "no_gain" | "failed" | "cancelled";
+  message: string;
+  engine: string;
+  original_size: number;
+  compressed_size: number | null;
+  ratio: number | null;
+  kept_original: boolean;
+  duration_ms: number;
+  page_count: number | null;
+  warnings: string[];
+  encryption: EncryptionInfo | null;
+  output_encryption: EncryptionInfo | null;
+}
+
+interface EncryptionInfo {
+  method: "rc4" | "aes128" | "aes256";
+  key_length: number;
+  revision: number;
+  requires_password: boolean;
+  permissions: { print: boolean; modify: boolean; copy: boolean; annotate: boolean };
+  permission_flags: number;
+}
+
+interface AppError {
+  code: string;
+  message: string;
+  details: string | null;
+}
+
+// 后端命令返回的错误带有错误码、提示和具体信息
+function isAppError(error: unknown): error is AppError {
+  return typeof error === "object" && error !== null && "code" in error && "message" in error;
+}
+
+function formatError(error: unknown): string {
+  if (isAppError(error)) {
+    return error.details ? `${error.message}: ${error.details}` : error.message;
+  }
+  return String(error);
+}
+
 interface GhostscriptStatus {
   is_installed: boolean;
   is_downloading: boolean;
@@ -23,6 +75,7 @@ function App() {
   });
   const [status, setStatus] = useState<string>("");
   const [isCompressing, setIsCompressing] = useState(false);
+  const [jobId, setJobId] = useState<string | null>(null);
   const [theme, setTheme] = useState<string>("light");
   const [gsStatus, setGsStatus] = useState<GhostscriptStatus>({
     is_installed: false,
@@ -36,6 +89,14 @@ function App() {
     setTheme(savedTheme);
     document.documentElement.setAttribute('data-theme', savedTheme);
   }, []);
+
+  // 后端消息的语言：优先使用保存的设置，否则跟随系统语言
+  useEffect(() => {
+    const locale = localStorage.getItem('locale') || navigator.language;
+    invoke("set_locale", { locale }).catch((error) => {
+      console.error("设置语言失败:", error);
+    });
+  }, []);
   
   // 检查 Ghostscript 状态
   async function checkGhostscriptStatus() {
@@ -56,12 +117,11 @@ function App() {
     } catch (error) {
       console.error("下载 Ghostscript 失败:", error);
       
-      // 检查是否是平台不支持的错误
-      const errorMessage = String(error);
-      if (errorMessage.includes("brew install") || errorMessage.includes("包管理器")) {
-        setStatus(`${error}`);
+      // 平台不支持自动下载时直接显示安装提示
+      if (isAppError(error) && error.code === "unsupported_platform") {
+        setStatus(error.details ?? error.message);
       } else {
-        setStatus(`下载 Ghostscript 失败: ${error}`);
+        setStatus(formatError(error));
       }
     }
   }
@@ -83,7 +143,7 @@ function App() {
       }
     } catch (error) {
       console.error("卸载 Ghostscript 失败:", error);
-      setStatus(`卸载 Ghostscript 失败: ${error}`);
+      setStatus(formatError(error));
     }
   }
   
@@ -114,15 +174,23 @@ function App() {
       setStatus("Ghostscript 安装成功！现在可以使用高级压缩功能。");
     });
     
-    const unlisten3 = listen('ghostscript-install-failed', (event) => {
+    const unlisten3 = listen<AppError>('ghostscript-install-failed', (event) => {
       setGsStatus(prev => ({...prev, is_downloading: false}));
-      setStatus(`Ghostscript 安装失败: ${event.payload}`);
+      setStatus(formatError(event.payload));
+    });
+    
+    const unlisten4 = listen<CompressionProgress>('compression-progress', (event) => {
+      const { unit, current, total, eta_ms } = event.payload;
+      const item = unit === "page" ? "页" : "个图像";
+      const eta = eta_ms !== null ? `，剩余约 ${Math.ceil(eta_ms / 1000)} 秒` : "";
+      setStatus(`正在压缩中... ${current}/${total} ${item}${eta}`);
     });
     
     return () => {
       unlisten1.then(fn => fn());
       unlisten2.then(fn => fn());
       unlisten3.then(fn => fn());
+      unlisten4.then(fn => fn());
     };
   }, []);
 
@@ -158,6 +226,12 @@ function App() {
       description: "300+ DPI，JPEG质量90%+，专业印刷标准",
       details: "适用于商业印刷、出版物制作等专业用途"
     },
+    { 
+      value: "/archive", 
+      label: "Archive 长期归档", 
+      description: "300 DPI，JPEG质量85%，输出 PDF/A-2b",
+      details: "适用于档案管理系统，需要 Ghostscript 引擎"
+    },
   ];
 
   async function selectInputFile() {
@@ -180,7 +254,7 @@ function App() {
       }
     } catch (error) {
       console.error("选择文件时出错:", error);
-      setStatus(`选择文件失败: ${error}`);
+      setStatus(`选择文件失败: ${formatError(error)}`);
     }
   }
 
@@ -206,11 +280,11 @@ function App() {
       }
     } catch (error) {
       console.error("选择输出路径时出错:", error);
-      setStatus(`选择保存位置失败: ${error}`);
+      setStatus(`选择保存位置失败: ${formatError(error)}`);
     }
   }
 
-  async function startCompression() {
+  async function startCompression(password?: string) {
     if (!settings.inputPath) {
       setStatus("请先选择输入文件");
       return;
@@ -221,23 +295,48 @@ function App() {
       return;
     }
 
+    const currentJobId = `job-${Date.now()}`;
+    setJobId(currentJobId);
     setIsCompressing(true);
     setStatus("正在压缩中...");
 
     try {
-      const result = await invoke("compress_pdf", {
+      const result = await invoke<CompressionResult>("compress_pdf", {
         inputPath: settings.inputPath,
         outputPath: settings.outputPath,
         compressionLevel: settings.level,
+        jobId: currentJobId,
+        options: password ? { password, reencrypt: true } : null,
       });
       
       console.log("压缩结果:", result);
-      setStatus("压缩完成！");
+      const warnings = result.warnings.length > 0 ? `（${result.warnings.length} 条警告）` : "";
+      setStatus(`${result.message}${warnings}`);
     } catch (error) {
       console.error("压缩失败:", error);
-      setStatus(`压缩失败: ${error}`);
+      setStatus(formatError(error));
+
+      // 加密的文件需要密码，输入后重新压缩并保留原来的加密
+      if (isAppError(error) && (error.code === "encrypted_input" || error.code === "invalid_password")) {
+        const entered = window.prompt(`${error.message}，请输入密码`);
+        if (entered) {
+          await startCompression(entered);
+        }
+      }
     } finally {
       setIsCompressing(false);
+      setJobId(null);
+    }
+  }
+
+  // 取消正在进行的压缩
+  async function cancelCompression() {
+    if (!jobId) return;
+    try {
+      await invoke("cancel_compression", { jobId });
+      setStatus("正在取消...");
+    } catch (error) {
+      console.error("取消失败:", error);
     }
   }
 
@@ -460,7 +559,7 @@ function App() {
                 <div className="pt-4">
                   <button
                     className="btn btn-primary w-full h-14 text-base font-medium"
-                    onClick={startCompression}
+                    onClick={() => startCompression()}
                     disabled={isCompressing || !settings.inputPath || !settings.outputPath}
                   >
                     {isCompressing ? (
@@ -477,6 +576,15 @@ function App() {
                       </>
                     )}
                   </button>
+                  {isCompressing && (
+                    <button
+                      className="btn btn-ghost w-full mt-2"
+                      onClick={cancelCompression}
+                      disabled={!jobId}
+                    >
+                      Cancel
+                    </button>
+                  )}
                 </div>
               </div>
             </div>